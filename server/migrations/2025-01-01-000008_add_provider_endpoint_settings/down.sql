@@ -0,0 +1,5 @@
+ALTER TABLE ai_models DROP COLUMN IF EXISTS extra_headers;
+ALTER TABLE ai_models DROP COLUMN IF EXISTS base_url;
+
+ALTER TABLE user_api_keys DROP COLUMN IF EXISTS extra_headers;
+ALTER TABLE user_api_keys DROP COLUMN IF EXISTS base_url;
@@ -0,0 +1,7 @@
+-- Endpoint settings for OpenAI-compatible servers (vLLM, LM Studio, llama.cpp, OpenRouter, Groq).
+-- A user key's settings take precedence over those of the ai_models row.
+ALTER TABLE user_api_keys ADD COLUMN base_url TEXT;
+ALTER TABLE user_api_keys ADD COLUMN extra_headers JSONB;
+
+ALTER TABLE ai_models ADD COLUMN base_url TEXT;
+ALTER TABLE ai_models ADD COLUMN extra_headers JSONB;
@@ -0,0 +1,3 @@
+-- Ordered fallback models ("provider:model_id" or a bare catalog model id) tried when
+-- the chat's own model fails with a transient error. NULL uses AI_FALLBACK_MODELS.
+ALTER TABLE chats ADD COLUMN fallback_models JSONB;
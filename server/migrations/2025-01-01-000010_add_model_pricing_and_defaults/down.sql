@@ -0,0 +1,3 @@
+ALTER TABLE ai_models DROP COLUMN IF EXISTS default_parameters;
+ALTER TABLE ai_models DROP COLUMN IF EXISTS output_cost_per_token;
+ALTER TABLE ai_models DROP COLUMN IF EXISTS input_cost_per_token;
@@ -0,0 +1,5 @@
+-- Separate input/output pricing (per token) and default request parameters such as
+-- temperature and max_tokens, declared in the model catalog file.
+ALTER TABLE ai_models ADD COLUMN input_cost_per_token NUMERIC;
+ALTER TABLE ai_models ADD COLUMN output_cost_per_token NUMERIC;
+ALTER TABLE ai_models ADD COLUMN default_parameters JSONB;
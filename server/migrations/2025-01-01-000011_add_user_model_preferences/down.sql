@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS idx_user_models_default;
+
+ALTER TABLE user_models DROP COLUMN IF EXISTS is_default;
+ALTER TABLE user_models DROP COLUMN IF EXISTS sort_order;
+ALTER TABLE user_models DROP COLUMN IF EXISTS is_favorite;
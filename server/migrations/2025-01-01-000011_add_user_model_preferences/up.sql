@@ -0,0 +1,7 @@
+-- Per-user model preferences. A model without a row is enabled for the user.
+ALTER TABLE user_models ADD COLUMN is_favorite BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE user_models ADD COLUMN sort_order INTEGER;
+ALTER TABLE user_models ADD COLUMN is_default BOOLEAN NOT NULL DEFAULT false;
+
+-- At most one default model per user, used by chats created without a model
+CREATE UNIQUE INDEX idx_user_models_default ON user_models(user_id) WHERE is_default;
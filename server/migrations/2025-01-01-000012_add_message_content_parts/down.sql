@@ -0,0 +1,4 @@
+DELETE FROM messages WHERE role = 'tool';
+
+ALTER TABLE messages DROP COLUMN IF EXISTS tool_call_id;
+ALTER TABLE messages DROP COLUMN IF EXISTS content_parts;
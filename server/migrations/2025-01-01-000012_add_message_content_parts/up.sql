@@ -0,0 +1,6 @@
+-- Structured message content: `content` keeps the text (or a tool result) and
+-- `content_parts` holds everything else in order: reasoning, images, files and tool
+-- calls. `tool` messages record which call they answer, so the exchange can be replayed
+-- to the model.
+ALTER TABLE messages ADD COLUMN tool_call_id TEXT;
+ALTER TABLE messages ADD COLUMN content_parts JSONB;
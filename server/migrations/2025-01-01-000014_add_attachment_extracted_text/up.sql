@@ -0,0 +1 @@
+ALTER TABLE attachments ADD COLUMN extracted_text TEXT;
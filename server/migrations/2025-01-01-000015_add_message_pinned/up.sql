@@ -0,0 +1,2 @@
+-- Pinned messages are always sent to the model, however long the chat grows.
+ALTER TABLE messages ADD COLUMN is_pinned BOOLEAN NOT NULL DEFAULT FALSE;
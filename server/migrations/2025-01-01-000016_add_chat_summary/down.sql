@@ -0,0 +1,2 @@
+ALTER TABLE chats DROP COLUMN IF EXISTS summarized_through;
+ALTER TABLE chats DROP COLUMN IF EXISTS summary;
@@ -0,0 +1,4 @@
+-- Rolling summary of a long chat: `summary` stands in for every message up to and
+-- including `summarized_through` (a sequence number) when the chat is sent to a model.
+ALTER TABLE chats ADD COLUMN summary TEXT;
+ALTER TABLE chats ADD COLUMN summarized_through INTEGER;
@@ -0,0 +1,7 @@
+ALTER TABLE ai_models DROP COLUMN IF EXISTS cached_input_cost_per_token;
+
+ALTER TABLE messages DROP COLUMN IF EXISTS cost;
+ALTER TABLE messages DROP COLUMN IF EXISTS reasoning_tokens;
+ALTER TABLE messages DROP COLUMN IF EXISTS cached_tokens;
+ALTER TABLE messages DROP COLUMN IF EXISTS completion_tokens;
+ALTER TABLE messages DROP COLUMN IF EXISTS prompt_tokens;
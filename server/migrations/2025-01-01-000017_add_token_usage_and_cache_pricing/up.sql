@@ -0,0 +1,12 @@
+-- Token usage of an assistant message as the provider reported it. `tokens_used` stays
+-- the total; `cached_tokens` is part of `prompt_tokens` and `reasoning_tokens` part of
+-- `completion_tokens`. `cost` is priced from the answering model's `ai_models` row.
+ALTER TABLE messages ADD COLUMN prompt_tokens INTEGER;
+ALTER TABLE messages ADD COLUMN completion_tokens INTEGER;
+ALTER TABLE messages ADD COLUMN cached_tokens INTEGER;
+ALTER TABLE messages ADD COLUMN reasoning_tokens INTEGER;
+ALTER TABLE messages ADD COLUMN cost NUMERIC;
+
+-- Providers bill prompt tokens read from their cache at a discount; unset, they are
+-- priced like other prompt tokens.
+ALTER TABLE ai_models ADD COLUMN cached_input_cost_per_token NUMERIC;
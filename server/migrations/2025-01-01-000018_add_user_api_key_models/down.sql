@@ -0,0 +1 @@
+ALTER TABLE user_api_keys DROP COLUMN IF EXISTS models;
@@ -0,0 +1,4 @@
+-- Models served through a user's key, as a JSON array of model ids. Filled in by the
+-- user or discovered from the key's endpoint, so self-hosted models stay off the
+-- shared ai_models catalog.
+ALTER TABLE user_api_keys ADD COLUMN models JSONB;
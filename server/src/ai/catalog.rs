@@ -0,0 +1,286 @@
+use crate::ai::manager::{ProviderConfig, ProviderManager, ProviderWrapper};
+use crate::db::models::{AiProvider, CreateAiModelDto};
+use crate::db::repositories::TAiModelRepository;
+use crate::env;
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Hosted providers whose models can be synced with a server-wide API key.
+const HOSTED_PROVIDERS: [AiProvider; 4] = [
+    AiProvider::OpenAI,
+    AiProvider::Anthropic,
+    AiProvider::Google,
+    AiProvider::DeepSeek,
+];
+
+/// Outcome of syncing one provider's models into `ai_models`.
+#[derive(Debug, Clone)]
+pub struct SyncReport {
+    pub provider: AiProvider,
+    /// Models the provider listed, inserted or refreshed
+    pub synced: usize,
+    /// Active rows the provider no longer lists, now marked inactive
+    pub deactivated: usize,
+}
+
+/// Registers every model `provider` currently lists in `ai_models` and deactivates the
+/// provider's rows that are no longer listed. Models already in the catalog keep their
+/// name, context window and prices, since the declared catalog file is more accurate
+/// than most listings, but gain any capability the listing reports. Nothing is
+/// deactivated if listing fails.
+pub async fn sync_provider(
+    repository: &impl TAiModelRepository,
+    provider: &ProviderWrapper,
+) -> Result<SyncReport> {
+    let kind = provider.provider();
+    let models = provider.discover_models().await?;
+
+    for model in &models {
+        repository
+            .insert_or_reactivate(CreateAiModelDto {
+                provider: kind,
+                model_id: model.id.clone(),
+                display_name: model.display_name.clone(),
+                description: None,
+                context_window: model.context_window.min(i32::MAX as u32) as i32,
+                supports_streaming: model.supports_streaming,
+                supports_images: model.supports_images,
+                supports_functions: model.supports_functions,
+                cost_per_token: None,
+                is_active: true,
+                base_url: None,
+                extra_headers: None,
+                input_cost_per_token: None,
+                output_cost_per_token: None,
+                default_parameters: None,
+                cached_input_cost_per_token: None,
+            })
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to register {} model {}: {:?}",
+                    kind.as_str(),
+                    model.id,
+                    e
+                )
+            })?;
+    }
+
+    // An empty listing is more likely a misconfigured account than a retired catalog
+    let deactivated = if models.is_empty() {
+        0
+    } else {
+        let model_ids: Vec<String> = models.iter().map(|m| m.id.clone()).collect();
+        repository
+            .deactivate_missing(&kind, &model_ids)
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to deactivate retired {} models: {:?}",
+                    kind.as_str(),
+                    e
+                )
+            })?
+    };
+
+    Ok(SyncReport {
+        provider: kind,
+        synced: models.len(),
+        deactivated,
+    })
+}
+
+/// Providers the server itself can reach: hosted ones with `<PROVIDER>_API_KEY` set
+/// and Ollama when `OLLAMA_BASE_URL` is set.
+pub fn configured_providers() -> Result<ProviderManager> {
+    let mut configs = HashMap::new();
+
+    for provider in HOSTED_PROVIDERS {
+        if let Some(api_key) = env::get_ai_provider_api_key(provider.as_str()) {
+            configs.insert(
+                provider,
+                ProviderConfig {
+                    api_key: Some(api_key),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    if let Some(base_url) = env::get_ollama_base_url() {
+        configs.insert(
+            AiProvider::Ollama,
+            ProviderConfig {
+                base_url: Some(base_url),
+                ..Default::default()
+            },
+        );
+    }
+
+    ProviderManager::new(configs)
+}
+
+/// Syncs every provider from `configured_providers`. A provider that fails is logged
+/// and skipped so the others still sync; the reports of those that succeeded are returned.
+pub async fn sync_configured(repository: &impl TAiModelRepository) -> Result<Vec<SyncReport>> {
+    let manager = configured_providers()?;
+    let mut reports = Vec::new();
+
+    for provider in manager.providers() {
+        match sync_provider(repository, provider).await {
+            Ok(report) => {
+                tracing::info!(
+                    "Synced {} {} model(s), deactivated {}",
+                    report.synced,
+                    report.provider.as_str(),
+                    report.deactivated
+                );
+                reports.push(report);
+            }
+            Err(e) => tracing::warn!(
+                "Failed to sync {} models: {}",
+                provider.provider().as_str(),
+                e
+            ),
+        }
+    }
+
+    Ok(reports)
+}
+
+/// The versioned catalog file, e.g. `models.json`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CatalogFile {
+    models: Vec<CatalogEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CatalogEntry {
+    provider: String,
+    model_id: String,
+    display_name: String,
+    description: Option<String>,
+    context_window: u32,
+    #[serde(default)]
+    capabilities: CatalogCapabilities,
+    pricing: Option<CatalogPricing>,
+    /// Request defaults such as `temperature` and `max_tokens`
+    default_parameters: Option<serde_json::Map<String, serde_json::Value>>,
+    base_url: Option<String>,
+    extra_headers: Option<HashMap<String, String>>,
+    /// `false` adds the model without offering it until an admin activates it
+    #[serde(default = "default_true")]
+    is_active: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CatalogCapabilities {
+    #[serde(default = "default_true")]
+    streaming: bool,
+    #[serde(default)]
+    images: bool,
+    #[serde(default)]
+    functions: bool,
+}
+
+impl Default for CatalogCapabilities {
+    fn default() -> Self {
+        Self {
+            streaming: true,
+            images: false,
+            functions: false,
+        }
+    }
+}
+
+/// Prices in USD per million tokens, the unit providers publish them in.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CatalogPricing {
+    input_per_million: Decimal,
+    output_per_million: Decimal,
+    /// Prompt tokens read from the provider's cache; `input_per_million` when unset
+    cached_input_per_million: Option<Decimal>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl CatalogEntry {
+    fn into_dto(self) -> Result<CreateAiModelDto> {
+        let provider = AiProvider::from_str(&self.provider).with_context(|| {
+            format!(
+                "Unknown provider '{}' for model {}",
+                self.provider, self.model_id
+            )
+        })?;
+        let per_token = |per_million: Decimal| per_million / Decimal::from(1_000_000);
+
+        Ok(CreateAiModelDto {
+            provider,
+            model_id: self.model_id,
+            display_name: self.display_name,
+            description: self.description,
+            context_window: self.context_window.min(i32::MAX as u32) as i32,
+            supports_streaming: self.capabilities.streaming,
+            supports_images: self.capabilities.images,
+            supports_functions: self.capabilities.functions,
+            cost_per_token: None,
+            is_active: self.is_active,
+            base_url: self.base_url,
+            extra_headers: self.extra_headers.map(|headers| serde_json::json!(headers)),
+            input_cost_per_token: self
+                .pricing
+                .as_ref()
+                .map(|p| per_token(p.input_per_million)),
+            output_cost_per_token: self
+                .pricing
+                .as_ref()
+                .map(|p| per_token(p.output_per_million)),
+            cached_input_cost_per_token: self
+                .pricing
+                .and_then(|p| p.cached_input_per_million)
+                .map(per_token),
+            default_parameters: self.default_parameters.map(serde_json::Value::Object),
+        })
+    }
+}
+
+/// Seeds `ai_models` from the catalog file at `path`: declared models that have no row
+/// yet are inserted. Existing rows are left alone, so a restart never undoes an admin's
+/// changes or reactivates a model that was turned off. Returns the number of models
+/// inserted.
+pub async fn seed_from_file(repository: &impl TAiModelRepository, path: &Path) -> Result<usize> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read model catalog {}", path.display()))?;
+    let file: CatalogFile = serde_json::from_str(&contents)
+        .with_context(|| format!("Invalid model catalog {}", path.display()))?;
+
+    // Validate the whole file before touching the database
+    let models = file
+        .models
+        .into_iter()
+        .map(CatalogEntry::into_dto)
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut inserted = 0;
+    for model in models {
+        let model_id = model.model_id.clone();
+        if repository
+            .insert_if_missing(model)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to seed model {}: {:?}", model_id, e))?
+        {
+            inserted += 1;
+        }
+    }
+
+    Ok(inserted)
+}
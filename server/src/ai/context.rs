@@ -0,0 +1,161 @@
+//! Fits a conversation into a model's context window.
+//!
+//! The prompt may take up the context window minus the tokens reserved for the reply.
+//! When the history does not fit, the oldest turns are left out first. System and
+//! pinned messages are always kept, as is the latest turn, which is what the model is
+//! asked to answer.
+
+use crate::ai::tokens::{REPLY_PRIMING_TOKENS, TokenCounter};
+use crate::ai::types::{ChatMessage, ToolDefinition};
+use crate::db::models::MessageRole;
+
+/// Reserved for the reply when the request does not set `max_tokens`; the limit the
+/// Anthropic provider sends in that case.
+const DEFAULT_COMPLETION_TOKENS: u32 = 1024;
+
+pub struct ContextBuilder {
+    counter: TokenCounter,
+    /// Tokens the prompt may take; `None` when the model's context window is unknown.
+    budget: Option<u32>,
+}
+
+/// The messages to send and what they cost.
+#[derive(Debug)]
+pub struct FittedContext {
+    pub messages: Vec<ChatMessage>,
+    /// Tokens of `messages` and the tool definitions.
+    pub prompt_tokens: u32,
+    /// Tokens the whole history would have taken.
+    pub history_tokens: u32,
+    pub budget: Option<u32>,
+    pub dropped_messages: usize,
+}
+
+impl FittedContext {
+    /// False when the messages that must be kept do not fit on their own.
+    pub fn fits(&self) -> bool {
+        self.budget
+            .is_none_or(|budget| self.prompt_tokens <= budget)
+    }
+}
+
+impl ContextBuilder {
+    pub fn new(
+        counter: TokenCounter,
+        context_window: Option<u32>,
+        max_tokens: Option<u32>,
+    ) -> Self {
+        let reserved = max_tokens.unwrap_or(DEFAULT_COMPLETION_TOKENS);
+        Self {
+            counter,
+            budget: context_window.map(|window| window.saturating_sub(reserved)),
+        }
+    }
+
+    pub fn budget(&self) -> Option<u32> {
+        self.budget
+    }
+
+    /// Leaves out the oldest turns until `messages` and `tools` fit the budget.
+    /// `pinned` flags the messages that must stay, by index.
+    pub fn fit(
+        &self,
+        messages: Vec<ChatMessage>,
+        pinned: &[bool],
+        tools: &[ToolDefinition],
+    ) -> FittedContext {
+        let selection = self.select(&messages, pinned, tools, self.budget.unwrap_or(u32::MAX));
+        let mut kept = vec![true; messages.len()];
+        for index in &selection.left_out {
+            kept[*index] = false;
+        }
+        let messages = messages
+            .into_iter()
+            .zip(kept)
+            .filter_map(|(message, kept)| kept.then_some(message))
+            .collect();
+
+        FittedContext {
+            messages,
+            prompt_tokens: selection.prompt_tokens,
+            history_tokens: selection.history_tokens,
+            budget: self.budget,
+            dropped_messages: selection.left_out.len(),
+        }
+    }
+
+    /// Picks the oldest turns to leave out so that the rest of `messages` and `tools`
+    /// take at most `budget` tokens. A tool result goes or stays with the assistant turn
+    /// that made the call.
+    pub fn select(
+        &self,
+        messages: &[ChatMessage],
+        pinned: &[bool],
+        tools: &[ToolDefinition],
+        budget: u32,
+    ) -> Selection {
+        let tokens: Vec<u32> = messages
+            .iter()
+            .map(|message| self.counter.count_message(message))
+            .collect();
+        let history_tokens =
+            self.counter.count_tools(tools) + REPLY_PRIMING_TOKENS + tokens.iter().sum::<u32>();
+        let mut selection = Selection {
+            left_out: Vec::new(),
+            prompt_tokens: history_tokens,
+            history_tokens,
+        };
+        if history_tokens <= budget {
+            return selection;
+        }
+
+        // Turns are the unit of truncation: a message and the tool results after it
+        let mut turns: Vec<Turn> = Vec::new();
+        for (index, message) in messages.iter().enumerate() {
+            let keep = message.role == MessageRole::System || pinned.get(index) == Some(&true);
+            match turns.last_mut() {
+                Some(turn) if message.role == MessageRole::Tool => {
+                    turn.end = index + 1;
+                    turn.tokens += tokens[index];
+                    turn.keep |= keep;
+                }
+                _ => turns.push(Turn {
+                    start: index,
+                    end: index + 1,
+                    tokens: tokens[index],
+                    keep,
+                }),
+            }
+        }
+        if let Some(latest) = turns.last_mut() {
+            latest.keep = true;
+        }
+
+        for turn in turns.iter().filter(|turn| !turn.keep) {
+            if selection.prompt_tokens <= budget {
+                break;
+            }
+            selection.prompt_tokens -= turn.tokens;
+            selection.left_out.extend(turn.start..turn.end);
+        }
+
+        selection
+    }
+}
+
+/// The outcome of `ContextBuilder::select`.
+pub struct Selection {
+    /// Indices of the messages left out, in order.
+    pub left_out: Vec<usize>,
+    /// Tokens of the messages kept and the tool definitions.
+    pub prompt_tokens: u32,
+    /// Tokens of all the messages and the tool definitions.
+    pub history_tokens: u32,
+}
+
+struct Turn {
+    start: usize,
+    end: usize,
+    tokens: u32,
+    keep: bool,
+}
@@ -0,0 +1,111 @@
+//! Checks on the provider endpoints users store with their keys. The server sends
+//! requests to these URLs and passes the responses back, so an unchecked URL would let
+//! any user reach hosts on the server's own network: loopback services, the cloud
+//! metadata endpoint and private ranges.
+
+use crate::env;
+use anyhow::{Result, bail};
+use reqwest::header::{HeaderName, HeaderValue};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use url::{Host, Url};
+
+/// Headers that would change where or how a request is sent rather than what it says.
+const RESERVED_HEADERS: [&str; 5] = [
+    "host",
+    "content-length",
+    "transfer-encoding",
+    "connection",
+    "upgrade",
+];
+
+/// Checks that `base_url` is an http(s) URL on a public host, or on one an admin allowed
+/// with `AI_ALLOWED_BASE_URL_HOSTS`. Domains are resolved and every address they resolve
+/// to must be public.
+pub async fn check_base_url(base_url: &str) -> Result<()> {
+    let url = Url::parse(base_url).map_err(|e| anyhow::anyhow!("Invalid base URL: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("The base URL must use http or https");
+    }
+    let Some(host) = url.host() else {
+        bail!("The base URL has no host");
+    };
+    let host_name = url.host_str().unwrap_or_default();
+    if env::is_allowed_base_url_host(host_name) {
+        return Ok(());
+    }
+
+    let addresses: Vec<IpAddr> = match host {
+        Host::Ipv4(ip) => vec![IpAddr::V4(ip)],
+        Host::Ipv6(ip) => vec![IpAddr::V6(ip)],
+        Host::Domain(domain) => {
+            if domain == "localhost" || domain.ends_with(".localhost") {
+                bail!("{} is not reachable from the server", domain);
+            }
+            let port = url.port_or_known_default().unwrap_or(443);
+            tokio::net::lookup_host((domain, port))
+                .await
+                .map_err(|e| anyhow::anyhow!("Could not resolve {}: {}", domain, e))?
+                .map(|address| address.ip())
+                .collect()
+        }
+    };
+    if addresses.is_empty() || addresses.into_iter().any(is_internal) {
+        bail!(
+            "{} is on a private network; an administrator has to allow it",
+            host_name
+        );
+    }
+
+    Ok(())
+}
+
+/// Checks that `headers` are valid HTTP headers and do not override how the request
+/// is framed or routed.
+pub fn check_extra_headers(headers: &HashMap<String, String>) -> Result<()> {
+    for (name, value) in headers {
+        let header = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Invalid header name '{}'", name))?;
+        if RESERVED_HEADERS.contains(&header.as_str()) {
+            bail!("The {} header cannot be set", name);
+        }
+        HeaderValue::from_str(value)
+            .map_err(|_| anyhow::anyhow!("Invalid value for header '{}'", name))?;
+    }
+
+    Ok(())
+}
+
+/// Loopback, private, link-local, carrier-grade NAT and unspecified addresses, and
+/// IPv4 addresses mapped into IPv6.
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_internal_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_internal_v4(ip),
+            None => is_internal_v6(ip),
+        },
+    }
+}
+
+fn is_internal_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        // "This network", 0.0.0.0/8
+        || a == 0
+        // Carrier-grade NAT, 100.64.0.0/10
+        || (a == 100 && (b & 0xc0) == 64)
+}
+
+fn is_internal_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // Unique local, fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // Link-local, fe80::/10
+        || (first & 0xffc0) == 0xfe80
+}
@@ -0,0 +1,146 @@
+use reqwest::StatusCode;
+use std::fmt;
+use std::time::Duration;
+
+pub type ProviderResult<T> = std::result::Result<T, ProviderError>;
+
+/// Why a call to an AI provider failed. Each provider parses its own error payload
+/// into one of these so callers can react (retry, fall back, tell the user to fix
+/// their key) without matching on provider-specific strings.
+#[derive(Debug)]
+pub enum ProviderError {
+    /// The API key is missing, malformed, revoked or lacks access.
+    InvalidApiKey(String),
+    /// Too many requests; `retry_after` is the delay the provider asked for, if any.
+    RateLimited {
+        retry_after: Option<Duration>,
+        message: String,
+    },
+    /// The prompt plus the requested completion does not fit the model's context window.
+    ContextLengthExceeded(String),
+    /// The prompt or the completion was blocked by the provider's safety filters.
+    ContentFiltered(String),
+    ModelNotFound(String),
+    /// Any other request the provider refused (4xx).
+    InvalidRequest(String),
+    /// The provider failed or is overloaded (5xx).
+    Upstream {
+        status: u16,
+        message: String,
+    },
+    Timeout,
+    /// Network failures and responses we could not understand.
+    Other(anyhow::Error),
+}
+
+impl ProviderError {
+    /// Classifies a failed response by its status code alone. Providers use this as
+    /// the fallback when their error payload carries no more specific code.
+    pub fn from_status(status: StatusCode, retry_after: Option<Duration>, message: String) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::InvalidApiKey(message),
+            StatusCode::TOO_MANY_REQUESTS => Self::RateLimited {
+                retry_after,
+                message,
+            },
+            StatusCode::NOT_FOUND => Self::ModelNotFound(message),
+            StatusCode::PAYLOAD_TOO_LARGE => Self::ContextLengthExceeded(message),
+            StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => Self::Timeout,
+            status if status.is_server_error() => Self::Upstream {
+                status: status.as_u16(),
+                message,
+            },
+            _ => Self::InvalidRequest(message),
+        }
+    }
+
+    /// A stream that closed before the provider marked the end of the answer, so what
+    /// arrived is only part of it.
+    pub fn stream_cut_short() -> Self {
+        Self::Upstream {
+            status: StatusCode::BAD_GATEWAY.as_u16(),
+            message: "The stream ended before the answer was complete".to_string(),
+        }
+    }
+
+    /// Stable identifier sent to API clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidApiKey(_) => "invalid_api_key",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::ContextLengthExceeded(_) => "context_length_exceeded",
+            Self::ContentFiltered(_) => "content_filtered",
+            Self::ModelNotFound(_) => "model_not_found",
+            Self::InvalidRequest(_) => "invalid_request",
+            Self::Upstream { .. } => "upstream_error",
+            Self::Timeout => "timeout",
+            Self::Other(_) => "provider_error",
+        }
+    }
+
+    /// Failures that may succeed if the same request is sent again.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::RateLimited { .. } | Self::Upstream { .. } | Self::Timeout
+        )
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidApiKey(message) => write!(f, "Invalid API key: {}", message),
+            Self::RateLimited { message, .. } => write!(f, "Rate limited: {}", message),
+            Self::ContextLengthExceeded(message) => {
+                write!(f, "Context length exceeded: {}", message)
+            }
+            Self::ContentFiltered(message) => write!(f, "Content filtered: {}", message),
+            Self::ModelNotFound(message) => write!(f, "Model not found: {}", message),
+            Self::InvalidRequest(message) => write!(f, "Invalid request: {}", message),
+            Self::Upstream { status, message } => {
+                write!(f, "Provider error ({}): {}", status, message)
+            }
+            Self::Timeout => write!(f, "The provider did not respond in time"),
+            Self::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+impl From<reqwest::Error> for ProviderError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            return Self::Timeout;
+        }
+        match e.status() {
+            Some(status) => Self::from_status(status, None, e.to_string()),
+            None => Self::Other(e.into()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ProviderError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Other(anyhow::anyhow!("Unexpected response from provider: {}", e))
+    }
+}
+
+/// Reads `Retry-After` when it is given in seconds, the form every provider we
+/// support uses.
+pub fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+}
@@ -0,0 +1,85 @@
+//! Fallback chains: the models tried, in order, when a chat's own model fails with a
+//! transient error. A chain entry is either `provider:model_id` (`anthropic:claude-3-opus`)
+//! or a bare model id resolved through the active `ai_models` catalog (`gpt-4`).
+
+use crate::db::models::{AiModelModel, AiProvider};
+use crate::env::get_ai_fallback_models;
+use std::fmt;
+
+/// One link of a fallback chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelTarget {
+    pub provider: AiProvider,
+    pub model_id: String,
+}
+
+impl ModelTarget {
+    pub fn new(provider: AiProvider, model_id: impl Into<String>) -> Self {
+        Self {
+            provider,
+            model_id: model_id.into(),
+        }
+    }
+
+    /// Resolves a chain entry. Ollama tags contain a colon too (`llama3:8b`), so a
+    /// prefix that is not a provider name is treated as part of a bare model id.
+    pub fn resolve(entry: &str, catalog: &[AiModelModel]) -> Option<Self> {
+        let entry = entry.trim();
+        if let Some((provider, model_id)) = entry.split_once(':') {
+            if let Some(provider) = AiProvider::from_str(provider.trim()) {
+                return Some(Self::new(provider, model_id.trim()));
+            }
+        }
+
+        catalog
+            .iter()
+            .find(|m| m.model_id == entry)
+            .map(|m| Self::new(m.provider, entry))
+    }
+}
+
+impl fmt::Display for ModelTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.provider.as_str(), self.model_id)
+    }
+}
+
+/// Splits a chain written as `a -> b -> c` or `a, b, c`.
+pub fn parse_entries(chain: &str) -> Vec<String> {
+    chain
+        .split(',')
+        .flat_map(|part| part.split("->"))
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Resolves the models to try after `primary`: the chat's own fallback entries, or
+/// `AI_FALLBACK_MODELS` when the chat has none. Unknown entries, repeats and the
+/// primary model itself are dropped.
+pub fn fallbacks_for(
+    primary: &ModelTarget,
+    chat_fallbacks: Option<&serde_json::Value>,
+    catalog: &[AiModelModel],
+) -> Vec<ModelTarget> {
+    let entries: Vec<String> = match chat_fallbacks {
+        Some(value) => serde_json::from_value(value.clone()).unwrap_or_default(),
+        None => get_ai_fallback_models()
+            .map(|chain| parse_entries(&chain))
+            .unwrap_or_default(),
+    };
+
+    let mut fallbacks: Vec<ModelTarget> = Vec::new();
+    for entry in entries {
+        match ModelTarget::resolve(&entry, catalog) {
+            Some(target) if &target != primary && !fallbacks.contains(&target) => {
+                fallbacks.push(target)
+            }
+            Some(_) => {}
+            None => tracing::warn!("Ignoring unknown fallback model '{}'", entry),
+        }
+    }
+
+    fallbacks
+}
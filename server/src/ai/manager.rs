@@ -1,101 +1,274 @@
+use crate::ai::error::{ProviderError, ProviderResult};
+use crate::ai::fallback::ModelTarget;
 use crate::ai::providers::{
-    AIProvider, anthropic::AnthropicProvider, google::GoogleProvider, openai::OpenAIProvider,
+    AIProvider, ImageProvider, anthropic::AnthropicProvider, deepseek::DeepSeekProvider,
+    google::GoogleProvider, ollama::OllamaProvider, openai::OpenAIProvider,
+    openai_compatible::OpenAICompatibleProvider,
+};
+use crate::ai::retry::RetryPolicy;
+use crate::ai::types::{
+    ChatRequest, ChatResponse, ChatStream, ImageGenerationRequest, ImageGenerationResponse,
 };
 use crate::db::models::AiProvider;
 use anyhow::Result;
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
 
 pub enum ProviderWrapper {
     OpenAI(OpenAIProvider),
     Anthropic(AnthropicProvider),
     Google(GoogleProvider),
+    DeepSeek(DeepSeekProvider),
+    Ollama(OllamaProvider),
+    OpenAICompatible(OpenAICompatibleProvider),
 }
 
 impl ProviderWrapper {
+    pub fn provider(&self) -> AiProvider {
+        match self {
+            ProviderWrapper::OpenAI(_) => AiProvider::OpenAI,
+            ProviderWrapper::Anthropic(_) => AiProvider::Anthropic,
+            ProviderWrapper::Google(_) => AiProvider::Google,
+            ProviderWrapper::DeepSeek(_) => AiProvider::DeepSeek,
+            ProviderWrapper::Ollama(_) => AiProvider::Ollama,
+            ProviderWrapper::OpenAICompatible(_) => AiProvider::OpenAICompatible,
+        }
+    }
+
+    /// Sends a chat request, retrying transient failures per the provider's `RetryPolicy`.
     pub async fn chat(
         &self,
         request: crate::ai::types::ChatRequest,
-    ) -> anyhow::Result<crate::ai::types::ChatResponse> {
+    ) -> ProviderResult<crate::ai::types::ChatResponse> {
+        RetryPolicy::for_provider(self.provider())
+            .run(|| self.chat_once(request.clone()))
+            .await
+    }
+
+    /// Opens a stream, retrying transient failures. Only establishing the stream is
+    /// retried; errors after the first chunk are returned to the caller as-is.
+    pub async fn stream_chat(
+        &self,
+        request: crate::ai::types::ChatRequest,
+    ) -> ProviderResult<crate::ai::types::ChatStream> {
+        RetryPolicy::for_provider(self.provider())
+            .run(|| self.stream_chat_once(request.clone()))
+            .await
+    }
+
+    async fn chat_once(
+        &self,
+        request: crate::ai::types::ChatRequest,
+    ) -> ProviderResult<crate::ai::types::ChatResponse> {
         match self {
             ProviderWrapper::OpenAI(p) => p.chat(request).await,
             ProviderWrapper::Anthropic(p) => p.chat(request).await,
             ProviderWrapper::Google(p) => p.chat(request).await,
+            ProviderWrapper::DeepSeek(p) => p.chat(request).await,
+            ProviderWrapper::Ollama(p) => p.chat(request).await,
+            ProviderWrapper::OpenAICompatible(p) => p.chat(request).await,
         }
     }
 
-    pub async fn stream_chat(
+    async fn stream_chat_once(
         &self,
         request: crate::ai::types::ChatRequest,
-    ) -> anyhow::Result<
-        Box<
-            dyn futures::Stream<Item = anyhow::Result<crate::ai::types::ChatResponseChunk>>
-                + Send
-                + Unpin
-                + '_,
-        >,
-    > {
+    ) -> ProviderResult<crate::ai::types::ChatStream> {
         match self {
-            ProviderWrapper::OpenAI(p) => {
-                let stream = p.stream_chat(request).await?;
-                Ok(Box::new(stream))
-            }
-            ProviderWrapper::Anthropic(p) => {
-                let stream = p.stream_chat(request).await?;
-                Ok(Box::new(stream))
-            }
-            ProviderWrapper::Google(p) => {
-                let stream = p.stream_chat(request).await?;
-                Ok(Box::new(stream))
-            }
+            ProviderWrapper::OpenAI(p) => p.stream_chat(request).await,
+            ProviderWrapper::Anthropic(p) => p.stream_chat(request).await,
+            ProviderWrapper::Google(p) => p.stream_chat(request).await,
+            ProviderWrapper::DeepSeek(p) => p.stream_chat(request).await,
+            ProviderWrapper::Ollama(p) => p.stream_chat(request).await,
+            ProviderWrapper::OpenAICompatible(p) => p.stream_chat(request).await,
         }
     }
 
-    pub fn get_model_info(&self, model_id: &str) -> Option<crate::ai::types::ModelInfo> {
+    /// The provider's image API, for the providers that have one.
+    pub fn image_provider(&self) -> Option<&dyn ImageProvider> {
         match self {
-            ProviderWrapper::OpenAI(p) => p.get_model_info(model_id),
-            ProviderWrapper::Anthropic(p) => p.get_model_info(model_id),
-            ProviderWrapper::Google(p) => p.get_model_info(model_id),
+            ProviderWrapper::OpenAI(p) => Some(p),
+            ProviderWrapper::Google(p) => Some(p),
+            _ => None,
         }
     }
 
-    pub fn list_models(&self) -> Vec<crate::ai::types::ModelInfo> {
+    /// Generates images, retrying transient failures like chat requests.
+    pub async fn generate_images(
+        &self,
+        request: ImageGenerationRequest,
+    ) -> ProviderResult<ImageGenerationResponse> {
+        let Some(provider) = self.image_provider() else {
+            return Err(ProviderError::InvalidRequest(format!(
+                "{} does not generate images",
+                self.provider().display_name()
+            )));
+        };
+
+        RetryPolicy::for_provider(self.provider())
+            .run(|| provider.generate_images(request.clone()))
+            .await
+    }
+
+    pub async fn discover_models(&self) -> ProviderResult<Vec<crate::ai::types::ModelInfo>> {
         match self {
-            ProviderWrapper::OpenAI(p) => p.list_models(),
-            ProviderWrapper::Anthropic(p) => p.list_models(),
-            ProviderWrapper::Google(p) => p.list_models(),
+            ProviderWrapper::OpenAI(p) => p.discover_models().await,
+            ProviderWrapper::Anthropic(p) => p.discover_models().await,
+            ProviderWrapper::Google(p) => p.discover_models().await,
+            ProviderWrapper::DeepSeek(p) => p.discover_models().await,
+            ProviderWrapper::Ollama(p) => p.discover_models().await,
+            ProviderWrapper::OpenAICompatible(p) => p.discover_models().await,
         }
     }
 }
 
+/// Connection settings for a single provider. Hosted providers need an API key;
+/// self-hosted ones such as Ollama only need to know where to connect.
+#[derive(Debug, Clone, Default)]
+pub struct ProviderConfig {
+    pub api_key: Option<String>,
+    pub base_url: Option<String>,
+    pub extra_headers: HashMap<String, String>,
+}
+
+#[derive(Default)]
 pub struct ProviderManager {
     providers: HashMap<AiProvider, Arc<ProviderWrapper>>,
 }
 
+/// Result of walking a fallback chain: the value together with the model that
+/// produced it, or the last model's error.
+pub type ChainResult<T> = std::result::Result<(ModelTarget, T), (ModelTarget, ProviderError)>;
+
 impl ProviderManager {
-    pub fn new(api_keys: HashMap<AiProvider, String>) -> Result<Self> {
-        let mut providers = HashMap::new();
+    pub fn new(configs: HashMap<AiProvider, ProviderConfig>) -> Result<Self> {
+        let mut manager = Self {
+            providers: HashMap::new(),
+        };
 
-        for (provider, api_key) in api_keys {
-            let provider_impl = match provider {
-                AiProvider::OpenAI => {
-                    Arc::new(ProviderWrapper::OpenAI(OpenAIProvider::new(api_key)))
-                }
-                AiProvider::Anthropic => {
-                    Arc::new(ProviderWrapper::Anthropic(AnthropicProvider::new(api_key)))
-                }
-                AiProvider::Google => {
-                    Arc::new(ProviderWrapper::Google(GoogleProvider::new(api_key)))
-                }
-                _ => continue, // Skip unsupported providers for now
-            };
-            providers.insert(provider, provider_impl);
+        for (provider, config) in configs {
+            manager.insert(provider, config)?;
         }
 
-        Ok(Self { providers })
+        Ok(manager)
+    }
+
+    /// Configures one more provider, replacing any existing configuration for it.
+    pub fn insert(&mut self, provider: AiProvider, config: ProviderConfig) -> Result<()> {
+        if provider.requires_api_key() && config.api_key.is_none() {
+            anyhow::bail!("An API key is required for provider {}", provider.as_str());
+        }
+
+        let api_key = config.api_key.unwrap_or_default();
+        let provider_impl = match provider {
+            AiProvider::OpenAI => Arc::new(ProviderWrapper::OpenAI(OpenAIProvider::new(api_key))),
+            AiProvider::Anthropic => {
+                Arc::new(ProviderWrapper::Anthropic(AnthropicProvider::new(api_key)))
+            }
+            AiProvider::Google => Arc::new(ProviderWrapper::Google(GoogleProvider::new(api_key))),
+            AiProvider::DeepSeek => {
+                Arc::new(ProviderWrapper::DeepSeek(DeepSeekProvider::new(api_key)))
+            }
+            AiProvider::Ollama => Arc::new(ProviderWrapper::Ollama(OllamaProvider::new(
+                config.base_url.or_else(crate::env::get_ollama_base_url),
+                Some(api_key),
+            ))),
+            AiProvider::OpenAICompatible => {
+                let base_url = config.base_url.ok_or_else(|| {
+                    anyhow::anyhow!("A base URL is required for OpenAI-compatible providers")
+                })?;
+                Arc::new(ProviderWrapper::OpenAICompatible(
+                    OpenAICompatibleProvider::new(api_key, base_url, config.extra_headers),
+                ))
+            }
+        };
+        self.providers.insert(provider, provider_impl);
+
+        Ok(())
     }
 
     pub fn get_provider(&self, provider: &AiProvider) -> Option<Arc<ProviderWrapper>> {
         self.providers.get(provider).cloned()
     }
+
+    pub fn providers(&self) -> impl Iterator<Item = &Arc<ProviderWrapper>> {
+        self.providers.values()
+    }
+
+    /// Sends the request to `primary`, then to each of `fallbacks` in turn until one
+    /// answers. Only transient failures (after the provider's own retries) move on to
+    /// the next model; fallbacks whose provider is not configured are skipped.
+    pub async fn chat_with_fallback(
+        &self,
+        primary: &ModelTarget,
+        fallbacks: &[ModelTarget],
+        request: ChatRequest,
+    ) -> ChainResult<ChatResponse> {
+        self.walk_chain(
+            primary,
+            fallbacks,
+            request,
+            |provider, request| async move { provider.chat(request).await },
+        )
+        .await
+    }
+
+    /// Like `chat_with_fallback`, but only for opening the stream: once a stream is
+    /// returned its errors are the caller's, since content may already have been sent.
+    pub async fn stream_chat_with_fallback(
+        &self,
+        primary: &ModelTarget,
+        fallbacks: &[ModelTarget],
+        request: ChatRequest,
+    ) -> ChainResult<ChatStream> {
+        self.walk_chain(
+            primary,
+            fallbacks,
+            request,
+            |provider, request| async move { provider.stream_chat(request).await },
+        )
+        .await
+    }
+
+    async fn walk_chain<T, F, Fut>(
+        &self,
+        primary: &ModelTarget,
+        fallbacks: &[ModelTarget],
+        request: ChatRequest,
+        mut call: F,
+    ) -> ChainResult<T>
+    where
+        F: FnMut(Arc<ProviderWrapper>, ChatRequest) -> Fut,
+        Fut: Future<Output = ProviderResult<T>>,
+    {
+        let mut last_error = None;
+
+        for target in std::iter::once(primary).chain(fallbacks) {
+            let Some(provider) = self.get_provider(&target.provider) else {
+                continue;
+            };
+
+            let request = ChatRequest {
+                model: target.model_id.clone(),
+                ..request.clone()
+            };
+            match call(provider, request).await {
+                Ok(value) => return Ok((target.clone(), value)),
+                Err(e) if e.is_retryable() => {
+                    tracing::warn!("{} failed, trying the next fallback model: {}", target, e);
+                    last_error = Some((target.clone(), e));
+                }
+                Err(e) => return Err((target.clone(), e)),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            let error = ProviderError::Other(anyhow::anyhow!(
+                "Provider {} is not configured",
+                primary.provider.as_str()
+            ));
+            (primary.clone(), error)
+        }))
+    }
 }
@@ -1,3 +1,13 @@
+pub mod catalog;
+pub mod context;
+pub mod endpoint;
+pub mod error;
+pub mod fallback;
 pub mod manager;
+pub mod normalize;
 pub mod providers;
+pub mod retry;
+pub mod summary;
+pub mod tokens;
+pub mod tools;
 pub mod types;
@@ -0,0 +1,237 @@
+//! Reshapes a conversation to fit each provider's rules before it is serialized.
+//!
+//! Stored history can hold system messages anywhere, consecutive turns from the same
+//! role (e.g. after an interrupted stream) and may start with an assistant turn.
+//! OpenAI-style APIs tolerate most of this, but Anthropic and Gemini do not.
+
+use crate::ai::types::ChatMessage;
+use crate::db::models::MessageRole;
+
+/// Inserted when a conversation would otherwise open with an assistant turn.
+const LEADING_USER_PLACEHOLDER: &str = "(continued)";
+
+/// A conversation whose system prompt has been taken out of the message list.
+#[derive(Debug, Clone, Default)]
+pub struct NormalizedMessages {
+    pub system: Option<String>,
+    pub messages: Vec<ChatMessage>,
+}
+
+/// For chat-completions APIs (OpenAI, DeepSeek, Ollama): every system message is
+/// collapsed into one leading system turn and consecutive same-role turns are merged.
+/// DeepSeek's reasoner rejects successive turns from the same role.
+pub fn for_chat_completions(messages: Vec<ChatMessage>) -> Vec<ChatMessage> {
+    let normalized = lift_system(messages);
+    let mut messages = Vec::with_capacity(normalized.messages.len() + 1);
+
+    if let Some(system) = normalized.system {
+        messages.push(ChatMessage::new(MessageRole::System, system));
+    }
+    messages.extend(normalized.messages);
+
+    messages
+}
+
+/// For Anthropic Messages and Gemini: the system prompt goes in its own field, roles
+/// must alternate and the first turn must come from the user.
+pub fn for_alternating_turns(messages: Vec<ChatMessage>) -> NormalizedMessages {
+    let mut normalized = lift_system(messages);
+
+    if normalized
+        .messages
+        .first()
+        .is_none_or(|m| m.role != MessageRole::User)
+    {
+        normalized.messages.insert(
+            0,
+            ChatMessage::new(MessageRole::User, LEADING_USER_PLACEHOLDER),
+        );
+    }
+
+    normalized
+}
+
+/// Pulls system messages out in order, drops empty turns and merges consecutive
+/// turns from the same role. Tool calls and tool results count as content, and each
+/// tool result stays its own turn.
+fn lift_system(messages: Vec<ChatMessage>) -> NormalizedMessages {
+    let mut system: Vec<String> = Vec::new();
+    let mut turns: Vec<ChatMessage> = Vec::with_capacity(messages.len());
+
+    for message in messages {
+        if message.is_empty() {
+            continue;
+        }
+
+        if message.role == MessageRole::System {
+            system.push(message.text());
+            continue;
+        }
+
+        match turns.last_mut() {
+            // Text after a tool call is not merged into it, or it would move ahead
+            // of the call's results
+            Some(last)
+                if last.role == message.role
+                    && message.role != MessageRole::Tool
+                    && !last.has_tool_calls() =>
+            {
+                last.content.extend(message.content);
+            }
+            _ => turns.push(message),
+        }
+    }
+
+    NormalizedMessages {
+        system: (!system.is_empty()).then(|| system.join("\n\n")),
+        messages: turns,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::types::ToolCall;
+
+    fn message(role: MessageRole, text: &str) -> ChatMessage {
+        ChatMessage::new(role, text)
+    }
+
+    fn call(id: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            name: "calculator".to_string(),
+            arguments: serde_json::json!({ "expression": "1 + 1" }),
+        }
+    }
+
+    fn roles(messages: &[ChatMessage]) -> Vec<MessageRole> {
+        messages.iter().map(|m| m.role).collect()
+    }
+
+    #[test]
+    fn chat_completions_hoists_system_messages_into_one_leading_turn() {
+        let messages = for_chat_completions(vec![
+            message(MessageRole::User, "Hi"),
+            message(MessageRole::System, "Be brief."),
+            message(MessageRole::Assistant, "Hello"),
+            message(MessageRole::System, "Answer in French."),
+        ]);
+
+        assert_eq!(
+            roles(&messages),
+            [
+                MessageRole::System,
+                MessageRole::User,
+                MessageRole::Assistant
+            ]
+        );
+        assert_eq!(messages[0].text(), "Be brief.\n\nAnswer in French.");
+    }
+
+    #[test]
+    fn chat_completions_merges_consecutive_turns_and_drops_empty_ones() {
+        let messages = for_chat_completions(vec![
+            message(MessageRole::User, "First"),
+            message(MessageRole::Assistant, "   "),
+            message(MessageRole::User, "Second"),
+            message(MessageRole::Assistant, "Answer"),
+            message(MessageRole::Assistant, "More"),
+        ]);
+
+        assert_eq!(
+            roles(&messages),
+            [MessageRole::User, MessageRole::Assistant]
+        );
+        assert_eq!(messages[0].text(), "First\n\nSecond");
+        assert_eq!(messages[1].text(), "Answer\n\nMore");
+    }
+
+    #[test]
+    fn chat_completions_keeps_a_leading_assistant_turn() {
+        let messages = for_chat_completions(vec![message(MessageRole::Assistant, "Welcome")]);
+
+        assert_eq!(roles(&messages), [MessageRole::Assistant]);
+    }
+
+    #[test]
+    fn tool_results_stay_separate_and_text_is_not_merged_into_a_call() {
+        let messages = for_chat_completions(vec![
+            message(MessageRole::User, "Add these"),
+            ChatMessage::assistant(String::new(), None, vec![call("a"), call("b")]),
+            ChatMessage::assistant("Working on it".to_string(), None, Vec::new()),
+            ChatMessage::tool_result("a".to_string(), "2".to_string(), false),
+            ChatMessage::tool_result("b".to_string(), "2".to_string(), false),
+        ]);
+
+        assert_eq!(
+            roles(&messages),
+            [
+                MessageRole::User,
+                MessageRole::Assistant,
+                MessageRole::Assistant,
+                MessageRole::Tool,
+                MessageRole::Tool,
+            ]
+        );
+        assert!(messages[1].has_tool_calls());
+        assert!(!messages[2].has_tool_calls());
+    }
+
+    #[test]
+    fn alternating_turns_lift_the_system_prompt_out_of_the_messages() {
+        let normalized = for_alternating_turns(vec![
+            message(MessageRole::System, "Be brief."),
+            message(MessageRole::User, "Hi"),
+            message(MessageRole::System, "Answer in French."),
+            message(MessageRole::Assistant, "Bonjour"),
+        ]);
+
+        assert_eq!(
+            normalized.system.as_deref(),
+            Some("Be brief.\n\nAnswer in French.")
+        );
+        assert_eq!(
+            roles(&normalized.messages),
+            [MessageRole::User, MessageRole::Assistant]
+        );
+    }
+
+    #[test]
+    fn alternating_turns_open_with_a_user_turn() {
+        let normalized = for_alternating_turns(vec![
+            message(MessageRole::System, "Be brief."),
+            message(MessageRole::Assistant, "Welcome"),
+            message(MessageRole::User, "Hi"),
+        ]);
+
+        assert_eq!(
+            roles(&normalized.messages),
+            [MessageRole::User, MessageRole::Assistant, MessageRole::User]
+        );
+        assert_eq!(normalized.messages[0].text(), LEADING_USER_PLACEHOLDER);
+    }
+
+    #[test]
+    fn alternating_turns_merge_consecutive_turns_from_the_same_role() {
+        let normalized = for_alternating_turns(vec![
+            message(MessageRole::User, "First"),
+            message(MessageRole::User, "Second"),
+            message(MessageRole::Assistant, "Answer"),
+        ]);
+
+        assert_eq!(normalized.system, None);
+        assert_eq!(
+            roles(&normalized.messages),
+            [MessageRole::User, MessageRole::Assistant]
+        );
+        assert_eq!(normalized.messages[0].text(), "First\n\nSecond");
+    }
+
+    #[test]
+    fn alternating_turns_of_an_empty_conversation_hold_the_placeholder() {
+        let normalized = for_alternating_turns(Vec::new());
+
+        assert_eq!(roles(&normalized.messages), [MessageRole::User]);
+    }
+}
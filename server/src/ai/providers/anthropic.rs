@@ -1,9 +1,23 @@
-use crate::ai::providers::AIProvider;
-use crate::ai::types::{ChatRequest, ChatResponse, ChatResponseChunk, ModelInfo};
+use crate::ai::error::{self, ProviderError, ProviderResult};
+use crate::ai::normalize;
+use crate::ai::providers::{self, AIProvider, sse};
+use crate::ai::types::{
+    ChatRequest, ChatResponse, ChatResponseChunk, ChatStream, ContentPart, InlineImage, ModelInfo,
+    TokenUsage, ToolCall, ToolChoice, ToolDefinition,
+};
+use crate::db::models::MessageRole;
 use async_trait::async_trait;
-use futures::Stream;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 
+const ANTHROPIC_MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
+const ANTHROPIC_MODELS_URL: &str = "https://api.anthropic.com/v1/models";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Every current Claude model accepts 200k tokens and image input; `/v1/models`
+/// reports neither.
+const ANTHROPIC_CONTEXT_WINDOW: u32 = 200000;
+
 pub struct AnthropicProvider {
     api_key: String,
     client: reqwest::Client,
@@ -13,153 +27,668 @@ impl AnthropicProvider {
     pub fn new(api_key: String) -> Self {
         Self {
             api_key,
-            client: reqwest::Client::new(),
+            client: providers::http_client(),
         }
     }
-}
 
-#[async_trait]
-impl AIProvider for AnthropicProvider {
-    async fn chat(&self, request: ChatRequest) -> anyhow::Result<ChatResponse> {
-        #[derive(Serialize)]
-        struct AnthropicRequest {
-            model: String,
-            messages: Vec<AnthropicMessage>,
-            max_tokens: u32,
-            temperature: Option<f32>,
+    async fn send(&self, request: &AnthropicRequest) -> ProviderResult<reqwest::Response> {
+        let mut builder = self
+            .client
+            .post(ANTHROPIC_MESSAGES_URL)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("Content-Type", "application/json")
+            .json(request);
+        // Streams are bounded by the read timeout alone, however long the answer
+        if !request.stream {
+            builder = builder.timeout(providers::REQUEST_TIMEOUT);
         }
+        let response = builder.send().await?;
 
-        #[derive(Serialize)]
-        struct AnthropicMessage {
-            role: String,
-            content: String,
+        if !response.status().is_success() {
+            return Err(error_from_response(response).await);
         }
 
-        #[derive(Deserialize)]
-        struct AnthropicResponse {
-            content: Vec<AnthropicContent>,
-            model: String,
-            usage: AnthropicUsage,
-            stop_reason: Option<String>,
-        }
+        Ok(response)
+    }
+
+    /// Pages through `/v1/models`, which lists every model the key can use.
+    async fn fetch_models(&self) -> ProviderResult<Vec<AnthropicModel>> {
+        let mut models = Vec::new();
+        let mut after_id: Option<String> = None;
+
+        loop {
+            let mut query = vec![("limit", "1000".to_string())];
+            if let Some(after_id) = after_id.take() {
+                query.push(("after_id", after_id));
+            }
+
+            let response = self
+                .client
+                .get(ANTHROPIC_MODELS_URL)
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .query(&query)
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(error_from_response(response).await);
+            }
 
-        #[derive(Deserialize)]
-        struct AnthropicContent {
-            text: String,
+            let page: AnthropicModelList = response.json().await?;
+            models.extend(page.data);
+            match page.last_id {
+                Some(last_id) if page.has_more => after_id = Some(last_id),
+                _ => break,
+            }
         }
 
-        #[derive(Deserialize)]
-        struct AnthropicUsage {
-            input_tokens: u32,
-            output_tokens: u32,
+        Ok(models)
+    }
+}
+
+async fn error_from_response(response: reqwest::Response) -> ProviderError {
+    let status = response.status();
+    let retry_after = error::retry_after(response.headers());
+    let body = response.text().await.unwrap_or_default();
+
+    match serde_json::from_str::<AnthropicErrorResponse>(&body) {
+        Ok(AnthropicErrorResponse { error }) => error.into_provider_error(status, retry_after),
+        Err(_) => ProviderError::from_status(status, retry_after, body),
+    }
+}
+
+#[derive(Serialize)]
+struct AnthropicRequest {
+    model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<AnthropicMessage>,
+    max_tokens: u32,
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
+}
+
+impl AnthropicRequest {
+    fn new(request: ChatRequest, stream: bool) -> Self {
+        let normalized = normalize::for_alternating_turns(request.messages);
+        let mut messages: Vec<AnthropicMessage> = Vec::with_capacity(normalized.messages.len());
+
+        for m in normalized.messages {
+            let role = match m.role {
+                MessageRole::Assistant => "assistant",
+                // System turns were lifted into `system` by the normalizer and tool
+                // results are sent back as user turns
+                MessageRole::User | MessageRole::System | MessageRole::Tool => "user",
+            };
+
+            let blocks: Vec<AnthropicBlock> = m
+                .content
+                .into_iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text { text } if !text.trim().is_empty() => {
+                        Some(AnthropicBlock::Text { text })
+                    }
+                    ContentPart::Image { url, .. } => Some(AnthropicBlock::Image {
+                        source: AnthropicImageSource::from_url(url),
+                    }),
+                    ContentPart::ToolCall(call) => Some(AnthropicBlock::ToolUse {
+                        id: call.id,
+                        name: call.name,
+                        input: call.arguments,
+                    }),
+                    ContentPart::ToolResult {
+                        tool_call_id,
+                        content,
+                        is_error,
+                    } => Some(AnthropicBlock::ToolResult {
+                        tool_use_id: tool_call_id,
+                        content,
+                        is_error,
+                    }),
+                    _ => None,
+                })
+                .collect();
+
+            // Results of parallel tool calls, and any user text after them, have to
+            // share one user turn for the roles to keep alternating
+            match messages.last_mut() {
+                Some(last) if last.role == role => last.content.extend(blocks),
+                _ => messages.push(AnthropicMessage {
+                    role: role.to_string(),
+                    content: blocks,
+                }),
+            }
         }
 
-        let messages: Vec<AnthropicMessage> = request
-            .messages
-            .into_iter()
-            .map(|m| AnthropicMessage {
-                role: match m.role {
-                    crate::db::models::MessageRole::User => "user".to_string(),
-                    crate::db::models::MessageRole::Assistant => "assistant".to_string(),
-                    crate::db::models::MessageRole::System => "system".to_string(),
-                },
-                content: m.content,
-            })
-            .collect();
+        let tool_choice = request.tool_choice.map(|choice| match choice {
+            ToolChoice::Auto => serde_json::json!({ "type": "auto" }),
+            ToolChoice::None => serde_json::json!({ "type": "none" }),
+            ToolChoice::Required => serde_json::json!({ "type": "any" }),
+            ToolChoice::Tool { name } => serde_json::json!({ "type": "tool", "name": name }),
+        });
 
-        let req = AnthropicRequest {
+        Self {
             model: request.model,
+            system: normalized.system,
             messages,
             max_tokens: request.max_tokens.unwrap_or(1024),
             temperature: request.temperature,
-        };
+            stream,
+            tools: request.tools.into_iter().map(AnthropicTool::from).collect(),
+            tool_choice,
+        }
+    }
+}
 
-        let response = self
-            .client
-            .post("https://api.anthropic.com/v1/messages")
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("Content-Type", "application/json")
-            .json(&req)
-            .send()
-            .await?;
+#[derive(Serialize)]
+struct AnthropicMessage {
+    role: String,
+    content: Vec<AnthropicBlock>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicBlock {
+    Text {
+        text: String,
+    },
+    Image {
+        source: AnthropicImageSource,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        is_error: bool,
+    },
+    /// Block types we do not send or read, such as `thinking`.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicImageSource {
+    Base64 { media_type: String, data: String },
+    Url { url: String },
+}
+
+impl AnthropicImageSource {
+    /// Images arrive as data URLs, which Anthropic wants split into type and data.
+    fn from_url(url: String) -> Self {
+        match InlineImage::parse(&url) {
+            Some(image) => AnthropicImageSource::Base64 {
+                media_type: image.mime_type.to_string(),
+                data: image.data.to_string(),
+            },
+            None => AnthropicImageSource::Url { url },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct AnthropicTool {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    input_schema: serde_json::Value,
+}
+
+impl From<ToolDefinition> for AnthropicTool {
+    fn from(tool: ToolDefinition) -> Self {
+        Self {
+            name: tool.name,
+            description: tool.description,
+            input_schema: tool.parameters,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AnthropicResponse {
+    content: Vec<AnthropicBlock>,
+    model: String,
+    usage: AnthropicUsage,
+    stop_reason: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+    /// Prompt tokens written to and read from the prompt cache; `input_tokens` leaves
+    /// both out
+    cache_creation_input_tokens: Option<u32>,
+    cache_read_input_tokens: Option<u32>,
+}
+
+impl From<AnthropicUsage> for TokenUsage {
+    fn from(usage: AnthropicUsage) -> Self {
+        Self {
+            prompt_tokens: usage.input_tokens
+                + usage.cache_creation_input_tokens.unwrap_or(0)
+                + usage.cache_read_input_tokens.unwrap_or(0),
+            completion_tokens: usage.output_tokens,
+            cached_tokens: usage.cache_read_input_tokens,
+            reasoning_tokens: None,
+        }
+    }
+}
+
+/// Events of the Messages streaming API. `content_block_start` and `content_block_stop`
+/// only matter for `tool_use` blocks, whose input arrives as JSON fragments in between.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicStreamEvent {
+    MessageStart {
+        message: AnthropicStreamMessage,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: AnthropicBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: AnthropicDelta,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+    MessageDelta {
+        delta: AnthropicMessageDelta,
+        usage: Option<AnthropicUsage>,
+    },
+    MessageStop,
+    Error {
+        error: AnthropicError,
+    },
+    Ping,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct AnthropicStreamMessage {
+    model: String,
+    #[serde(default)]
+    usage: AnthropicUsage,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicDelta {
+    TextDelta {
+        text: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    #[serde(other)]
+    Other,
+}
 
+#[derive(Deserialize)]
+struct AnthropicMessageDelta {
+    stop_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicErrorResponse {
+    error: AnthropicError,
+}
+
+#[derive(Deserialize)]
+struct AnthropicModelList {
+    data: Vec<AnthropicModel>,
+    #[serde(default)]
+    has_more: bool,
+    last_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicModel {
+    id: String,
+    display_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicError {
+    #[serde(rename = "type")]
+    kind: String,
+    message: String,
+}
+
+impl AnthropicError {
+    fn into_provider_error(
+        self,
+        status: reqwest::StatusCode,
+        retry_after: Option<std::time::Duration>,
+    ) -> ProviderError {
+        match self.kind.as_str() {
+            "authentication_error" | "permission_error" => {
+                ProviderError::InvalidApiKey(self.message)
+            }
+            "rate_limit_error" => ProviderError::RateLimited {
+                retry_after,
+                message: self.message,
+            },
+            "not_found_error" => ProviderError::ModelNotFound(self.message),
+            "request_too_large" => ProviderError::ContextLengthExceeded(self.message),
+            // Over-long prompts are reported as a plain invalid_request_error
+            "invalid_request_error" if self.message.contains("prompt is too long") => {
+                ProviderError::ContextLengthExceeded(self.message)
+            }
+            "overloaded_error" => ProviderError::Upstream {
+                status: 529,
+                message: self.message,
+            },
+            _ => ProviderError::from_status(status, retry_after, self.message),
+        }
+    }
+}
+
+/// Usage is split across events: input tokens arrive on `message_start` and the
+/// cumulative output count on `message_delta`, so it is collected here until the end.
+struct AnthropicStreamState {
+    events: std::pin::Pin<Box<dyn futures::Stream<Item = ProviderResult<sse::SseEvent>> + Send>>,
+    model: String,
+    usage: Option<AnthropicUsage>,
+    stop_reason: Option<String>,
+    tool_use: Option<PendingToolUse>,
+    finished: bool,
+}
+
+/// A `tool_use` block whose input is still streaming in as JSON fragments.
+struct PendingToolUse {
+    index: usize,
+    id: String,
+    name: String,
+    input: String,
+}
+
+impl AnthropicStreamState {
+    fn final_chunk(&mut self) -> ChatResponseChunk {
+        self.finished = true;
+        ChatResponseChunk {
+            content: String::new(),
+            reasoning: None,
+            tool_calls: Vec::new(),
+            done: true,
+            model: Some(self.model.clone()),
+            usage: self.usage.take().map(TokenUsage::from),
+            finish_reason: self.stop_reason.take(),
+        }
+    }
+}
+
+#[async_trait]
+impl AIProvider for AnthropicProvider {
+    async fn chat(&self, request: ChatRequest) -> ProviderResult<ChatResponse> {
+        let req = AnthropicRequest::new(request, false);
+        let response = self.send(&req).await?;
         let response: AnthropicResponse = response.json().await?;
 
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+        for block in response.content {
+            match block {
+                AnthropicBlock::Text { text } => content.push_str(&text),
+                AnthropicBlock::ToolUse { id, name, input } => tool_calls.push(ToolCall {
+                    id,
+                    name,
+                    arguments: input,
+                }),
+                AnthropicBlock::Image { .. }
+                | AnthropicBlock::ToolResult { .. }
+                | AnthropicBlock::Other => {}
+            }
+        }
+
         Ok(ChatResponse {
-            content: response
-                .content
-                .first()
-                .map(|c| c.text.clone())
-                .unwrap_or_default(),
+            content,
+            reasoning: None,
+            tool_calls,
             model: response.model,
-            tokens_used: Some(response.usage.input_tokens + response.usage.output_tokens),
+            usage: Some(response.usage.into()),
             finish_reason: response.stop_reason,
         })
     }
 
-    async fn stream_chat(
-        &self,
-        request: ChatRequest,
-    ) -> anyhow::Result<impl Stream<Item = anyhow::Result<ChatResponseChunk>>> {
-        let response = self.chat(request).await?;
-        Ok(futures::stream::iter(vec![Ok(ChatResponseChunk {
-            content: response.content,
-            done: true,
-            model: Some(response.model),
-        })]))
+    async fn stream_chat(&self, request: ChatRequest) -> ProviderResult<ChatStream> {
+        let model = request.model.clone();
+        let req = AnthropicRequest::new(request, true);
+        let response = self.send(&req).await?;
+
+        let state = AnthropicStreamState {
+            events: Box::pin(sse::events(response)),
+            model,
+            usage: None,
+            stop_reason: None,
+            tool_use: None,
+            finished: false,
+        };
+
+        let stream = futures::stream::unfold(state, |mut state| async move {
+            if state.finished {
+                return None;
+            }
+
+            loop {
+                let event = match state.events.next().await {
+                    Some(Ok(event)) => event,
+                    Some(Err(e)) => {
+                        state.finished = true;
+                        return Some((Err(e), state));
+                    }
+                    // The connection closed before message_stop; the answer was cut short
+                    None => {
+                        state.finished = true;
+                        return Some((Err(ProviderError::stream_cut_short()), state));
+                    }
+                };
+
+                let event: AnthropicStreamEvent = match serde_json::from_str(&event.data) {
+                    Ok(event) => event,
+                    Err(e) => {
+                        state.finished = true;
+                        return Some((Err(e.into()), state));
+                    }
+                };
+
+                match event {
+                    AnthropicStreamEvent::MessageStart { message } => {
+                        state.model = message.model;
+                        state.usage = Some(message.usage);
+                    }
+                    AnthropicStreamEvent::ContentBlockStart {
+                        index,
+                        content_block: AnthropicBlock::ToolUse { id, name, .. },
+                    } => {
+                        state.tool_use = Some(PendingToolUse {
+                            index,
+                            id,
+                            name,
+                            input: String::new(),
+                        });
+                    }
+                    AnthropicStreamEvent::ContentBlockDelta {
+                        index,
+                        delta: AnthropicDelta::InputJsonDelta { partial_json },
+                    } => {
+                        if let Some(tool_use) = state.tool_use.as_mut().filter(|t| t.index == index)
+                        {
+                            tool_use.input.push_str(&partial_json);
+                        }
+                    }
+                    AnthropicStreamEvent::ContentBlockStop { index }
+                        if state.tool_use.as_ref().is_some_and(|t| t.index == index) =>
+                    {
+                        let Some(tool_use) = state.tool_use.take() else {
+                            continue;
+                        };
+                        let call = ToolCall::from_json_arguments(
+                            tool_use.id,
+                            tool_use.name,
+                            &tool_use.input,
+                        );
+                        let chunk = ChatResponseChunk {
+                            tool_calls: vec![call],
+                            done: false,
+                            model: Some(state.model.clone()),
+                            ..Default::default()
+                        };
+                        return Some((Ok(chunk), state));
+                    }
+                    AnthropicStreamEvent::ContentBlockDelta {
+                        delta: AnthropicDelta::TextDelta { text },
+                        ..
+                    } if !text.is_empty() => {
+                        let chunk = ChatResponseChunk {
+                            content: text,
+                            done: false,
+                            model: Some(state.model.clone()),
+                            ..Default::default()
+                        };
+                        return Some((Ok(chunk), state));
+                    }
+                    AnthropicStreamEvent::MessageDelta { delta, usage } => {
+                        if delta.stop_reason.is_some() {
+                            state.stop_reason = delta.stop_reason;
+                        }
+                        // message_delta usage is cumulative for the whole response
+                        if let Some(output_tokens) = usage.map(|u| u.output_tokens) {
+                            state.usage.get_or_insert_default().output_tokens = output_tokens;
+                        }
+                    }
+                    AnthropicStreamEvent::MessageStop => {
+                        let chunk = state.final_chunk();
+                        return Some((Ok(chunk), state));
+                    }
+                    AnthropicStreamEvent::Error { error } => {
+                        state.finished = true;
+                        // Errors after the stream has started come without an HTTP status
+                        let error = error
+                            .into_provider_error(reqwest::StatusCode::INTERNAL_SERVER_ERROR, None);
+                        return Some((Err(error), state));
+                    }
+                    AnthropicStreamEvent::ContentBlockStart { .. }
+                    | AnthropicStreamEvent::ContentBlockDelta { .. }
+                    | AnthropicStreamEvent::ContentBlockStop { .. }
+                    | AnthropicStreamEvent::Ping
+                    | AnthropicStreamEvent::Other => {}
+                }
+            }
+        });
+
+        Ok(Box::pin(stream))
     }
 
-    fn get_model_info(&self, model_id: &str) -> Option<ModelInfo> {
-        match model_id {
-            "claude-3-opus" => Some(ModelInfo {
-                id: "claude-3-opus".to_string(),
-                display_name: "Claude 3 Opus".to_string(),
-                context_window: 200000,
-                supports_streaming: true,
-                supports_images: true,
-            }),
-            "claude-3-sonnet" => Some(ModelInfo {
-                id: "claude-3-sonnet".to_string(),
-                display_name: "Claude 3 Sonnet".to_string(),
-                context_window: 200000,
-                supports_streaming: true,
-                supports_images: true,
-            }),
-            "claude-3-haiku" => Some(ModelInfo {
-                id: "claude-3-haiku".to_string(),
-                display_name: "Claude 3 Haiku".to_string(),
-                context_window: 200000,
+    async fn discover_models(&self) -> ProviderResult<Vec<ModelInfo>> {
+        let models = self.fetch_models().await?;
+
+        Ok(models
+            .into_iter()
+            .map(|model| ModelInfo {
+                display_name: model.display_name.unwrap_or_else(|| model.id.clone()),
+                id: model.id,
+                context_window: ANTHROPIC_CONTEXT_WINDOW,
                 supports_streaming: true,
                 supports_images: true,
-            }),
-            _ => None,
+                // Every Claude model the API lists takes tools
+                supports_functions: true,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::types::ChatMessage;
+
+    fn request(messages: Vec<ChatMessage>) -> ChatRequest {
+        ChatRequest {
+            model: "claude-sonnet-4-0".to_string(),
+            messages,
+            temperature: None,
+            max_tokens: None,
+            stream: false,
+            tools: Vec::new(),
+            tool_choice: None,
         }
     }
 
-    fn list_models(&self) -> Vec<ModelInfo> {
-        vec![
-            ModelInfo {
-                id: "claude-3-opus".to_string(),
-                display_name: "Claude 3 Opus".to_string(),
-                context_window: 200000,
-                supports_streaming: true,
-                supports_images: true,
-            },
-            ModelInfo {
-                id: "claude-3-sonnet".to_string(),
-                display_name: "Claude 3 Sonnet".to_string(),
-                context_window: 200000,
-                supports_streaming: true,
-                supports_images: true,
-            },
-            ModelInfo {
-                id: "claude-3-haiku".to_string(),
-                display_name: "Claude 3 Haiku".to_string(),
-                context_window: 200000,
-                supports_streaming: true,
-                supports_images: true,
-            },
-        ]
+    fn call(id: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            name: "calculator".to_string(),
+            arguments: serde_json::json!({ "expression": "1 + 1" }),
+        }
+    }
+
+    fn roles(body: &serde_json::Value) -> Vec<&str> {
+        body["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["role"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn system_prompt_goes_in_its_own_field_and_roles_alternate() {
+        let body = serde_json::to_value(AnthropicRequest::new(
+            request(vec![
+                ChatMessage::new(MessageRole::System, "Be brief."),
+                ChatMessage::new(MessageRole::Assistant, "Welcome"),
+                ChatMessage::new(MessageRole::User, "Hi"),
+                ChatMessage::new(MessageRole::User, "Anyone there?"),
+            ]),
+            false,
+        ))
+        .unwrap();
+
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(roles(&body), ["user", "assistant", "user"]);
+        assert_eq!(body["messages"][2]["content"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn parallel_tool_results_share_one_user_turn() {
+        let body = serde_json::to_value(AnthropicRequest::new(
+            request(vec![
+                ChatMessage::new(MessageRole::User, "Add these"),
+                ChatMessage::assistant(String::new(), None, vec![call("a"), call("b")]),
+                ChatMessage::tool_result("a".to_string(), "2".to_string(), false),
+                ChatMessage::tool_result("b".to_string(), "2".to_string(), false),
+                ChatMessage::new(MessageRole::User, "Thanks"),
+            ]),
+            false,
+        ))
+        .unwrap();
+
+        assert_eq!(body.get("system"), None);
+        assert_eq!(roles(&body), ["user", "assistant", "user"]);
+        let types: Vec<&str> = body["messages"][2]["content"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|block| block["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(types, ["tool_result", "tool_result", "text"]);
     }
 }
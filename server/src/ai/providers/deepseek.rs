@@ -0,0 +1,50 @@
+use crate::ai::error::ProviderResult;
+use crate::ai::providers::{AIProvider, openai::OpenAIProvider};
+use crate::ai::types::{ChatRequest, ChatResponse, ChatStream, ModelInfo};
+use async_trait::async_trait;
+
+const DEEPSEEK_API_BASE_URL: &str = "https://api.deepseek.com/v1";
+
+/// DeepSeek exposes an OpenAI-compatible chat-completions API, so requests and
+/// streaming are delegated to `OpenAIProvider`. deepseek-reasoner returns its chain of
+/// thought in `reasoning_content`, which surfaces as `reasoning` on responses and chunks.
+pub struct DeepSeekProvider {
+    inner: OpenAIProvider,
+}
+
+impl DeepSeekProvider {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            inner: OpenAIProvider::with_base_url(api_key, DEEPSEEK_API_BASE_URL.to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl AIProvider for DeepSeekProvider {
+    async fn chat(&self, request: ChatRequest) -> ProviderResult<ChatResponse> {
+        self.inner.chat(request).await
+    }
+
+    async fn stream_chat(&self, request: ChatRequest) -> ProviderResult<ChatStream> {
+        self.inner.stream_chat(request).await
+    }
+
+    /// DeepSeek's `/models` only returns ids; every model it serves has a 128K context,
+    /// takes tools and reads text only.
+    async fn discover_models(&self) -> ProviderResult<Vec<ModelInfo>> {
+        let ids = self.inner.fetch_model_ids().await?;
+
+        Ok(ids
+            .into_iter()
+            .map(|id| ModelInfo {
+                display_name: id.clone(),
+                id,
+                context_window: 131072,
+                supports_streaming: true,
+                supports_images: false,
+                supports_functions: true,
+            })
+            .collect())
+    }
+}
@@ -1,8 +1,20 @@
-use crate::ai::providers::AIProvider;
-use crate::ai::types::{ChatRequest, ChatResponse, ChatResponseChunk, ModelInfo};
+use crate::ai::error::{self, ProviderError, ProviderResult};
+use crate::ai::normalize;
+use crate::ai::providers::{self, AIProvider, ImageProvider, sse};
+use crate::ai::types::{
+    ChatRequest, ChatResponse, ChatResponseChunk, ChatStream, ContentPart, GeneratedImage,
+    ImageGenerationRequest, ImageGenerationResponse, InlineImage, ModelInfo, TokenUsage, ToolCall,
+    ToolChoice,
+};
+use crate::db::models::MessageRole;
 use async_trait::async_trait;
-use futures::Stream;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const GEMINI_API_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
 
 pub struct GoogleProvider {
     api_key: String,
@@ -13,159 +25,873 @@ impl GoogleProvider {
     pub fn new(api_key: String) -> Self {
         Self {
             api_key,
-            client: reqwest::Client::new(),
+            client: providers::http_client(),
         }
     }
-}
 
-#[async_trait]
-impl AIProvider for GoogleProvider {
-    async fn chat(&self, request: ChatRequest) -> anyhow::Result<ChatResponse> {
-        #[derive(Serialize)]
-        struct GoogleRequest {
-            contents: Vec<GoogleContent>,
-            generation_config: GoogleGenerationConfig,
+    /// Posts `request` to `url`. Streams are bounded by the read timeout alone, however
+    /// long the answer; other requests also by `REQUEST_TIMEOUT`.
+    async fn send(
+        &self,
+        url: &str,
+        request: &GoogleRequest,
+        stream: bool,
+    ) -> ProviderResult<reqwest::Response> {
+        let mut builder = self
+            .client
+            .post(url)
+            .header("x-goog-api-key", &self.api_key)
+            .header("Content-Type", "application/json")
+            .json(request);
+        if !stream {
+            builder = builder.timeout(providers::REQUEST_TIMEOUT);
         }
+        let response = builder.send().await?;
 
-        #[derive(Serialize)]
-        struct GoogleContent {
-            role: String,
-            parts: Vec<GooglePart>,
+        if !response.status().is_success() {
+            return Err(error_from_response(response).await);
         }
 
-        #[derive(Serialize, Deserialize)]
-        struct GooglePart {
-            text: String,
-        }
+        Ok(response)
+    }
 
-        #[derive(Serialize)]
-        struct GoogleGenerationConfig {
-            temperature: Option<f32>,
-            max_output_tokens: Option<u32>,
-        }
+    /// Pages through `models.list`.
+    async fn fetch_models(&self) -> ProviderResult<Vec<GoogleModel>> {
+        let url = format!("{}/models", GEMINI_API_BASE_URL);
+        let mut models = Vec::new();
+        let mut page_token: Option<String> = None;
 
-        #[derive(Deserialize)]
-        struct GoogleResponse {
-            candidates: Vec<GoogleCandidate>,
-            usage_metadata: Option<GoogleUsageMetadata>,
-        }
+        loop {
+            let mut query = vec![("pageSize", "1000".to_string())];
+            if let Some(page_token) = page_token.take() {
+                query.push(("pageToken", page_token));
+            }
+
+            let response = self
+                .client
+                .get(&url)
+                .header("x-goog-api-key", &self.api_key)
+                .query(&query)
+                .send()
+                .await?;
 
-        #[derive(Deserialize)]
-        struct GoogleCandidate {
-            content: GoogleResponseContent,
-            finish_reason: Option<String>,
+            if !response.status().is_success() {
+                return Err(error_from_response(response).await);
+            }
+
+            let page: GoogleModelList = response.json().await?;
+            models.extend(page.models);
+            match page.next_page_token {
+                Some(token) if !token.is_empty() => page_token = Some(token),
+                _ => break,
+            }
         }
 
-        #[derive(Deserialize)]
-        struct GoogleResponseContent {
-            parts: Vec<GooglePart>,
+        Ok(models)
+    }
+}
+
+async fn error_from_response(response: reqwest::Response) -> ProviderError {
+    let status = response.status();
+    let retry_after = error::retry_after(response.headers());
+    let body = response.text().await.unwrap_or_default();
+
+    match serde_json::from_str::<GoogleErrorResponse>(&body) {
+        Ok(GoogleErrorResponse { error }) => error.into_provider_error(status, retry_after),
+        Err(_) => ProviderError::from_status(status, retry_after, body),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleModelList {
+    #[serde(default)]
+    models: Vec<GoogleModel>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleModel {
+    /// Resource name, `models/{id}`
+    name: String,
+    display_name: Option<String>,
+    input_token_limit: Option<u32>,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+/// Google's standard `{"error": {"code", "message", "status", "details"}}` payload.
+#[derive(Deserialize)]
+struct GoogleErrorResponse {
+    error: GoogleError,
+}
+
+#[derive(Deserialize)]
+struct GoogleError {
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    details: Vec<GoogleErrorDetail>,
+}
+
+/// Either an `ErrorInfo` (with `reason`) or a `RetryInfo` (with `retryDelay`).
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleErrorDetail {
+    reason: Option<String>,
+    retry_delay: Option<String>,
+}
+
+impl GoogleError {
+    fn into_provider_error(
+        self,
+        status: reqwest::StatusCode,
+        retry_after: Option<std::time::Duration>,
+    ) -> ProviderError {
+        let invalid_key = self
+            .details
+            .iter()
+            .any(|d| d.reason.as_deref() == Some("API_KEY_INVALID"));
+        // RetryInfo gives the delay as a duration string such as "37s"
+        let retry_delay = self
+            .details
+            .iter()
+            .filter_map(|d| d.retry_delay.as_deref())
+            .filter_map(|delay| delay.strip_suffix('s')?.parse::<f64>().ok())
+            .map(std::time::Duration::from_secs_f64)
+            .next();
+
+        match self.status.as_str() {
+            _ if invalid_key => ProviderError::InvalidApiKey(self.message),
+            "UNAUTHENTICATED" | "PERMISSION_DENIED" => ProviderError::InvalidApiKey(self.message),
+            "RESOURCE_EXHAUSTED" => ProviderError::RateLimited {
+                retry_after: retry_after.or(retry_delay),
+                message: self.message,
+            },
+            "NOT_FOUND" => ProviderError::ModelNotFound(self.message),
+            "INVALID_ARGUMENT"
+                if self
+                    .message
+                    .contains("exceeds the maximum number of tokens") =>
+            {
+                ProviderError::ContextLengthExceeded(self.message)
+            }
+            _ => ProviderError::from_status(status, retry_after, self.message),
         }
+    }
+}
+
+/// Aspect ratios Imagen can draw.
+const IMAGEN_ASPECT_RATIOS: &[(&str, f64)] = &[
+    ("1:1", 1.0),
+    ("3:4", 0.75),
+    ("4:3", 4.0 / 3.0),
+    ("9:16", 9.0 / 16.0),
+    ("16:9", 16.0 / 9.0),
+];
+
+/// Imagen takes an aspect ratio rather than a size: a supported ratio is used as it is,
+/// a `WIDTHxHEIGHT` size becomes the nearest one.
+fn aspect_ratio(size: &str) -> Option<&'static str> {
+    if let Some((ratio, _)) = IMAGEN_ASPECT_RATIOS.iter().find(|(r, _)| *r == size) {
+        return Some(ratio);
+    }
+
+    let (width, height) = size.split_once('x')?;
+    let width: f64 = width.trim().parse().ok()?;
+    let height: f64 = height.trim().parse().ok()?;
+    if width <= 0.0 || height <= 0.0 {
+        return None;
+    }
+    let target = width / height;
+
+    IMAGEN_ASPECT_RATIOS
+        .iter()
+        .min_by(|a, b| (a.1 - target).abs().total_cmp(&(b.1 - target).abs()))
+        .map(|(ratio, _)| *ratio)
+}
+
+#[derive(Serialize)]
+struct ImagenRequest<'a> {
+    instances: [ImagenInstance<'a>; 1],
+    parameters: ImagenParameters,
+}
+
+#[derive(Serialize)]
+struct ImagenInstance<'a> {
+    prompt: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImagenParameters {
+    sample_count: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aspect_ratio: Option<&'static str>,
+}
 
-        #[derive(Deserialize)]
-        struct GoogleUsageMetadata {
-            total_token_count: u32,
+#[derive(Deserialize)]
+struct ImagenResponse {
+    #[serde(default)]
+    predictions: Vec<ImagenPrediction>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImagenPrediction {
+    bytes_base64_encoded: Option<String>,
+    mime_type: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<GoogleContent>,
+    contents: Vec<GoogleContent>,
+    generation_config: GoogleGenerationConfig,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<GoogleTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_config: Option<GoogleToolConfig>,
+}
+
+impl GoogleRequest {
+    fn new(request: &ChatRequest) -> Self {
+        let normalized = normalize::for_alternating_turns(request.messages.clone());
+        // Function responses are matched to calls by name, not by id
+        let mut call_names: HashMap<String, String> = HashMap::new();
+        let mut contents: Vec<GoogleContent> = Vec::with_capacity(normalized.messages.len());
+
+        for m in normalized.messages {
+            let role = match m.role {
+                MessageRole::Assistant => "model",
+                // System turns were lifted into `systemInstruction` by the normalizer
+                // and function responses are sent back as user turns
+                MessageRole::User | MessageRole::System | MessageRole::Tool => "user",
+            };
+
+            let mut parts = Vec::with_capacity(m.content.len());
+            for part in m.content {
+                match part {
+                    ContentPart::Text { text } if !text.trim().is_empty() => {
+                        parts.push(GooglePart::Text(text));
+                    }
+                    // Gemini only takes inline image data, not URLs
+                    ContentPart::Image { url, .. } => {
+                        if let Some(image) = InlineImage::parse(&url) {
+                            parts.push(GooglePart::InlineData(GoogleBlob {
+                                mime_type: image.mime_type.to_string(),
+                                data: image.data.to_string(),
+                            }));
+                        }
+                    }
+                    ContentPart::ToolCall(call) => {
+                        call_names.insert(call.id, call.name.clone());
+                        parts.push(GooglePart::FunctionCall(GoogleFunctionCall {
+                            name: call.name,
+                            args: call.arguments,
+                        }));
+                    }
+                    ContentPart::ToolResult {
+                        tool_call_id,
+                        content,
+                        ..
+                    } => {
+                        let name = call_names.get(&tool_call_id).cloned().unwrap_or_default();
+                        parts.push(GooglePart::FunctionResponse(GoogleFunctionResponse {
+                            name,
+                            response: function_response(content),
+                        }));
+                    }
+                    _ => {}
+                }
+            }
+
+            // Responses to parallel calls have to share one turn
+            match contents.last_mut() {
+                Some(last) if last.role.as_deref() == Some(role) => last.parts.extend(parts),
+                _ => contents.push(GoogleContent {
+                    role: Some(role.to_string()),
+                    parts,
+                }),
+            }
         }
 
-        let contents: Vec<GoogleContent> = request
-            .messages
-            .into_iter()
-            .map(|m| GoogleContent {
-                role: match m.role {
-                    crate::db::models::MessageRole::User => "user".to_string(),
-                    crate::db::models::MessageRole::Assistant => "model".to_string(),
-                    crate::db::models::MessageRole::System => "user".to_string(),
+        let tools = if request.tools.is_empty() {
+            Vec::new()
+        } else {
+            vec![GoogleTool {
+                function_declarations: request
+                    .tools
+                    .iter()
+                    .map(|tool| GoogleFunctionDeclaration {
+                        name: tool.name.clone(),
+                        description: tool.description.clone(),
+                        parameters: tool.parameters.clone(),
+                    })
+                    .collect(),
+            }]
+        };
+        let tool_config = request.tool_choice.as_ref().map(|choice| {
+            let (mode, allowed_function_names) = match choice {
+                ToolChoice::Auto => ("AUTO", None),
+                ToolChoice::None => ("NONE", None),
+                ToolChoice::Required => ("ANY", None),
+                ToolChoice::Tool { name } => ("ANY", Some(vec![name.clone()])),
+            };
+            GoogleToolConfig {
+                function_calling_config: GoogleFunctionCallingConfig {
+                    mode,
+                    allowed_function_names,
                 },
-                parts: vec![GooglePart { text: m.content }],
-            })
-            .collect();
+            }
+        });
 
-        let req = GoogleRequest {
+        Self {
+            system_instruction: normalized.system.map(|text| GoogleContent {
+                role: None,
+                parts: vec![GooglePart::Text(text)],
+            }),
             contents,
             generation_config: GoogleGenerationConfig {
                 temperature: request.temperature,
                 max_output_tokens: request.max_tokens,
             },
-        };
+            tools,
+            tool_config,
+        }
+    }
+}
+
+/// `functionResponse.response` must be an object, so results that are not a JSON
+/// object are wrapped in `{"content": ...}`.
+fn function_response(content: String) -> serde_json::Value {
+    match serde_json::from_str::<serde_json::Value>(&content) {
+        Ok(value @ serde_json::Value::Object(_)) => value,
+        Ok(value) => serde_json::json!({ "content": value }),
+        Err(_) => serde_json::json!({ "content": content }),
+    }
+}
+
+#[derive(Serialize)]
+struct GoogleContent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    parts: Vec<GooglePart>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum GooglePart {
+    Text(String),
+    InlineData(GoogleBlob),
+    FunctionCall(GoogleFunctionCall),
+    FunctionResponse(GoogleFunctionResponse),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleBlob {
+    mime_type: String,
+    /// Base64-encoded bytes.
+    data: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GoogleFunctionCall {
+    name: String,
+    #[serde(default)]
+    args: serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct GoogleFunctionResponse {
+    name: String,
+    response: serde_json::Value,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleTool {
+    function_declarations: Vec<GoogleFunctionDeclaration>,
+}
+
+#[derive(Serialize)]
+struct GoogleFunctionDeclaration {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    parameters: serde_json::Value,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleToolConfig {
+    function_calling_config: GoogleFunctionCallingConfig,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleFunctionCallingConfig {
+    mode: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    allowed_function_names: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleGenerationConfig {
+    temperature: Option<f32>,
+    max_output_tokens: Option<u32>,
+}
+
+/// Finish reasons Gemini uses when its filters stop a candidate.
+const FILTERED_FINISH_REASONS: [&str; 4] =
+    ["SAFETY", "RECITATION", "BLOCKLIST", "PROHIBITED_CONTENT"];
+
+/// Shared by `generateContent` and every event of `streamGenerateContent`; in the
+/// streamed form each event holds only the newly generated parts.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleResponse {
+    #[serde(default)]
+    candidates: Vec<GoogleCandidate>,
+    usage_metadata: Option<GoogleUsageMetadata>,
+    model_version: Option<String>,
+    prompt_feedback: Option<GooglePromptFeedback>,
+}
+
+impl GoogleResponse {
+    /// A prompt rejected by the safety filters comes back as a successful response
+    /// with no candidates and a `blockReason`; an answer stopped by them comes back with
+    /// one of the filtered finish reasons on its candidate.
+    fn blocked(&self) -> Option<ProviderError> {
+        if let Some(reason) = self
+            .prompt_feedback
+            .as_ref()
+            .and_then(|f| f.block_reason.as_ref())
+        {
+            return Some(ProviderError::ContentFiltered(format!(
+                "Prompt blocked by Gemini ({})",
+                reason
+            )));
+        }
 
+        self.finish_reason()
+            .filter(|reason| FILTERED_FINISH_REASONS.contains(&reason.as_str()))
+            .map(|reason| {
+                ProviderError::ContentFiltered(format!("Answer blocked by Gemini ({})", reason))
+            })
+    }
+
+    /// Concatenates the text of every part of the first candidate.
+    fn text(&self) -> String {
+        self.candidates
+            .first()
+            .and_then(|c| c.content.as_ref())
+            .map(|c| c.parts.iter().filter_map(|p| p.text.as_deref()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Function calls of the first candidate. Gemini does not give them ids, so one
+    /// is generated for each to pair it with its result.
+    fn tool_calls(&mut self) -> Vec<ToolCall> {
+        self.candidates
+            .first_mut()
+            .and_then(|c| c.content.as_mut())
+            .map(|c| {
+                c.parts
+                    .iter_mut()
+                    .filter_map(|p| p.function_call.take())
+                    .map(|call| ToolCall {
+                        id: format!("call_{}", uuid::Uuid::new_v4().simple()),
+                        name: call.name,
+                        // Calls to functions without parameters come without `args`
+                        arguments: match call.args {
+                            serde_json::Value::Null => serde_json::json!({}),
+                            args => args,
+                        },
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn finish_reason(&self) -> Option<String> {
+        self.candidates
+            .first()
+            .and_then(|c| c.finish_reason.clone())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleCandidate {
+    content: Option<GoogleResponseContent>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GoogleResponseContent {
+    #[serde(default)]
+    parts: Vec<GoogleResponsePart>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleResponsePart {
+    text: Option<String>,
+    function_call: Option<GoogleFunctionCall>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GooglePromptFeedback {
+    block_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleUsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+    /// Part of `prompt_token_count`
+    cached_content_token_count: Option<u32>,
+    /// Thinking tokens, which `candidates_token_count` leaves out
+    thoughts_token_count: Option<u32>,
+}
+
+impl From<GoogleUsageMetadata> for TokenUsage {
+    fn from(usage: GoogleUsageMetadata) -> Self {
+        Self {
+            prompt_tokens: usage.prompt_token_count,
+            completion_tokens: usage.candidates_token_count
+                + usage.thoughts_token_count.unwrap_or(0),
+            cached_tokens: usage.cached_content_token_count,
+            reasoning_tokens: usage.thoughts_token_count,
+        }
+    }
+}
+
+/// Gemini repeats the (cumulative) usage metadata on each event and sets the finish
+/// reason on the last one; the stream itself just ends without a terminator.
+struct GoogleStreamState {
+    events: std::pin::Pin<Box<dyn futures::Stream<Item = ProviderResult<sse::SseEvent>> + Send>>,
+    model: String,
+    usage: Option<GoogleUsageMetadata>,
+    finish_reason: Option<String>,
+    finished: bool,
+}
+
+impl GoogleStreamState {
+    fn final_chunk(&mut self) -> ChatResponseChunk {
+        self.finished = true;
+        ChatResponseChunk {
+            content: String::new(),
+            reasoning: None,
+            tool_calls: Vec::new(),
+            done: true,
+            model: Some(self.model.clone()),
+            usage: self.usage.take().map(TokenUsage::from),
+            finish_reason: self.finish_reason.take(),
+        }
+    }
+}
+
+#[async_trait]
+impl AIProvider for GoogleProvider {
+    async fn chat(&self, request: ChatRequest) -> ProviderResult<ChatResponse> {
         let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            request.model, self.api_key
+            "{}/models/{}:generateContent",
+            GEMINI_API_BASE_URL, request.model
         );
+        let req = GoogleRequest::new(&request);
+        let response = self.send(&url, &req, false).await?;
+        let mut response: GoogleResponse = response.json().await?;
+        if let Some(error) = response.blocked() {
+            return Err(error);
+        }
+
+        Ok(ChatResponse {
+            content: response.text(),
+            reasoning: None,
+            tool_calls: response.tool_calls(),
+            model: response
+                .model_version
+                .clone()
+                .unwrap_or_else(|| request.model.clone()),
+            usage: response.usage_metadata.take().map(TokenUsage::from),
+            finish_reason: response.finish_reason(),
+        })
+    }
+
+    async fn stream_chat(&self, request: ChatRequest) -> ProviderResult<ChatStream> {
+        let url = format!(
+            "{}/models/{}:streamGenerateContent?alt=sse",
+            GEMINI_API_BASE_URL, request.model
+        );
+        let req = GoogleRequest::new(&request);
+        let response = self.send(&url, &req, true).await?;
+
+        let state = GoogleStreamState {
+            events: Box::pin(sse::events(response)),
+            model: request.model,
+            usage: None,
+            finish_reason: None,
+            finished: false,
+        };
+
+        let stream = futures::stream::unfold(state, |mut state| async move {
+            if state.finished {
+                return None;
+            }
+
+            loop {
+                let event = match state.events.next().await {
+                    Some(Ok(event)) => event,
+                    Some(Err(e)) => {
+                        state.finished = true;
+                        return Some((Err(e), state));
+                    }
+                    None => {
+                        let chunk = state.final_chunk();
+                        return Some((Ok(chunk), state));
+                    }
+                };
 
+                let mut response: GoogleResponse = match serde_json::from_str(&event.data) {
+                    Ok(response) => response,
+                    Err(e) => {
+                        state.finished = true;
+                        return Some((Err(e.into()), state));
+                    }
+                };
+
+                if let Some(error) = response.blocked() {
+                    state.finished = true;
+                    return Some((Err(error), state));
+                }
+
+                let content = response.text();
+                let tool_calls = response.tool_calls();
+                if let Some(finish_reason) = response.finish_reason() {
+                    state.finish_reason = Some(finish_reason);
+                }
+                if let Some(model) = response.model_version {
+                    state.model = model;
+                }
+                if response.usage_metadata.is_some() {
+                    state.usage = response.usage_metadata;
+                }
+
+                // Gemini streams each function call whole, in a single event
+                if !content.is_empty() || !tool_calls.is_empty() {
+                    let chunk = ChatResponseChunk {
+                        content,
+                        tool_calls,
+                        done: false,
+                        model: Some(state.model.clone()),
+                        ..Default::default()
+                    };
+                    return Some((Ok(chunk), state));
+                }
+            }
+        });
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Keeps only models that can serve `generateContent`; embedding and AQA models are
+    /// listed too.
+    async fn discover_models(&self) -> ProviderResult<Vec<ModelInfo>> {
+        let models = self.fetch_models().await?;
+
+        Ok(models
+            .into_iter()
+            .filter(|model| {
+                model
+                    .supported_generation_methods
+                    .iter()
+                    .any(|method| method == "generateContent")
+            })
+            .map(|model| {
+                let id = model
+                    .name
+                    .strip_prefix("models/")
+                    .unwrap_or(&model.name)
+                    .to_string();
+                // Gemini 1.5 and later accept images and function declarations on every
+                // model; Gemma and the 1.0 models take neither
+                let current_gemini = id.starts_with("gemini-") && !id.starts_with("gemini-1.0");
+                ModelInfo {
+                    display_name: model.display_name.unwrap_or_else(|| id.clone()),
+                    context_window: model.input_token_limit.unwrap_or(32768),
+                    // streamGenerateContent is not always listed but works wherever
+                    // generateContent does
+                    supports_streaming: true,
+                    supports_images: current_gemini,
+                    supports_functions: current_gemini,
+                    id,
+                }
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl ImageProvider for GoogleProvider {
+    async fn generate_images(
+        &self,
+        request: ImageGenerationRequest,
+    ) -> ProviderResult<ImageGenerationResponse> {
+        let url = format!("{}/models/{}:predict", GEMINI_API_BASE_URL, request.model);
+        let body = ImagenRequest {
+            instances: [ImagenInstance {
+                prompt: &request.prompt,
+            }],
+            parameters: ImagenParameters {
+                sample_count: request.n,
+                aspect_ratio: request.size.as_deref().and_then(aspect_ratio),
+            },
+        };
         let response = self
             .client
             .post(&url)
-            .header("Content-Type", "application/json")
-            .json(&req)
+            .header("x-goog-api-key", &self.api_key)
+            .json(&body)
+            .timeout(providers::REQUEST_TIMEOUT)
             .send()
             .await?;
 
-        let response: GoogleResponse = response.json().await?;
+        if !response.status().is_success() {
+            return Err(error_from_response(response).await);
+        }
 
-        Ok(ChatResponse {
-            content: response
-                .candidates
-                .first()
-                .and_then(|c| c.content.parts.first())
-                .map(|p| p.text.clone())
-                .unwrap_or_default(),
-            model: request.model.clone(),
-            tokens_used: response.usage_metadata.map(|u| u.total_token_count),
-            finish_reason: response
-                .candidates
-                .first()
-                .and_then(|c| c.finish_reason.clone()),
+        let response: ImagenResponse = response.json().await?;
+        let mut images = Vec::with_capacity(response.predictions.len());
+        for prediction in response.predictions {
+            // Images removed by the safety filter come back without data
+            let Some(data) = prediction.bytes_base64_encoded else {
+                continue;
+            };
+            images.push(GeneratedImage {
+                mime_type: prediction
+                    .mime_type
+                    .unwrap_or_else(|| "image/png".to_string()),
+                data: STANDARD
+                    .decode(data)
+                    .map_err(|e| ProviderError::Other(e.into()))?,
+                revised_prompt: None,
+            });
+        }
+        if images.is_empty() {
+            return Err(ProviderError::ContentFiltered(
+                "Imagen returned no images for this prompt".to_string(),
+            ));
+        }
+
+        Ok(ImageGenerationResponse {
+            model: request.model,
+            images,
         })
     }
 
-    async fn stream_chat(
-        &self,
-        request: ChatRequest,
-    ) -> anyhow::Result<impl Stream<Item = anyhow::Result<ChatResponseChunk>>> {
-        let response = self.chat(request).await?;
-        Ok(futures::stream::iter(vec![Ok(ChatResponseChunk {
-            content: response.content,
-            done: true,
-            model: Some(response.model),
-        })]))
-    }
-
-    fn get_model_info(&self, model_id: &str) -> Option<ModelInfo> {
-        match model_id {
-            "gemini-pro" => Some(ModelInfo {
-                id: "gemini-pro".to_string(),
-                display_name: "Gemini Pro".to_string(),
-                context_window: 32768,
-                supports_streaming: true,
-                supports_images: false,
-            }),
-            "gemini-pro-vision" => Some(ModelInfo {
-                id: "gemini-pro-vision".to_string(),
-                display_name: "Gemini Pro Vision".to_string(),
-                context_window: 16384,
-                supports_streaming: true,
-                supports_images: true,
-            }),
-            _ => None,
+    fn default_image_model(&self) -> &'static str {
+        "imagen-3.0-generate-002"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::types::ChatMessage;
+
+    fn request(messages: Vec<ChatMessage>) -> ChatRequest {
+        ChatRequest {
+            model: "gemini-2.5-flash".to_string(),
+            messages,
+            temperature: None,
+            max_tokens: None,
+            stream: false,
+            tools: Vec::new(),
+            tool_choice: None,
         }
     }
 
-    fn list_models(&self) -> Vec<ModelInfo> {
-        vec![
-            ModelInfo {
-                id: "gemini-pro".to_string(),
-                display_name: "Gemini Pro".to_string(),
-                context_window: 32768,
-                supports_streaming: true,
-                supports_images: false,
-            },
-            ModelInfo {
-                id: "gemini-pro-vision".to_string(),
-                display_name: "Gemini Pro Vision".to_string(),
-                context_window: 16384,
-                supports_streaming: true,
-                supports_images: true,
-            },
-        ]
+    fn call(id: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            name: "calculator".to_string(),
+            arguments: serde_json::json!({ "expression": "1 + 1" }),
+        }
+    }
+
+    fn roles(body: &serde_json::Value) -> Vec<&str> {
+        body["contents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["role"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn system_prompt_goes_in_system_instruction_and_roles_alternate() {
+        let body = serde_json::to_value(GoogleRequest::new(&request(vec![
+            ChatMessage::new(MessageRole::Assistant, "Welcome"),
+            ChatMessage::new(MessageRole::System, "Be brief."),
+            ChatMessage::new(MessageRole::User, "Hi"),
+            ChatMessage::new(MessageRole::User, "Anyone there?"),
+        ])))
+        .unwrap();
+
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Be brief.");
+        assert_eq!(roles(&body), ["user", "model", "user"]);
+        assert_eq!(body["contents"][2]["parts"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn parallel_function_responses_share_one_user_turn() {
+        let body = serde_json::to_value(GoogleRequest::new(&request(vec![
+            ChatMessage::new(MessageRole::User, "Add these"),
+            ChatMessage::assistant(String::new(), None, vec![call("a"), call("b")]),
+            ChatMessage::tool_result("a".to_string(), "2".to_string(), false),
+            ChatMessage::tool_result("b".to_string(), "2".to_string(), false),
+        ])))
+        .unwrap();
+
+        assert_eq!(body.get("systemInstruction"), None);
+        assert_eq!(roles(&body), ["user", "model", "user"]);
+        let parts = body["contents"][2]["parts"].as_array().unwrap();
+        assert_eq!(parts.len(), 2);
+        assert!(
+            parts
+                .iter()
+                .all(|part| part["functionResponse"]["name"] == "calculator")
+        );
+    }
+
+    #[test]
+    fn filtered_finish_reasons_are_reported_as_content_filtered() {
+        let response: GoogleResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{ "finishReason": "RECITATION" }]
+        }))
+        .unwrap();
+        assert!(matches!(
+            response.blocked(),
+            Some(ProviderError::ContentFiltered(_))
+        ));
+
+        let response: GoogleResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{ "finishReason": "STOP" }]
+        }))
+        .unwrap();
+        assert!(response.blocked().is_none());
     }
 }
@@ -1,18 +1,57 @@
-use crate::ai::types::{ChatRequest, ChatResponse, ChatResponseChunk, ModelInfo};
+use crate::ai::error::ProviderResult;
+use crate::ai::types::{
+    ChatRequest, ChatResponse, ChatStream, ImageGenerationRequest, ImageGenerationResponse,
+    ModelInfo,
+};
 use async_trait::async_trait;
-use futures::Stream;
+use std::time::Duration;
+
+/// How long a provider has to accept the connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest silence while reading a response. Reasoning models can think for minutes
+/// before their first token.
+const READ_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Longest a request without streaming may take from start to finish.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// The HTTP client every provider sends its requests with. A stalled provider fails
+/// with `ProviderError::Timeout` instead of holding the request open. Redirects are not
+/// followed, since a checked user-supplied URL could otherwise send the request
+/// elsewhere.
+pub fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap_or_default()
+}
 
 #[async_trait]
 pub trait AIProvider: Send + Sync {
-    async fn chat(&self, request: ChatRequest) -> anyhow::Result<ChatResponse>;
-    async fn stream_chat(
+    async fn chat(&self, request: ChatRequest) -> ProviderResult<ChatResponse>;
+    async fn stream_chat(&self, request: ChatRequest) -> ProviderResult<ChatStream>;
+    /// Asks the provider which models are currently available.
+    async fn discover_models(&self) -> ProviderResult<Vec<ModelInfo>>;
+}
+
+/// A provider with an image generation API.
+#[async_trait]
+pub trait ImageProvider: Send + Sync {
+    async fn generate_images(
         &self,
-        request: ChatRequest,
-    ) -> anyhow::Result<impl Stream<Item = anyhow::Result<ChatResponseChunk>>>;
-    fn get_model_info(&self, model_id: &str) -> Option<ModelInfo>;
-    fn list_models(&self) -> Vec<ModelInfo>;
+        request: ImageGenerationRequest,
+    ) -> ProviderResult<ImageGenerationResponse>;
+    /// Model used when a request names none.
+    fn default_image_model(&self) -> &'static str;
 }
 
 pub mod anthropic;
+pub mod deepseek;
 pub mod google;
+pub mod ollama;
 pub mod openai;
+pub mod openai_compatible;
+pub mod sse;
@@ -0,0 +1,469 @@
+use crate::ai::error::{self, ProviderError, ProviderResult};
+use crate::ai::normalize;
+use crate::ai::providers::{self, AIProvider, openai::OpenAITool, sse};
+use crate::ai::types::{
+    ChatRequest, ChatResponse, ChatResponseChunk, ChatStream, ContentPart, InlineImage, ModelInfo,
+    TokenUsage, ToolCall, ToolChoice,
+};
+use crate::db::models::MessageRole;
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const OLLAMA_DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+/// Ollama's own default `num_ctx`, used when `/api/show` does not report a context length.
+const OLLAMA_DEFAULT_CONTEXT_WINDOW: u32 = 4096;
+
+/// Talks to a local (or proxied) Ollama server. No API key is needed; when one is
+/// configured it is sent as a bearer token for deployments behind an auth proxy.
+pub struct OllamaProvider {
+    base_url: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl OllamaProvider {
+    pub fn new(base_url: Option<String>, api_key: Option<String>) -> Self {
+        let base_url = base_url.unwrap_or_else(|| OLLAMA_DEFAULT_BASE_URL.to_string());
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.filter(|k| !k.is_empty()),
+            client: providers::http_client(),
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let builder = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path));
+        match &self.api_key {
+            Some(api_key) => builder.bearer_auth(api_key),
+            None => builder,
+        }
+    }
+
+    async fn send(&self, request: &OllamaChatRequest) -> ProviderResult<reqwest::Response> {
+        let mut builder = self
+            .request(reqwest::Method::POST, "/api/chat")
+            .json(request);
+        // Streams are bounded by the read timeout alone, however long the answer
+        if !request.stream {
+            builder = builder.timeout(providers::REQUEST_TIMEOUT);
+        }
+        let response = builder.send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let retry_after = error::retry_after(response.headers());
+            let body = response.text().await.unwrap_or_default();
+            // Ollama reports failures as {"error": "..."}
+            let message = serde_json::from_str::<OllamaErrorResponse>(&body)
+                .map(|e| e.error)
+                .unwrap_or(body);
+            return Err(ProviderError::from_status(status, retry_after, message));
+        }
+
+        Ok(response)
+    }
+
+    /// Reads the context length and capabilities of a pulled model from `/api/show`.
+    async fn show(&self, name: &str) -> ProviderResult<OllamaShowResponse> {
+        let response = self
+            .request(reqwest::Method::POST, "/api/show")
+            .json(&serde_json::json!({ "model": name }))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(response.json().await?)
+    }
+}
+
+#[derive(Deserialize)]
+struct OllamaErrorResponse {
+    error: String,
+}
+
+/// Errors reported inside an otherwise successful response body.
+fn inline_error(message: String) -> ProviderError {
+    if message.contains("not found") {
+        ProviderError::ModelNotFound(message)
+    } else {
+        ProviderError::Upstream {
+            status: 500,
+            message,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct OllamaChatRequest {
+    model: String,
+    messages: Vec<OllamaMessage>,
+    stream: bool,
+    options: OllamaOptions,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<OpenAITool>,
+}
+
+impl OllamaChatRequest {
+    fn new(request: ChatRequest, stream: bool) -> Self {
+        // Tool results are matched to calls by name, not by id
+        let mut call_names: HashMap<String, String> = HashMap::new();
+        let mut messages = Vec::new();
+
+        for m in normalize::for_chat_completions(request.messages) {
+            let role = m.role.as_str().to_string();
+            let content = m.text();
+            let mut tool_calls = Vec::new();
+            let mut images = Vec::new();
+
+            for part in m.content {
+                match part {
+                    ContentPart::Image { url, .. } => {
+                        images.extend(InlineImage::parse(&url).map(|image| image.data.to_string()));
+                    }
+                    ContentPart::ToolCall(call) => {
+                        call_names.insert(call.id, call.name.clone());
+                        tool_calls.push(OllamaToolCall {
+                            function: OllamaFunctionCall {
+                                name: call.name,
+                                arguments: call.arguments,
+                            },
+                        });
+                    }
+                    // Each result is a message of its own
+                    ContentPart::ToolResult {
+                        tool_call_id,
+                        content,
+                        ..
+                    } => messages.push(OllamaMessage {
+                        role: role.clone(),
+                        content,
+                        thinking: None,
+                        images: Vec::new(),
+                        tool_calls: Vec::new(),
+                        tool_name: call_names.get(&tool_call_id).cloned(),
+                    }),
+                    _ => {}
+                }
+            }
+
+            if m.role != MessageRole::Tool {
+                messages.push(OllamaMessage {
+                    role,
+                    content,
+                    thinking: None,
+                    images,
+                    tool_calls,
+                    tool_name: None,
+                });
+            }
+        }
+
+        // Ollama has no tool_choice; "none" is honored by not offering the tools
+        let tools = if request.tool_choice == Some(ToolChoice::None) {
+            Vec::new()
+        } else {
+            request.tools.into_iter().map(OpenAITool::from).collect()
+        };
+
+        Self {
+            model: request.model,
+            messages,
+            stream,
+            options: OllamaOptions {
+                temperature: request.temperature,
+                num_predict: request.max_tokens,
+            },
+            tools,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct OllamaOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OllamaMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thinking: Option<String>,
+    /// Base64-encoded images, without a `data:` prefix.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OllamaToolCall>,
+    /// On a `tool` message, the function whose result it carries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_name: Option<String>,
+}
+
+impl OllamaMessage {
+    /// Ollama does not give tool calls ids, so one is generated for each to pair it
+    /// with its result.
+    fn take_tool_calls(&mut self) -> Vec<ToolCall> {
+        std::mem::take(&mut self.tool_calls)
+            .into_iter()
+            .map(|call| ToolCall {
+                id: format!("call_{}", uuid::Uuid::new_v4().simple()),
+                name: call.function.name,
+                arguments: call.function.arguments,
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunctionCall,
+}
+
+/// Unlike OpenAI, Ollama sends and expects `arguments` as a JSON object.
+#[derive(Serialize, Deserialize)]
+struct OllamaFunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
+/// Both the non-streaming reply and every NDJSON line of a streamed one. Token counts
+/// and `done_reason` are only present on the object with `done: true`.
+#[derive(Deserialize)]
+struct OllamaChatResponse {
+    model: String,
+    message: Option<OllamaMessage>,
+    #[serde(default)]
+    done: bool,
+    done_reason: Option<String>,
+    prompt_eval_count: Option<u32>,
+    eval_count: Option<u32>,
+    error: Option<String>,
+}
+
+impl OllamaChatResponse {
+    fn usage(&self) -> Option<TokenUsage> {
+        match (self.prompt_eval_count, self.eval_count) {
+            (None, None) => None,
+            (input, output) => Some(TokenUsage {
+                prompt_tokens: input.unwrap_or(0),
+                completion_tokens: output.unwrap_or(0),
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OllamaTagsResponse {
+    #[serde(default)]
+    models: Vec<OllamaTag>,
+}
+
+#[derive(Deserialize)]
+struct OllamaTag {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct OllamaShowResponse {
+    #[serde(default)]
+    capabilities: Vec<String>,
+    #[serde(default)]
+    model_info: HashMap<String, serde_json::Value>,
+}
+
+impl OllamaShowResponse {
+    /// `model_info` keys are prefixed by architecture, e.g. `llama.context_length`.
+    fn context_length(&self) -> Option<u32> {
+        self.model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+            .map(|value| value.min(u32::MAX as u64) as u32)
+    }
+}
+
+#[async_trait]
+impl AIProvider for OllamaProvider {
+    async fn chat(&self, request: ChatRequest) -> ProviderResult<ChatResponse> {
+        let req = OllamaChatRequest::new(request, false);
+        let response = self.send(&req).await?;
+        let response: OllamaChatResponse = response.json().await?;
+
+        if let Some(error) = response.error {
+            return Err(inline_error(error));
+        }
+
+        let usage = response.usage();
+        let mut message = response.message.unwrap_or(OllamaMessage {
+            role: "assistant".to_string(),
+            content: String::new(),
+            thinking: None,
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+            tool_name: None,
+        });
+
+        Ok(ChatResponse {
+            tool_calls: message.take_tool_calls(),
+            content: message.content,
+            reasoning: message.thinking.filter(|t| !t.is_empty()),
+            model: response.model,
+            usage,
+            finish_reason: response.done_reason,
+        })
+    }
+
+    async fn stream_chat(&self, request: ChatRequest) -> ProviderResult<ChatStream> {
+        let req = OllamaChatRequest::new(request, true);
+        let response = self.send(&req).await?;
+
+        // Each NDJSON line maps to one chunk; the `done` line carries the usage
+        let stream = sse::lines(response).filter_map(|line| async move {
+            let line = match line {
+                Ok(line) if line.trim().is_empty() => return None,
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let response: OllamaChatResponse = match serde_json::from_str(&line) {
+                Ok(response) => response,
+                Err(e) => return Some(Err(e.into())),
+            };
+
+            if let Some(error) = response.error {
+                return Some(Err(inline_error(error)));
+            }
+
+            let usage = response.usage();
+            let (content, reasoning, tool_calls) = response
+                .message
+                .map(|mut m| {
+                    let tool_calls = m.take_tool_calls();
+                    (m.content, m.thinking.filter(|t| !t.is_empty()), tool_calls)
+                })
+                .unwrap_or_default();
+
+            if !response.done && content.is_empty() && reasoning.is_none() && tool_calls.is_empty()
+            {
+                return None;
+            }
+
+            Some(Ok(ChatResponseChunk {
+                content,
+                reasoning,
+                tool_calls,
+                done: response.done,
+                model: Some(response.model),
+                usage,
+                finish_reason: response.done_reason,
+            }))
+        });
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn discover_models(&self) -> ProviderResult<Vec<ModelInfo>> {
+        let tags: OllamaTagsResponse = self
+            .request(reqwest::Method::GET, "/api/tags")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let mut models = Vec::with_capacity(tags.models.len());
+        for tag in tags.models {
+            let (context_window, supports_images, supports_functions) =
+                match self.show(&tag.name).await {
+                    Ok(show) => (
+                        show.context_length()
+                            .unwrap_or(OLLAMA_DEFAULT_CONTEXT_WINDOW),
+                        show.capabilities.iter().any(|c| c == "vision"),
+                        show.capabilities.iter().any(|c| c == "tools"),
+                    ),
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed to read Ollama model details for {}: {}",
+                            tag.name,
+                            e
+                        );
+                        (OLLAMA_DEFAULT_CONTEXT_WINDOW, false, false)
+                    }
+                };
+
+            models.push(ModelInfo {
+                id: tag.name.clone(),
+                display_name: tag.name,
+                context_window,
+                supports_streaming: true,
+                supports_images,
+                supports_functions,
+            });
+        }
+
+        Ok(models)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::types::ChatMessage;
+
+    fn request(messages: Vec<ChatMessage>) -> ChatRequest {
+        ChatRequest {
+            model: "llama3.1".to_string(),
+            messages,
+            temperature: None,
+            max_tokens: None,
+            stream: false,
+            tools: Vec::new(),
+            tool_choice: None,
+        }
+    }
+
+    fn call(id: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            name: "calculator".to_string(),
+            arguments: serde_json::json!({ "expression": "1 + 1" }),
+        }
+    }
+
+    #[test]
+    fn system_messages_lead_and_tool_results_carry_the_function_name() {
+        let body = serde_json::to_value(OllamaChatRequest::new(
+            request(vec![
+                ChatMessage::new(MessageRole::User, "Hi"),
+                ChatMessage::new(MessageRole::System, "Be brief."),
+                ChatMessage::new(MessageRole::User, "Add these"),
+                ChatMessage::assistant(String::new(), None, vec![call("a"), call("b")]),
+                ChatMessage::tool_result("a".to_string(), "2".to_string(), false),
+                ChatMessage::tool_result("b".to_string(), "2".to_string(), false),
+            ]),
+            false,
+        ))
+        .unwrap();
+        let messages = body["messages"].as_array().unwrap();
+
+        let roles: Vec<&str> = messages
+            .iter()
+            .map(|m| m["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, ["system", "user", "assistant", "tool", "tool"]);
+        assert_eq!(messages[0]["content"], "Be brief.");
+        assert_eq!(messages[1]["content"], "Hi\n\nAdd these");
+        assert!(messages[3..].iter().all(|m| m["tool_name"] == "calculator"));
+    }
+}
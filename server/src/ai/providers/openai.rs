@@ -1,155 +1,827 @@
-use crate::ai::providers::AIProvider;
-use crate::ai::types::{ChatRequest, ChatResponse, ChatResponseChunk, ModelInfo};
+use crate::ai::error::{self, ProviderError, ProviderResult};
+use crate::ai::normalize;
+use crate::ai::providers::{self, AIProvider, ImageProvider, sse};
+use crate::ai::types::{
+    ChatMessage, ChatRequest, ChatResponse, ChatResponseChunk, ChatStream, ContentPart,
+    GeneratedImage, ImageGenerationRequest, ImageGenerationResponse, ModelInfo, TokenUsage,
+    ToolCall, ToolChoice, ToolDefinition,
+};
+use crate::db::models::MessageRole;
 use async_trait::async_trait;
-use futures::Stream;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+const OPENAI_API_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// `/models` reports no context sizes; this is what current GPT-4o-class models accept.
+const OPENAI_DEFAULT_CONTEXT_WINDOW: u32 = 128000;
+
+/// `/models` also lists embedding, audio, image and moderation models, which cannot be
+/// used through chat completions.
+fn is_chat_model(id: &str) -> bool {
+    let chat_family = ["gpt-", "chatgpt-", "o1", "o3", "o4"]
+        .iter()
+        .any(|prefix| id.starts_with(prefix));
+    let excluded = [
+        "instruct",
+        "embedding",
+        "audio",
+        "realtime",
+        "transcribe",
+        "tts",
+        "image",
+        "search",
+    ]
+    .iter()
+    .any(|part| id.contains(part));
+
+    chat_family && !excluded
+}
+
+/// The ChatGPT aliases and the first o1 previews are the only chat models that reject
+/// `tools`.
+fn supports_functions(id: &str) -> bool {
+    !["chatgpt-", "o1-mini", "o1-preview"]
+        .iter()
+        .any(|prefix| id.starts_with(prefix))
+}
 
 pub struct OpenAIProvider {
     api_key: String,
+    base_url: String,
+    extra_headers: HashMap<String, String>,
     client: reqwest::Client,
 }
 
 impl OpenAIProvider {
     pub fn new(api_key: String) -> Self {
+        Self::with_base_url(api_key, OPENAI_API_BASE_URL.to_string())
+    }
+
+    /// Targets another server that speaks the chat-completions dialect.
+    /// `base_url` is everything before `/chat/completions`.
+    pub fn with_base_url(api_key: String, base_url: String) -> Self {
         Self {
             api_key,
-            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            extra_headers: HashMap::new(),
+            client: providers::http_client(),
         }
     }
-}
 
-#[async_trait]
-impl AIProvider for OpenAIProvider {
-    async fn chat(&self, request: ChatRequest) -> anyhow::Result<ChatResponse> {
-        #[derive(Serialize)]
-        struct OpenAIRequest {
-            model: String,
-            messages: Vec<OpenAIMessage>,
-            temperature: Option<f32>,
-            max_tokens: Option<u32>,
+    /// Adds headers sent with every request, e.g. OpenRouter's `HTTP-Referer`.
+    pub fn with_extra_headers(mut self, extra_headers: HashMap<String, String>) -> Self {
+        self.extra_headers = extra_headers;
+        self
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let mut builder = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path));
+
+        // Self-hosted servers such as vLLM are often run without authentication
+        if !self.api_key.is_empty() {
+            builder = builder.bearer_auth(&self.api_key);
         }
+        for (name, value) in &self.extra_headers {
+            builder = builder.header(name, value);
+        }
+
+        builder
+    }
 
-        #[derive(Serialize, Deserialize)]
-        struct OpenAIMessage {
-            role: String,
-            content: String,
+    async fn send(&self, request: &OpenAIRequest) -> ProviderResult<reqwest::Response> {
+        let mut builder = self
+            .request(reqwest::Method::POST, "/chat/completions")
+            .header("Content-Type", "application/json")
+            .json(request);
+        // Streams are bounded by the read timeout alone, however long the answer
+        if !request.stream {
+            builder = builder.timeout(providers::REQUEST_TIMEOUT);
         }
+        let response = builder.send().await?;
 
-        #[derive(Deserialize)]
-        struct OpenAIResponse {
-            choices: Vec<OpenAIChoice>,
-            model: String,
-            usage: Option<OpenAIUsage>,
+        if !response.status().is_success() {
+            return Err(error_from_response(response).await);
         }
 
-        #[derive(Deserialize)]
-        struct OpenAIChoice {
-            message: OpenAIMessage,
-            finish_reason: Option<String>,
+        Ok(response)
+    }
+
+    /// Lists the model ids served at `{base_url}/models`.
+    pub async fn fetch_model_ids(&self) -> ProviderResult<Vec<String>> {
+        let response = self.request(reqwest::Method::GET, "/models").send().await?;
+
+        if !response.status().is_success() {
+            return Err(error_from_response(response).await);
         }
 
-        #[derive(Deserialize)]
-        struct OpenAIUsage {
-            total_tokens: u32,
+        let response: OpenAIModelList = response.json().await?;
+
+        Ok(response.data.into_iter().map(|m| m.id).collect())
+    }
+}
+
+/// Maps an `{"error": {"message", "type", "code"}}` payload, which DeepSeek and most
+/// OpenAI-compatible servers also use, to a `ProviderError`.
+async fn error_from_response(response: reqwest::Response) -> ProviderError {
+    let status = response.status();
+    let retry_after = error::retry_after(response.headers());
+    let body = response.text().await.unwrap_or_default();
+
+    let Ok(OpenAIErrorResponse { error }) = serde_json::from_str::<OpenAIErrorResponse>(&body)
+    else {
+        return ProviderError::from_status(status, retry_after, body);
+    };
+
+    let code = error
+        .code
+        .as_ref()
+        .and_then(|code| code.as_str())
+        .or(error.kind.as_deref())
+        .unwrap_or_default();
+    match code {
+        "invalid_api_key" | "invalid_authentication" => ProviderError::InvalidApiKey(error.message),
+        "context_length_exceeded" | "string_above_max_length" => {
+            ProviderError::ContextLengthExceeded(error.message)
+        }
+        "content_filter" | "content_policy_violation" => {
+            ProviderError::ContentFiltered(error.message)
         }
+        "model_not_found" => ProviderError::ModelNotFound(error.message),
+        // Retrying does not help an account that is out of credit
+        "insufficient_quota" => ProviderError::InvalidRequest(error.message),
+        _ => ProviderError::from_status(status, retry_after, error.message),
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenAIErrorResponse {
+    error: OpenAIError,
+}
+
+#[derive(Deserialize)]
+struct OpenAIError {
+    #[serde(default)]
+    message: String,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    /// A string for OpenAI, but some compatible servers send a number.
+    code: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIModelList {
+    #[serde(default)]
+    data: Vec<OpenAIModel>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIModel {
+    id: String,
+}
+
+#[derive(Serialize)]
+struct OpenAIImageRequest<'a> {
+    model: &'a str,
+    prompt: &'a str,
+    n: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<&'static str>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIImageResponse {
+    #[serde(default)]
+    data: Vec<OpenAIImageData>,
+    /// `png`, `jpeg` or `webp`; only gpt-image models report it
+    output_format: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIImageData {
+    b64_json: Option<String>,
+    url: Option<String>,
+    revised_prompt: Option<String>,
+}
 
-        let messages: Vec<OpenAIMessage> = request
-            .messages
+#[derive(Serialize)]
+struct OpenAIRequest {
+    model: String,
+    messages: Vec<OpenAIMessage>,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<OpenAIStreamOptions>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<OpenAITool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
+}
+
+impl OpenAIRequest {
+    fn new(request: ChatRequest, stream: bool) -> Self {
+        let messages = normalize::for_chat_completions(request.messages)
             .into_iter()
-            .map(|m| OpenAIMessage {
-                role: match m.role {
-                    crate::db::models::MessageRole::User => "user".to_string(),
-                    crate::db::models::MessageRole::Assistant => "assistant".to_string(),
-                    crate::db::models::MessageRole::System => "system".to_string(),
-                },
-                content: m.content,
-            })
+            .flat_map(OpenAIMessage::from_message)
             .collect();
+        let tool_choice = request.tool_choice.map(|choice| match choice {
+            ToolChoice::Auto => serde_json::json!("auto"),
+            ToolChoice::None => serde_json::json!("none"),
+            ToolChoice::Required => serde_json::json!("required"),
+            ToolChoice::Tool { name } => {
+                serde_json::json!({ "type": "function", "function": { "name": name } })
+            }
+        });
 
-        let req = OpenAIRequest {
+        Self {
             model: request.model,
             messages,
             temperature: request.temperature,
             max_tokens: request.max_tokens,
+            stream,
+            // Without this the final usage chunk is never sent on a streamed completion
+            stream_options: stream.then_some(OpenAIStreamOptions {
+                include_usage: true,
+            }),
+            tools: request.tools.into_iter().map(OpenAITool::from).collect(),
+            tool_choice,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct OpenAIStreamOptions {
+    include_usage: bool,
+}
+
+/// Also the tool format of Ollama's native chat API.
+#[derive(Serialize)]
+pub(super) struct OpenAITool {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: OpenAIFunction,
+}
+
+impl From<ToolDefinition> for OpenAITool {
+    fn from(tool: ToolDefinition) -> Self {
+        Self {
+            kind: "function",
+            function: OpenAIFunction {
+                name: tool.name,
+                description: tool.description,
+                parameters: tool.parameters,
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct OpenAIFunction {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    parameters: serde_json::Value,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct OpenAIMessage {
+    role: String,
+    /// `null` on assistant turns that only call tools.
+    #[serde(default)]
+    content: Option<OpenAIContent>,
+    /// Chain of thought returned by reasoning models such as deepseek-reasoner.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reasoning_content: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OpenAIToolCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+impl OpenAIMessage {
+    /// Each tool result becomes a `tool` message of its own; other turns map one to one.
+    fn from_message(message: ChatMessage) -> Vec<Self> {
+        if message.role == MessageRole::Tool {
+            return message
+                .content
+                .into_iter()
+                .filter_map(|part| match part {
+                    ContentPart::ToolResult {
+                        tool_call_id,
+                        content,
+                        ..
+                    } => Some(Self {
+                        role: MessageRole::Tool.as_str().to_string(),
+                        content: Some(OpenAIContent::Text(content)),
+                        tool_call_id: Some(tool_call_id),
+                        ..Default::default()
+                    }),
+                    _ => None,
+                })
+                .collect();
+        }
+
+        let text = message.text();
+        let mut images = Vec::new();
+        let mut tool_calls = Vec::new();
+        for part in message.content {
+            match part {
+                // Data URLs are accepted as they are
+                ContentPart::Image { url, .. } => images.push(OpenAIContentPart::ImageUrl {
+                    image_url: OpenAIImageUrl { url },
+                }),
+                ContentPart::ToolCall(call) => tool_calls.push(OpenAIToolCall::from(call)),
+                _ => {}
+            }
+        }
+
+        let content = if images.is_empty() {
+            (!text.is_empty() || tool_calls.is_empty()).then_some(OpenAIContent::Text(text))
+        } else {
+            let mut parts = Vec::with_capacity(1 + images.len());
+            if !text.is_empty() {
+                parts.push(OpenAIContentPart::Text { text });
+            }
+            parts.extend(images);
+            Some(OpenAIContent::Parts(parts))
         };
 
-        let response = self
-            .client
-            .post("https://api.openai.com/v1/chat/completions")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&req)
-            .send()
-            .await?;
+        vec![Self {
+            role: message.role.as_str().to_string(),
+            content,
+            reasoning_content: None,
+            tool_calls,
+            tool_call_id: None,
+        }]
+    }
+}
+
+/// A string, or a list of parts on user turns that carry images.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum OpenAIContent {
+    Text(String),
+    Parts(Vec<OpenAIContentPart>),
+}
+
+impl OpenAIContent {
+    fn into_text(self) -> String {
+        match self {
+            OpenAIContent::Text(text) => text,
+            OpenAIContent::Parts(parts) => parts
+                .into_iter()
+                .filter_map(|part| match part {
+                    OpenAIContentPart::Text { text } => Some(text),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OpenAIContentPart {
+    Text {
+        text: String,
+    },
+    ImageUrl {
+        image_url: OpenAIImageUrl,
+    },
+    /// Part types we do not send, such as `refusal`.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OpenAIImageUrl {
+    url: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct OpenAIToolCall {
+    #[serde(default)]
+    id: String,
+    #[serde(rename = "type", default = "function_kind")]
+    kind: String,
+    function: OpenAIFunctionCall,
+}
+
+fn function_kind() -> String {
+    "function".to_string()
+}
 
+/// `arguments` is a JSON document encoded as a string.
+#[derive(Default, Serialize, Deserialize)]
+struct OpenAIFunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: String,
+}
+
+impl From<ToolCall> for OpenAIToolCall {
+    fn from(call: ToolCall) -> Self {
+        Self {
+            id: call.id,
+            kind: function_kind(),
+            function: OpenAIFunctionCall {
+                name: call.name,
+                arguments: call.arguments.to_string(),
+            },
+        }
+    }
+}
+
+impl From<OpenAIToolCall> for ToolCall {
+    fn from(call: OpenAIToolCall) -> Self {
+        ToolCall::from_json_arguments(call.id, call.function.name, &call.function.arguments)
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenAIResponse {
+    choices: Vec<OpenAIChoice>,
+    model: String,
+    usage: Option<OpenAIUsage>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIChoice {
+    message: OpenAIMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIUsage {
+    #[serde(default)]
+    prompt_tokens: u32,
+    #[serde(default)]
+    completion_tokens: u32,
+    prompt_tokens_details: Option<OpenAIPromptTokensDetails>,
+    completion_tokens_details: Option<OpenAICompletionTokensDetails>,
+    /// DeepSeek reports its cache hits here rather than in `prompt_tokens_details`
+    prompt_cache_hit_tokens: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIPromptTokensDetails {
+    cached_tokens: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct OpenAICompletionTokensDetails {
+    reasoning_tokens: Option<u32>,
+}
+
+impl From<OpenAIUsage> for TokenUsage {
+    fn from(usage: OpenAIUsage) -> Self {
+        Self {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            cached_tokens: usage
+                .prompt_tokens_details
+                .and_then(|d| d.cached_tokens)
+                .or(usage.prompt_cache_hit_tokens),
+            reasoning_tokens: usage
+                .completion_tokens_details
+                .and_then(|d| d.reasoning_tokens),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenAIStreamChunk {
+    model: Option<String>,
+    #[serde(default)]
+    choices: Vec<OpenAIStreamChoice>,
+    usage: Option<OpenAIUsage>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIStreamChoice {
+    #[serde(default)]
+    delta: OpenAIDelta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+struct OpenAIDelta {
+    content: Option<String>,
+    reasoning_content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<OpenAIToolCallDelta>,
+}
+
+/// A fragment of a streamed tool call. The id and name arrive on the first fragment
+/// for an `index`, the arguments string is spread across the rest.
+#[derive(Deserialize)]
+struct OpenAIToolCallDelta {
+    index: usize,
+    id: Option<String>,
+    function: Option<OpenAIFunctionDelta>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIFunctionDelta {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+/// Tracks what has been seen so far on a streamed completion so the final chunk can
+/// report the model, usage and finish reason even though they arrive on separate events.
+struct OpenAIStreamState {
+    events: std::pin::Pin<Box<dyn futures::Stream<Item = ProviderResult<sse::SseEvent>> + Send>>,
+    model: String,
+    usage: Option<OpenAIUsage>,
+    finish_reason: Option<String>,
+    /// Tool calls being assembled, keyed by their index in the response.
+    tool_calls: BTreeMap<usize, OpenAIToolCall>,
+    finished: bool,
+}
+
+impl OpenAIStreamState {
+    fn final_chunk(&mut self) -> ChatResponseChunk {
+        self.finished = true;
+        ChatResponseChunk {
+            content: String::new(),
+            reasoning: None,
+            tool_calls: std::mem::take(&mut self.tool_calls)
+                .into_values()
+                .map(ToolCall::from)
+                .collect(),
+            done: true,
+            model: Some(self.model.clone()),
+            usage: self.usage.take().map(TokenUsage::from),
+            finish_reason: self.finish_reason.take(),
+        }
+    }
+}
+
+#[async_trait]
+impl AIProvider for OpenAIProvider {
+    async fn chat(&self, request: ChatRequest) -> ProviderResult<ChatResponse> {
+        let req = OpenAIRequest::new(request, false);
+        let response = self.send(&req).await?;
         let response: OpenAIResponse = response.json().await?;
+        let (message, finish_reason) = match response.choices.into_iter().next() {
+            Some(choice) => (choice.message, choice.finish_reason),
+            None => (OpenAIMessage::default(), None),
+        };
 
         Ok(ChatResponse {
-            content: response
-                .choices
-                .first()
-                .map(|c| c.message.content.clone())
+            content: message
+                .content
+                .map(OpenAIContent::into_text)
                 .unwrap_or_default(),
+            reasoning: message.reasoning_content.filter(|r| !r.is_empty()),
+            tool_calls: message.tool_calls.into_iter().map(ToolCall::from).collect(),
             model: response.model,
-            tokens_used: response.usage.map(|u| u.total_tokens),
-            finish_reason: response
-                .choices
-                .first()
-                .and_then(|c| c.finish_reason.clone()),
+            usage: response.usage.map(TokenUsage::from),
+            finish_reason,
         })
     }
 
-    async fn stream_chat(
-        &self,
-        request: ChatRequest,
-    ) -> anyhow::Result<impl Stream<Item = anyhow::Result<ChatResponseChunk>>> {
-        // For now, return a simple implementation
-        // Full SSE streaming implementation would go here
-        let response = self.chat(request).await?;
-        Ok(futures::stream::iter(vec![Ok(ChatResponseChunk {
-            content: response.content,
-            done: true,
-            model: Some(response.model),
-        })]))
+    async fn stream_chat(&self, request: ChatRequest) -> ProviderResult<ChatStream> {
+        let model = request.model.clone();
+        let req = OpenAIRequest::new(request, true);
+        let response = self.send(&req).await?;
+
+        let state = OpenAIStreamState {
+            events: Box::pin(sse::events(response)),
+            model,
+            usage: None,
+            finish_reason: None,
+            tool_calls: BTreeMap::new(),
+            finished: false,
+        };
+
+        let stream = futures::stream::unfold(state, |mut state| async move {
+            if state.finished {
+                return None;
+            }
+
+            loop {
+                let event = match state.events.next().await {
+                    Some(Ok(event)) => event,
+                    Some(Err(e)) => {
+                        state.finished = true;
+                        return Some((Err(e), state));
+                    }
+                    // The connection closed without [DONE]; the answer was cut short
+                    None => {
+                        state.finished = true;
+                        return Some((Err(ProviderError::stream_cut_short()), state));
+                    }
+                };
+
+                if event.data == "[DONE]" {
+                    let chunk = state.final_chunk();
+                    return Some((Ok(chunk), state));
+                }
+
+                let chunk: OpenAIStreamChunk = match serde_json::from_str(&event.data) {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        state.finished = true;
+                        return Some((Err(e.into()), state));
+                    }
+                };
+
+                if let Some(model) = chunk.model {
+                    state.model = model;
+                }
+                if chunk.usage.is_some() {
+                    state.usage = chunk.usage;
+                }
+
+                let Some(choice) = chunk.choices.into_iter().next() else {
+                    continue;
+                };
+                if choice.finish_reason.is_some() {
+                    state.finish_reason = choice.finish_reason;
+                }
+                for delta in choice.delta.tool_calls {
+                    let call = state.tool_calls.entry(delta.index).or_default();
+                    if let Some(id) = delta.id {
+                        call.id = id;
+                    }
+                    if let Some(function) = delta.function {
+                        if let Some(name) = function.name {
+                            call.function.name = name;
+                        }
+                        if let Some(arguments) = function.arguments {
+                            call.function.arguments.push_str(&arguments);
+                        }
+                    }
+                }
+
+                let content = choice.delta.content.unwrap_or_default();
+                let reasoning = choice.delta.reasoning_content.filter(|r| !r.is_empty());
+                if content.is_empty() && reasoning.is_none() {
+                    continue;
+                }
+
+                let chunk = ChatResponseChunk {
+                    content,
+                    reasoning,
+                    done: false,
+                    model: Some(state.model.clone()),
+                    ..Default::default()
+                };
+                return Some((Ok(chunk), state));
+            }
+        });
+
+        Ok(Box::pin(stream))
     }
 
-    fn get_model_info(&self, model_id: &str) -> Option<ModelInfo> {
-        // Common OpenAI models
-        match model_id {
-            "gpt-4" => Some(ModelInfo {
-                id: "gpt-4".to_string(),
-                display_name: "GPT-4".to_string(),
-                context_window: 8192,
-                supports_streaming: true,
-                supports_images: false,
-            }),
-            "gpt-3.5-turbo" => Some(ModelInfo {
-                id: "gpt-3.5-turbo".to_string(),
-                display_name: "GPT-3.5 Turbo".to_string(),
-                context_window: 4096,
+    async fn discover_models(&self) -> ProviderResult<Vec<ModelInfo>> {
+        let ids = self.fetch_model_ids().await?;
+
+        Ok(ids
+            .into_iter()
+            .filter(|id| is_chat_model(id))
+            .map(|id| ModelInfo {
+                display_name: id.clone(),
+                context_window: OPENAI_DEFAULT_CONTEXT_WINDOW,
                 supports_streaming: true,
-                supports_images: false,
-            }),
-            _ => None,
+                supports_images: ["gpt-4o", "gpt-4.1", "gpt-4-turbo", "gpt-5", "o3", "o4"]
+                    .iter()
+                    .any(|prefix| id.starts_with(prefix)),
+                supports_functions: supports_functions(&id),
+                id,
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl ImageProvider for OpenAIProvider {
+    async fn generate_images(
+        &self,
+        request: ImageGenerationRequest,
+    ) -> ProviderResult<ImageGenerationResponse> {
+        // DALL·E answers with short-lived links unless asked for the data; gpt-image
+        // models always return it and reject the parameter
+        let body = OpenAIImageRequest {
+            model: &request.model,
+            prompt: &request.prompt,
+            n: request.n,
+            size: request.size.as_deref(),
+            response_format: request.model.starts_with("dall-e").then_some("b64_json"),
+        };
+        let response = self
+            .request(reqwest::Method::POST, "/images/generations")
+            .json(&body)
+            .timeout(providers::REQUEST_TIMEOUT)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(error_from_response(response).await);
         }
+
+        let response: OpenAIImageResponse = response.json().await?;
+        let mime_type = match response.output_format.as_deref() {
+            Some("jpeg" | "jpg") => "image/jpeg",
+            Some("webp") => "image/webp",
+            _ => "image/png",
+        };
+
+        let mut images = Vec::with_capacity(response.data.len());
+        for image in response.data {
+            let data = match (image.b64_json, image.url) {
+                (Some(data), _) => STANDARD
+                    .decode(data)
+                    .map_err(|e| ProviderError::Other(e.into()))?,
+                (None, Some(url)) => self
+                    .client
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await?
+                    .to_vec(),
+                (None, None) => continue,
+            };
+            images.push(GeneratedImage {
+                mime_type: mime_type.to_string(),
+                data,
+                revised_prompt: image.revised_prompt,
+            });
+        }
+
+        Ok(ImageGenerationResponse {
+            model: request.model,
+            images,
+        })
     }
 
-    fn list_models(&self) -> Vec<ModelInfo> {
-        vec![
-            ModelInfo {
-                id: "gpt-4".to_string(),
-                display_name: "GPT-4".to_string(),
-                context_window: 8192,
-                supports_streaming: true,
-                supports_images: false,
-            },
-            ModelInfo {
-                id: "gpt-3.5-turbo".to_string(),
-                display_name: "GPT-3.5 Turbo".to_string(),
-                context_window: 4096,
-                supports_streaming: true,
-                supports_images: false,
-            },
-        ]
+    fn default_image_model(&self) -> &'static str {
+        "gpt-image-1"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(messages: Vec<ChatMessage>) -> ChatRequest {
+        ChatRequest {
+            model: "gpt-4o".to_string(),
+            messages,
+            temperature: None,
+            max_tokens: None,
+            stream: false,
+            tools: Vec::new(),
+            tool_choice: None,
+        }
+    }
+
+    fn call(id: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            name: "calculator".to_string(),
+            arguments: serde_json::json!({ "expression": "1 + 1" }),
+        }
+    }
+
+    #[test]
+    fn system_messages_lead_and_same_role_turns_merge() {
+        let body = serde_json::to_value(OpenAIRequest::new(
+            request(vec![
+                ChatMessage::new(MessageRole::System, "Be brief."),
+                ChatMessage::new(MessageRole::User, "Hi"),
+                ChatMessage::new(MessageRole::System, "Answer in French."),
+                ChatMessage::new(MessageRole::User, "Add these"),
+                ChatMessage::assistant(String::new(), None, vec![call("a"), call("b")]),
+                ChatMessage::tool_result("a".to_string(), "2".to_string(), false),
+                ChatMessage::tool_result("b".to_string(), "2".to_string(), false),
+            ]),
+            false,
+        ))
+        .unwrap();
+        let messages = body["messages"].as_array().unwrap();
+
+        let roles: Vec<&str> = messages
+            .iter()
+            .map(|m| m["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, ["system", "user", "assistant", "tool", "tool"]);
+        assert_eq!(messages[0]["content"], "Be brief.\n\nAnswer in French.");
+        assert_eq!(messages[1]["content"], "Hi\n\nAdd these");
+        assert_eq!(messages[2]["tool_calls"].as_array().unwrap().len(), 2);
+        assert_eq!(messages[3]["tool_call_id"], "a");
+        assert_eq!(messages[4]["tool_call_id"], "b");
     }
 }
@@ -0,0 +1,54 @@
+use crate::ai::error::ProviderResult;
+use crate::ai::providers::{AIProvider, openai::OpenAIProvider};
+use crate::ai::types::{ChatRequest, ChatResponse, ChatStream, ModelInfo};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+/// Context window assumed for models discovered through `/models`, which does not
+/// report one. Catalog rows can override it.
+const DEFAULT_CONTEXT_WINDOW: u32 = 8192;
+
+/// Any server speaking the OpenAI chat-completions dialect: vLLM, LM Studio, the
+/// llama.cpp server, OpenRouter, Groq and the like. The base URL and extra headers
+/// come from the user's key or the `ai_models` row instead of being hard-coded.
+pub struct OpenAICompatibleProvider {
+    inner: OpenAIProvider,
+}
+
+impl OpenAICompatibleProvider {
+    pub fn new(api_key: String, base_url: String, extra_headers: HashMap<String, String>) -> Self {
+        Self {
+            inner: OpenAIProvider::with_base_url(api_key, base_url)
+                .with_extra_headers(extra_headers),
+        }
+    }
+}
+
+#[async_trait]
+impl AIProvider for OpenAICompatibleProvider {
+    async fn chat(&self, request: ChatRequest) -> ProviderResult<ChatResponse> {
+        self.inner.chat(request).await
+    }
+
+    async fn stream_chat(&self, request: ChatRequest) -> ProviderResult<ChatStream> {
+        self.inner.stream_chat(request).await
+    }
+
+    async fn discover_models(&self) -> ProviderResult<Vec<ModelInfo>> {
+        let ids = self.inner.fetch_model_ids().await?;
+
+        Ok(ids
+            .into_iter()
+            .map(|id| ModelInfo {
+                display_name: id.clone(),
+                id,
+                context_window: DEFAULT_CONTEXT_WINDOW,
+                supports_streaming: true,
+                supports_images: false,
+                // `/models` does not say; tools are offered and the server rejects
+                // them if it cannot call them
+                supports_functions: true,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,97 @@
+use crate::ai::error::ProviderResult;
+use futures::{Stream, StreamExt};
+
+/// A single server-sent event as framed by the `event:`/`data:` fields.
+#[derive(Debug, Clone, Default)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Splits a streaming HTTP body into lines. Bytes are buffered until a full line is
+/// available so multi-byte characters split across network chunks are decoded intact.
+pub fn lines(response: reqwest::Response) -> impl Stream<Item = ProviderResult<String>> + Send {
+    let state = (Box::pin(response.bytes_stream()), Vec::<u8>::new(), false);
+
+    futures::stream::unfold(state, |(mut bytes, mut buffer, mut finished)| async move {
+        loop {
+            if let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                let mut line: Vec<u8> = buffer.drain(..=pos).collect();
+                line.pop();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                let line = String::from_utf8_lossy(&line).into_owned();
+                return Some((Ok(line), (bytes, buffer, finished)));
+            }
+
+            if finished {
+                if buffer.is_empty() {
+                    return None;
+                }
+                let line = String::from_utf8_lossy(&std::mem::take(&mut buffer)).into_owned();
+                return Some((Ok(line), (bytes, buffer, finished)));
+            }
+
+            match bytes.next().await {
+                Some(Ok(chunk)) => buffer.extend_from_slice(&chunk),
+                Some(Err(e)) => {
+                    finished = true;
+                    buffer.clear();
+                    return Some((Err(e.into()), (bytes, buffer, finished)));
+                }
+                None => finished = true,
+            }
+        }
+    })
+}
+
+/// Parses a `text/event-stream` body into events. Comment lines (`: ping`) are skipped
+/// and multi-line `data:` fields are joined with `\n` as the SSE spec requires.
+pub fn events(response: reqwest::Response) -> impl Stream<Item = ProviderResult<SseEvent>> + Send {
+    let state = (Box::pin(lines(response)), SseEvent::default());
+
+    futures::stream::unfold(state, |(mut lines, mut event)| async move {
+        loop {
+            match lines.next().await {
+                Some(Ok(line)) => {
+                    if line.is_empty() {
+                        if event.event.is_none() && event.data.is_empty() {
+                            continue;
+                        }
+                        let ready = std::mem::take(&mut event);
+                        return Some((Ok(ready), (lines, event)));
+                    }
+
+                    if line.starts_with(':') {
+                        continue;
+                    }
+
+                    let (field, value) = match line.split_once(':') {
+                        Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                        None => (line.as_str(), ""),
+                    };
+
+                    match field {
+                        "event" => event.event = Some(value.to_string()),
+                        "data" => {
+                            if !event.data.is_empty() {
+                                event.data.push('\n');
+                            }
+                            event.data.push_str(value);
+                        }
+                        _ => {}
+                    }
+                }
+                Some(Err(e)) => return Some((Err(e), (lines, event))),
+                None => {
+                    if event.event.is_none() && event.data.is_empty() {
+                        return None;
+                    }
+                    let ready = std::mem::take(&mut event);
+                    return Some((Ok(ready), (lines, event)));
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,100 @@
+use crate::ai::error::ProviderResult;
+use crate::db::models::AiProvider;
+use crate::env::get_ai_retry_setting;
+use std::future::Future;
+use std::time::Duration;
+
+const DEFAULT_MAX_RETRIES: u32 = 2;
+const DEFAULT_BASE_DELAY_MS: u64 = 500;
+const DEFAULT_MAX_DELAY_MS: u64 = 30_000;
+
+/// How often and how patiently a provider call is retried after a transient failure
+/// (rate limiting, 5xx, Anthropic's `overloaded_error`, timeouts).
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_delay: Duration::from_millis(DEFAULT_BASE_DELAY_MS),
+            max_delay: Duration::from_millis(DEFAULT_MAX_DELAY_MS),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Reads `<PROVIDER>_RETRY_MAX_RETRIES`, `_BASE_DELAY_MS` and `_MAX_DELAY_MS`
+    /// (e.g. `ANTHROPIC_RETRY_MAX_RETRIES`), falling back to `AI_RETRY_*` and then to
+    /// the defaults.
+    pub fn for_provider(provider: AiProvider) -> Self {
+        let provider = provider.as_str();
+        let defaults = Self::default();
+
+        Self {
+            max_retries: get_ai_retry_setting(provider, "MAX_RETRIES")
+                .map(|n| n.min(u32::MAX as u64) as u32)
+                .unwrap_or(defaults.max_retries),
+            base_delay: get_ai_retry_setting(provider, "BASE_DELAY_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.base_delay),
+            max_delay: get_ai_retry_setting(provider, "MAX_DELAY_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.max_delay),
+        }
+    }
+
+    /// Runs `operation` until it succeeds, fails with a non-transient error or runs out
+    /// of retries. Only wrap calls that have not produced output yet: a retried stream
+    /// would repeat content the client has already seen.
+    pub async fn run<T, F, Fut>(&self, mut operation: F) -> ProviderResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ProviderResult<T>>,
+    {
+        let mut attempt = 0;
+
+        loop {
+            let error = match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+
+            if attempt >= self.max_retries || !error.is_retryable() {
+                return Err(error);
+            }
+
+            let delay = match error.retry_after() {
+                // A provider asking for a longer pause than we are willing to wait is
+                // better reported to the user than silently held
+                Some(retry_after) if retry_after > self.max_delay => return Err(error),
+                Some(retry_after) => retry_after,
+                None => self.backoff(attempt),
+            };
+
+            attempt += 1;
+            tracing::warn!(
+                "Provider call failed ({}); retry {}/{} in {:?}",
+                error,
+                attempt,
+                self.max_retries,
+                delay
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Exponential backoff with jitter: a random delay between half and all of
+    /// `base_delay * 2^attempt`, capped at `max_delay`.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        exponential.mul_f64(0.5 + rand::random::<f64>() * 0.5)
+    }
+}
@@ -0,0 +1,101 @@
+//! Rolling summaries of long chats. Once a chat outgrows its model's context window,
+//! the turns that would be left out are folded into a running summary that is sent in
+//! their place. Each update reads only the previous summary and the turns it now
+//! covers, so its cost does not grow with the chat.
+
+use crate::ai::types::{ChatMessage, ChatRequest, ContentPart};
+use crate::db::models::MessageRole;
+
+/// Longest summary asked of the model.
+const MAX_SUMMARY_TOKENS: u32 = 1024;
+
+/// Most characters of one message passed to the summarizer; long documents and pasted
+/// code are cut.
+const MAX_MESSAGE_CHARS: usize = 8000;
+
+const INSTRUCTIONS: &str = "You maintain the running summary of a conversation between a \
+     user and an AI assistant. Update the summary with the new messages and reply with the \
+     updated summary only. Keep facts, decisions, names, numbers, code identifiers, open \
+     questions and the user's stated preferences; drop greetings and filler. Write compact \
+     notes in the language of the conversation.";
+
+/// Asks `model` to fold `messages` into `previous`.
+pub fn summary_request(
+    model: &str,
+    previous: Option<&str>,
+    messages: &[&ChatMessage],
+) -> ChatRequest {
+    let mut prompt = String::new();
+    if let Some(previous) = previous {
+        prompt.push_str("Summary so far:\n");
+        prompt.push_str(previous);
+        prompt.push_str("\n\n");
+    }
+    prompt.push_str("New messages:\n");
+    for message in messages {
+        let text = transcript(message);
+        if !text.is_empty() {
+            prompt.push_str(&format!("\n{}: {}\n", label(message.role), text));
+        }
+    }
+
+    ChatRequest {
+        model: model.to_string(),
+        messages: vec![
+            ChatMessage::new(MessageRole::System, INSTRUCTIONS),
+            ChatMessage::new(MessageRole::User, prompt),
+        ],
+        temperature: Some(0.2),
+        max_tokens: Some(MAX_SUMMARY_TOKENS),
+        stream: false,
+        tools: Vec::new(),
+        tool_choice: None,
+    }
+}
+
+/// The system message sent in place of the summarized turns.
+pub fn summary_message(summary: &str) -> ChatMessage {
+    ChatMessage::new(
+        MessageRole::System,
+        format!(
+            "Summary of the earlier part of this conversation, which is not shown:\n\n{}",
+            summary
+        ),
+    )
+}
+
+fn label(role: MessageRole) -> &'static str {
+    match role {
+        MessageRole::User => "User",
+        MessageRole::Assistant => "Assistant",
+        MessageRole::System => "System",
+        MessageRole::Tool => "Tool",
+    }
+}
+
+/// `message` as plain text; reasoning is left out and attachments are named.
+fn transcript(message: &ChatMessage) -> String {
+    message
+        .content
+        .iter()
+        .filter_map(|part| match part {
+            ContentPart::Text { text } => Some(truncate(text)),
+            ContentPart::Image { .. } => Some("[image]".to_string()),
+            ContentPart::File { name, .. } => Some(format!("[file {}]", name)),
+            ContentPart::ToolCall(call) => {
+                Some(format!("[called {} with {}]", call.name, call.arguments))
+            }
+            ContentPart::ToolResult { content, .. } => Some(truncate(content)),
+            ContentPart::Reasoning { .. } => None,
+        })
+        .filter(|text| !text.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_MESSAGE_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
@@ -0,0 +1,143 @@
+//! Prompt token counts, used to fit a conversation into a model's context window
+//! before it is sent.
+//!
+//! OpenAI models are counted with their own BPE vocabularies. Anthropic and Google do
+//! not publish theirs, so their counts are estimated from the length of the text; the
+//! OpenAI-style providers (DeepSeek, Ollama, compatible servers) are counted with
+//! `cl100k_base`, which is close for the BPE tokenizers their models use.
+
+use crate::ai::types::{ChatMessage, ContentPart, ToolDefinition};
+use crate::db::models::AiProvider;
+use once_cell::sync::Lazy;
+use tiktoken_rs::CoreBPE;
+
+static O200K_BASE: Lazy<Option<CoreBPE>> =
+    Lazy::new(|| load("o200k_base", tiktoken_rs::o200k_base));
+static CL100K_BASE: Lazy<Option<CoreBPE>> =
+    Lazy::new(|| load("cl100k_base", tiktoken_rs::cl100k_base));
+
+/// Tokens every message costs on top of its content: the role and the delimiters
+/// around it, as OpenAI documents for its chat format.
+const TOKENS_PER_MESSAGE: u32 = 3;
+
+/// Tokens that prime the model's reply, counted once per prompt.
+pub const REPLY_PRIMING_TOKENS: u32 = 3;
+
+/// Cost of one image. Providers charge by resolution, which is not known here; this is
+/// what OpenAI charges for a 1024x1024 image at high detail and close to Anthropic's
+/// charge for the same image.
+const IMAGE_TOKENS: u32 = 765;
+
+/// Characters per token for text counted by length; rough averages for English text.
+const ANTHROPIC_CHARS_PER_TOKEN: f32 = 3.5;
+const DEFAULT_CHARS_PER_TOKEN: f32 = 4.0;
+
+fn load(name: &str, init: fn() -> anyhow::Result<CoreBPE>) -> Option<CoreBPE> {
+    init()
+        .inspect_err(|e| tracing::error!("Failed to load the {} tokenizer: {}", name, e))
+        .ok()
+}
+
+#[derive(Clone, Copy)]
+enum Encoding {
+    Bpe(&'static Lazy<Option<CoreBPE>>),
+    Chars(f32),
+}
+
+/// Counts tokens the way one model's provider does.
+#[derive(Clone, Copy)]
+pub struct TokenCounter {
+    encoding: Encoding,
+    exact: bool,
+}
+
+impl TokenCounter {
+    pub fn new(provider: AiProvider, model_id: &str) -> Self {
+        match provider {
+            AiProvider::OpenAI => Self {
+                encoding: Encoding::Bpe(openai_encoding(model_id)),
+                exact: true,
+            },
+            AiProvider::Anthropic => Self {
+                encoding: Encoding::Chars(ANTHROPIC_CHARS_PER_TOKEN),
+                exact: false,
+            },
+            AiProvider::Google => Self {
+                encoding: Encoding::Chars(DEFAULT_CHARS_PER_TOKEN),
+                exact: false,
+            },
+            AiProvider::DeepSeek | AiProvider::Ollama | AiProvider::OpenAICompatible => Self {
+                encoding: Encoding::Bpe(&CL100K_BASE),
+                exact: false,
+            },
+        }
+    }
+
+    /// Whether counts come from the model's own tokenizer rather than an estimate.
+    pub fn is_exact(&self) -> bool {
+        self.exact
+    }
+
+    pub fn count_text(&self, text: &str) -> u32 {
+        if text.is_empty() {
+            return 0;
+        }
+
+        let chars_per_token = match self.encoding {
+            Encoding::Bpe(bpe) => match bpe.as_ref() {
+                Some(bpe) => return bpe.encode_ordinary(text).len() as u32,
+                None => DEFAULT_CHARS_PER_TOKEN,
+            },
+            Encoding::Chars(chars_per_token) => chars_per_token,
+        };
+
+        (text.chars().count() as f32 / chars_per_token).ceil() as u32
+    }
+
+    /// Tokens `message` takes up in a prompt. Reasoning is never sent back, and files
+    /// that were not replaced by their text count as their name.
+    pub fn count_message(&self, message: &ChatMessage) -> u32 {
+        let content: u32 = message
+            .content
+            .iter()
+            .map(|part| match part {
+                ContentPart::Text { text } => self.count_text(text),
+                ContentPart::Image { .. } => IMAGE_TOKENS,
+                ContentPart::File { name, .. } => self.count_text(name),
+                ContentPart::ToolCall(call) => {
+                    self.count_text(&call.name) + self.count_text(&call.arguments.to_string())
+                }
+                ContentPart::ToolResult { content, .. } => self.count_text(content),
+                ContentPart::Reasoning { .. } => 0,
+            })
+            .sum();
+
+        TOKENS_PER_MESSAGE + content
+    }
+
+    /// Tokens taken by the tool definitions offered with a request.
+    pub fn count_tools(&self, tools: &[ToolDefinition]) -> u32 {
+        tools
+            .iter()
+            .map(|tool| {
+                self.count_text(&tool.name)
+                    + tool
+                        .description
+                        .as_deref()
+                        .map_or(0, |description| self.count_text(description))
+                    + self.count_text(&tool.parameters.to_string())
+            })
+            .sum()
+    }
+}
+
+/// `o200k_base` for GPT-4o and the models after it, `cl100k_base` for GPT-4 and
+/// GPT-3.5.
+fn openai_encoding(model_id: &str) -> &'static Lazy<Option<CoreBPE>> {
+    let legacy = ["gpt-4-", "gpt-3.5"]
+        .iter()
+        .any(|prefix| model_id.starts_with(prefix))
+        || model_id == "gpt-4";
+
+    if legacy { &CL100K_BASE } else { &O200K_BASE }
+}
@@ -0,0 +1,320 @@
+use super::{Tool, ToolContext, string_arg};
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+
+/// Evaluates arithmetic expressions, which models routinely get wrong on their own.
+pub struct CalculatorTool;
+
+#[async_trait]
+impl Tool for CalculatorTool {
+    fn name(&self) -> &'static str {
+        "calculator"
+    }
+
+    fn description(&self) -> &'static str {
+        "Evaluates an arithmetic expression. Supports + - * / % ^, parentheses, the \
+         constants pi and e, and sqrt, abs, exp, ln, log, log2, sin, cos, tan, asin, acos, \
+         atan, floor, ceil, round, min, max and pow."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "expression": {
+                    "type": "string",
+                    "description": "Expression to evaluate, e.g. \"(2 + 3) * sqrt(16) / 4\""
+                }
+            },
+            "required": ["expression"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        _context: &ToolContext,
+    ) -> Result<serde_json::Value> {
+        let expression = string_arg(&arguments, "expression")?;
+        let result = evaluate(expression)?;
+
+        Ok(serde_json::json!({
+            "expression": expression,
+            "result": result,
+        }))
+    }
+}
+
+/// Evaluates `expression` with the usual precedence; `^` binds tighter than unary minus
+/// and is right-associative.
+fn evaluate(expression: &str) -> Result<f64> {
+    let mut parser = Parser {
+        chars: expression.chars().collect(),
+        pos: 0,
+    };
+    let value = parser.expression()?;
+
+    parser.skip_whitespace();
+    if let Some(c) = parser.peek() {
+        bail!("Unexpected `{}` at position {}", c, parser.pos + 1);
+    }
+    if !value.is_finite() {
+        bail!("The result is not a finite number");
+    }
+
+    Ok(value)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consumes `c` if it is the next non-blank character.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expression(&mut self) -> Result<f64> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat('+') {
+                value += self.term()?;
+            } else if self.eat('-') {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<f64> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat('*') {
+                value *= self.unary()?;
+            } else if self.eat('/') {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    bail!("Division by zero");
+                }
+                value /= divisor;
+            } else if self.eat('%') {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    bail!("Division by zero");
+                }
+                value %= divisor;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<f64> {
+        if self.eat('-') {
+            Ok(-self.unary()?)
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<f64> {
+        let base = self.primary()?;
+        if self.eat('^') {
+            let exponent = self.unary()?;
+            Ok(base.powf(exponent))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn primary(&mut self) -> Result<f64> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let value = self.expression()?;
+                if !self.eat(')') {
+                    bail!("Missing closing parenthesis");
+                }
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => self.identifier(),
+            Some(c) => bail!("Unexpected `{}` at position {}", c, self.pos + 1),
+            None => bail!("Unexpected end of expression"),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || c == '.' || c == '_')
+        {
+            self.pos += 1;
+        }
+        // Exponent, as in 1.5e-3
+        if matches!(self.peek(), Some('e' | 'E'))
+            && self
+                .chars
+                .get(self.pos + 1)
+                .is_some_and(|c| c.is_ascii_digit() || *c == '-' || *c == '+')
+        {
+            self.pos += 2;
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.pos += 1;
+            }
+        }
+
+        let text: String = self.chars[start..self.pos]
+            .iter()
+            .filter(|c| **c != '_')
+            .collect();
+        text.parse()
+            .map_err(|_| anyhow::anyhow!("Invalid number `{}`", text))
+    }
+
+    fn identifier(&mut self) -> Result<f64> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos].iter().collect();
+        let name = name.to_lowercase();
+
+        if !self.eat('(') {
+            return match name.as_str() {
+                "pi" => Ok(std::f64::consts::PI),
+                "e" => Ok(std::f64::consts::E),
+                _ => bail!("Unknown constant `{}`", name),
+            };
+        }
+
+        let mut args = vec![self.expression()?];
+        while self.eat(',') {
+            args.push(self.expression()?);
+        }
+        if !self.eat(')') {
+            bail!("Missing closing parenthesis after arguments of `{}`", name);
+        }
+
+        apply(&name, &args)
+    }
+}
+
+fn apply(name: &str, args: &[f64]) -> Result<f64> {
+    let unary = |f: fn(f64) -> f64| match args {
+        [x] => Ok(f(*x)),
+        _ => bail!("`{}` takes one argument", name),
+    };
+
+    match name {
+        "sqrt" => unary(f64::sqrt),
+        "abs" => unary(f64::abs),
+        "exp" => unary(f64::exp),
+        "ln" => unary(f64::ln),
+        "log" => unary(f64::log10),
+        "log2" => unary(f64::log2),
+        "sin" => unary(f64::sin),
+        "cos" => unary(f64::cos),
+        "tan" => unary(f64::tan),
+        "asin" => unary(f64::asin),
+        "acos" => unary(f64::acos),
+        "atan" => unary(f64::atan),
+        "floor" => unary(f64::floor),
+        "ceil" => unary(f64::ceil),
+        "round" => unary(f64::round),
+        "pow" => match args {
+            [base, exponent] => Ok(base.powf(*exponent)),
+            _ => bail!("`pow` takes two arguments"),
+        },
+        "min" => args
+            .iter()
+            .copied()
+            .reduce(f64::min)
+            .ok_or_else(|| anyhow::anyhow!("`min` needs arguments")),
+        "max" => args
+            .iter()
+            .copied()
+            .reduce(f64::max)
+            .ok_or_else(|| anyhow::anyhow!("`max` needs arguments")),
+        _ => bail!("Unknown function `{}`", name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_operator_precedence() {
+        assert_eq!(evaluate("2 + 3 * 4").unwrap(), 14.0);
+        assert_eq!(evaluate("(2 + 3) * 4").unwrap(), 20.0);
+        assert_eq!(evaluate("10 - 4 - 3").unwrap(), 3.0);
+        assert_eq!(evaluate("7 % 4 * 2").unwrap(), 6.0);
+        assert_eq!(evaluate("(2 + 3) * sqrt(16) / 4").unwrap(), 5.0);
+    }
+
+    #[test]
+    fn power_is_right_associative_and_binds_tighter_than_unary_minus() {
+        assert_eq!(evaluate("2 ^ 3 ^ 2").unwrap(), 512.0);
+        assert_eq!(evaluate("-2 ^ 2").unwrap(), -4.0);
+        assert_eq!(evaluate("(-2) ^ 2").unwrap(), 4.0);
+        assert_eq!(evaluate("2 ^ -1").unwrap(), 0.5);
+    }
+
+    #[test]
+    fn reads_exponents_and_digit_separators() {
+        assert_eq!(evaluate("1.5e-3").unwrap(), 0.0015);
+        assert_eq!(evaluate("2E3").unwrap(), 2000.0);
+        assert_eq!(evaluate("1_000 + .5").unwrap(), 1000.5);
+    }
+
+    #[test]
+    fn rejects_division_by_zero() {
+        assert!(evaluate("1 / 0").is_err());
+        assert!(evaluate("5 % (2 - 2)").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_identifiers() {
+        assert!(evaluate("tau * 2").is_err());
+        assert!(evaluate("cbrt(8)").is_err());
+        assert!(evaluate("sqrt(1, 2)").is_err());
+    }
+
+    #[test]
+    fn rejects_trailing_and_missing_input() {
+        assert!(evaluate("1 2").is_err());
+        assert!(evaluate("(1 + 2").is_err());
+        assert!(evaluate("3 +").is_err());
+        assert!(evaluate("").is_err());
+    }
+
+    #[test]
+    fn rejects_results_that_are_not_finite() {
+        assert!(evaluate("10 ^ 400").is_err());
+        assert!(evaluate("sqrt(-1)").is_err());
+    }
+}
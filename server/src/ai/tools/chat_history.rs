@@ -0,0 +1,101 @@
+use super::{Tool, ToolContext};
+use crate::db::models::MessageRole;
+use crate::db::repositories::TChatRepository;
+use anyhow::Result;
+use async_trait::async_trait;
+use uuid::Uuid;
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+/// Longest message text returned; keeps one long answer from filling the context.
+const MAX_CONTENT_CHARS: usize = 2000;
+
+/// Reads earlier messages of the current chat, or of another chat the user owns.
+pub struct ChatHistoryTool;
+
+#[async_trait]
+impl Tool for ChatHistoryTool {
+    fn name(&self) -> &'static str {
+        "fetch_chat_history"
+    }
+
+    fn description(&self) -> &'static str {
+        "Returns the most recent user and assistant messages of this chat, or of another \
+         chat of the same user when chat_id is given, optionally only those containing \
+         a search term."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "chat_id": { "type": "string", "description": "Chat to read; defaults to the current chat" },
+                "query": { "type": "string", "description": "Only return messages containing this text (case-insensitive)" },
+                "limit": { "type": "integer", "description": "Number of messages to return, at most 100; defaults to 20" }
+            }
+        })
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<serde_json::Value> {
+        let chat_id = match arguments.get("chat_id").and_then(|v| v.as_str()) {
+            Some(id) => {
+                Uuid::parse_str(id).map_err(|_| anyhow::anyhow!("Invalid chat_id `{}`", id))?
+            }
+            None => context.chat_id,
+        };
+        let query = arguments
+            .get("query")
+            .and_then(|v| v.as_str())
+            .map(|q| q.to_lowercase())
+            .filter(|q| !q.is_empty());
+        let limit = arguments
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_LIMIT, |limit| (limit as usize).clamp(1, MAX_LIMIT));
+
+        // Also checks that the chat belongs to the user
+        let messages = context
+            .chat_repository
+            .list_messages(chat_id, &context.user_id)
+            .await
+            .map_err(|_| anyhow::anyhow!("Chat {} not found", chat_id))?;
+
+        let mut matching: Vec<serde_json::Value> = messages
+            .into_iter()
+            .rev()
+            .filter(|m| matches!(m.role, MessageRole::User | MessageRole::Assistant))
+            .filter(|m| !m.content.trim().is_empty())
+            .filter(|m| {
+                query
+                    .as_ref()
+                    .is_none_or(|q| m.content.to_lowercase().contains(q))
+            })
+            .take(limit)
+            .map(|m| {
+                serde_json::json!({
+                    "role": m.role.as_str(),
+                    "content": truncate(&m.content),
+                    "created_at": m.created_at.to_rfc3339(),
+                })
+            })
+            .collect();
+        matching.reverse();
+
+        Ok(serde_json::json!({
+            "chat_id": chat_id,
+            "messages": matching,
+        }))
+    }
+}
+
+fn truncate(content: &str) -> String {
+    match content.char_indices().nth(MAX_CONTENT_CHARS) {
+        Some((end, _)) => format!("{}…", &content[..end]),
+        None => content.to_string(),
+    }
+}
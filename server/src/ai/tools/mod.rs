@@ -0,0 +1,132 @@
+//! Tools the server runs itself when a chat turns them on. The model requests calls as
+//! usual; instead of handing them to the client, the chat pipeline executes them here,
+//! feeds the results back and lets the model continue.
+
+mod calculator;
+mod chat_history;
+mod time;
+mod units;
+
+use crate::ai::types::{ToolCall, ToolDefinition};
+use crate::db::repositories::ChatRepository;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub use calculator::CalculatorTool;
+pub use chat_history::ChatHistoryTool;
+pub use time::CurrentTimeTool;
+pub use units::UnitConversionTool;
+
+/// A function the server can execute on the model's behalf.
+#[async_trait]
+pub trait Tool: Send + Sync {
+    /// Name the model calls the tool by; unique within a registry.
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    /// JSON Schema of the arguments object.
+    fn parameters(&self) -> serde_json::Value;
+    /// Runs the tool. Errors are reported back to the model, which can retry or explain.
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> anyhow::Result<serde_json::Value>;
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name().to_string(),
+            description: Some(self.description().to_string()),
+            parameters: self.parameters(),
+        }
+    }
+}
+
+/// The chat a tool call was made in, for tools that read the user's data.
+pub struct ToolContext {
+    pub user_id: String,
+    pub chat_id: Uuid,
+    pub chat_repository: Arc<ChatRepository>,
+}
+
+/// Result of one tool call, as sent back to the model.
+#[derive(Debug, Clone)]
+pub struct ToolOutput {
+    pub content: String,
+    pub is_error: bool,
+}
+
+impl ToolOutput {
+    fn error(message: String) -> Self {
+        Self {
+            content: serde_json::json!({ "error": message }).to_string(),
+            is_error: true,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: HashMap<&'static str, Arc<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    /// Every tool that ships with the server.
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        registry.register(CurrentTimeTool);
+        registry.register(CalculatorTool);
+        registry.register(UnitConversionTool);
+        registry.register(ChatHistoryTool);
+        registry
+    }
+
+    /// Adds `tool`, replacing any tool registered under the same name.
+    pub fn register(&mut self, tool: impl Tool + 'static) {
+        self.tools.insert(tool.name(), Arc::new(tool));
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.tools.contains_key(name)
+    }
+
+    /// Definitions to offer the model, in a stable order.
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        let mut definitions: Vec<ToolDefinition> =
+            self.tools.values().map(|tool| tool.definition()).collect();
+        definitions.sort_by(|a, b| a.name.cmp(&b.name));
+        definitions
+    }
+
+    /// Runs `call`. Unknown tools and failures come back as an error output rather than
+    /// an `Err` so the model sees what went wrong.
+    pub async fn execute(&self, call: &ToolCall, context: &ToolContext) -> ToolOutput {
+        let Some(tool) = self.tools.get(call.name.as_str()) else {
+            return ToolOutput::error(format!("Unknown tool: {}", call.name));
+        };
+
+        match tool.execute(call.arguments.clone(), context).await {
+            Ok(serde_json::Value::String(content)) => ToolOutput {
+                content,
+                is_error: false,
+            },
+            Ok(value) => ToolOutput {
+                content: value.to_string(),
+                is_error: false,
+            },
+            Err(e) => {
+                tracing::warn!("Tool {} failed: {}", call.name, e);
+                ToolOutput::error(e.to_string())
+            }
+        }
+    }
+}
+
+/// Reads a required string argument.
+fn string_arg<'a>(arguments: &'a serde_json::Value, name: &str) -> anyhow::Result<&'a str> {
+    arguments
+        .get(name)
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing string argument `{}`", name))
+}
@@ -0,0 +1,54 @@
+use super::{Tool, ToolContext};
+use async_trait::async_trait;
+use chrono::{FixedOffset, Offset, Utc};
+
+/// The current date and time, optionally at a fixed UTC offset.
+pub struct CurrentTimeTool;
+
+#[async_trait]
+impl Tool for CurrentTimeTool {
+    fn name(&self) -> &'static str {
+        "current_time"
+    }
+
+    fn description(&self) -> &'static str {
+        "Returns the current date and time. Pass utc_offset (e.g. \"+02:00\") for local time."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "utc_offset": {
+                    "type": "string",
+                    "description": "Offset from UTC as +HH:MM or -HH:MM; defaults to UTC"
+                }
+            }
+        })
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        _context: &ToolContext,
+    ) -> anyhow::Result<serde_json::Value> {
+        let offset = match arguments.get("utc_offset").and_then(|v| v.as_str()) {
+            Some(offset) => offset
+                .trim()
+                .parse::<FixedOffset>()
+                .map_err(|_| anyhow::anyhow!("Invalid utc_offset `{}`", offset))?,
+            None => Utc.fix(),
+        };
+
+        let now = Utc::now().with_timezone(&offset);
+
+        Ok(serde_json::json!({
+            "datetime": now.to_rfc3339(),
+            "date": now.format("%Y-%m-%d").to_string(),
+            "time": now.format("%H:%M:%S").to_string(),
+            "weekday": now.format("%A").to_string(),
+            "utc_offset": now.format("%:z").to_string(),
+            "unix_timestamp": now.timestamp(),
+        }))
+    }
+}
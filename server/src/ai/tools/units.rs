@@ -0,0 +1,272 @@
+use super::{Tool, ToolContext, string_arg};
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+
+/// Converts between units of length, mass, volume, area, speed, time, data and
+/// temperature.
+pub struct UnitConversionTool;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dimension {
+    Length,
+    Mass,
+    Volume,
+    Area,
+    Speed,
+    Time,
+    Data,
+    Temperature,
+}
+
+/// A unit, its accepted spellings and its size in the dimension's base unit (metre,
+/// kilogram, litre, square metre, metre per second, second, byte). Temperatures are
+/// not proportional and are handled separately.
+struct Unit {
+    names: &'static [&'static str],
+    dimension: Dimension,
+    factor: f64,
+}
+
+const fn unit(names: &'static [&'static str], dimension: Dimension, factor: f64) -> Unit {
+    Unit {
+        names,
+        dimension,
+        factor,
+    }
+}
+
+const UNITS: &[Unit] = &[
+    unit(
+        &["mm", "millimeter", "millimetre"],
+        Dimension::Length,
+        0.001,
+    ),
+    unit(&["cm", "centimeter", "centimetre"], Dimension::Length, 0.01),
+    unit(&["m", "meter", "metre"], Dimension::Length, 1.0),
+    unit(&["km", "kilometer", "kilometre"], Dimension::Length, 1000.0),
+    unit(&["in", "inch", "inches"], Dimension::Length, 0.0254),
+    unit(&["ft", "foot", "feet"], Dimension::Length, 0.3048),
+    unit(&["yd", "yard"], Dimension::Length, 0.9144),
+    unit(&["mi", "mile"], Dimension::Length, 1609.344),
+    unit(&["nmi", "nautical mile"], Dimension::Length, 1852.0),
+    unit(&["mg", "milligram"], Dimension::Mass, 0.000_001),
+    unit(&["g", "gram"], Dimension::Mass, 0.001),
+    unit(&["kg", "kilogram"], Dimension::Mass, 1.0),
+    unit(&["t", "tonne", "metric ton"], Dimension::Mass, 1000.0),
+    unit(&["oz", "ounce"], Dimension::Mass, 0.028_349_523_125),
+    unit(&["lb", "lbs", "pound"], Dimension::Mass, 0.453_592_37),
+    unit(&["st", "stone"], Dimension::Mass, 6.350_293_18),
+    unit(
+        &["ml", "milliliter", "millilitre"],
+        Dimension::Volume,
+        0.001,
+    ),
+    unit(&["l", "liter", "litre"], Dimension::Volume, 1.0),
+    unit(
+        &["m3", "cubic meter", "cubic metre"],
+        Dimension::Volume,
+        1000.0,
+    ),
+    unit(
+        &["tsp", "teaspoon"],
+        Dimension::Volume,
+        0.004_928_921_593_75,
+    ),
+    unit(
+        &["tbsp", "tablespoon"],
+        Dimension::Volume,
+        0.014_786_764_781_25,
+    ),
+    unit(
+        &["floz", "fl oz", "fluid ounce"],
+        Dimension::Volume,
+        0.029_573_529_562_5,
+    ),
+    unit(&["cup"], Dimension::Volume, 0.236_588_236_5),
+    unit(&["pt", "pint"], Dimension::Volume, 0.473_176_473),
+    unit(&["qt", "quart"], Dimension::Volume, 0.946_352_946),
+    unit(&["gal", "gallon"], Dimension::Volume, 3.785_411_784),
+    unit(
+        &["m2", "square meter", "square metre"],
+        Dimension::Area,
+        1.0,
+    ),
+    unit(
+        &["km2", "square kilometer", "square kilometre"],
+        Dimension::Area,
+        1_000_000.0,
+    ),
+    unit(
+        &["ft2", "square foot", "square feet"],
+        Dimension::Area,
+        0.092_903_04,
+    ),
+    unit(&["ha", "hectare"], Dimension::Area, 10_000.0),
+    unit(&["acre"], Dimension::Area, 4_046.856_422_4),
+    unit(&["mi2", "square mile"], Dimension::Area, 2_589_988.110_336),
+    unit(&["m/s", "mps"], Dimension::Speed, 1.0),
+    unit(&["km/h", "kph", "kmh"], Dimension::Speed, 1000.0 / 3600.0),
+    unit(&["mph"], Dimension::Speed, 0.447_04),
+    unit(&["kn", "knot"], Dimension::Speed, 1852.0 / 3600.0),
+    unit(&["ms", "millisecond"], Dimension::Time, 0.001),
+    unit(&["s", "sec", "second"], Dimension::Time, 1.0),
+    unit(&["min", "minute"], Dimension::Time, 60.0),
+    unit(&["h", "hr", "hour"], Dimension::Time, 3600.0),
+    unit(&["d", "day"], Dimension::Time, 86_400.0),
+    unit(&["wk", "week"], Dimension::Time, 604_800.0),
+    unit(&["yr", "year"], Dimension::Time, 31_557_600.0),
+    unit(&["bit"], Dimension::Data, 0.125),
+    unit(&["b", "byte"], Dimension::Data, 1.0),
+    unit(&["kb", "kilobyte"], Dimension::Data, 1e3),
+    unit(&["mb", "megabyte"], Dimension::Data, 1e6),
+    unit(&["gb", "gigabyte"], Dimension::Data, 1e9),
+    unit(&["tb", "terabyte"], Dimension::Data, 1e12),
+    unit(&["kib", "kibibyte"], Dimension::Data, 1024.0),
+    unit(&["mib", "mebibyte"], Dimension::Data, 1_048_576.0),
+    unit(&["gib", "gibibyte"], Dimension::Data, 1_073_741_824.0),
+    unit(&["tib", "tebibyte"], Dimension::Data, 1_099_511_627_776.0),
+    unit(&["c", "celsius", "°c"], Dimension::Temperature, 1.0),
+    unit(&["f", "fahrenheit", "°f"], Dimension::Temperature, 1.0),
+    unit(&["k", "kelvin"], Dimension::Temperature, 1.0),
+];
+
+/// Finds a unit by name, ignoring case and a plural `s`.
+fn find_unit(name: &str) -> Option<&'static Unit> {
+    let name = name.trim().to_lowercase();
+    let singular = name.strip_suffix('s').unwrap_or(&name);
+
+    UNITS
+        .iter()
+        .find(|unit| unit.names.contains(&name.as_str()))
+        .or_else(|| UNITS.iter().find(|unit| unit.names.contains(&singular)))
+}
+
+fn to_kelvin(value: f64, unit: &Unit) -> f64 {
+    match unit.names[0] {
+        "c" => value + 273.15,
+        "f" => (value - 32.0) * 5.0 / 9.0 + 273.15,
+        _ => value,
+    }
+}
+
+fn from_kelvin(value: f64, unit: &Unit) -> f64 {
+    match unit.names[0] {
+        "c" => value - 273.15,
+        "f" => (value - 273.15) * 9.0 / 5.0 + 32.0,
+        _ => value,
+    }
+}
+
+fn convert(value: f64, from: &Unit, to: &Unit) -> f64 {
+    if from.dimension == Dimension::Temperature {
+        from_kelvin(to_kelvin(value, from), to)
+    } else {
+        value * from.factor / to.factor
+    }
+}
+
+#[async_trait]
+impl Tool for UnitConversionTool {
+    fn name(&self) -> &'static str {
+        "convert_units"
+    }
+
+    fn description(&self) -> &'static str {
+        "Converts a value between units of length, mass, volume, area, speed, time, data \
+         size or temperature, e.g. 5 mi to km or 72 f to c."
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "value": { "type": "number", "description": "Amount to convert" },
+                "from": { "type": "string", "description": "Unit of the value, e.g. \"mi\", \"lb\", \"celsius\"" },
+                "to": { "type": "string", "description": "Unit to convert to" }
+            },
+            "required": ["value", "from", "to"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        _context: &ToolContext,
+    ) -> Result<serde_json::Value> {
+        let Some(value) = arguments.get("value").and_then(|v| v.as_f64()) else {
+            bail!("Missing number argument `value`");
+        };
+        let from_name = string_arg(&arguments, "from")?;
+        let to_name = string_arg(&arguments, "to")?;
+
+        let Some(from) = find_unit(from_name) else {
+            bail!("Unknown unit `{}`", from_name);
+        };
+        let Some(to) = find_unit(to_name) else {
+            bail!("Unknown unit `{}`", to_name);
+        };
+        if from.dimension != to.dimension {
+            bail!(
+                "Cannot convert {:?} ({}) to {:?} ({})",
+                from.dimension,
+                from_name,
+                to.dimension,
+                to_name
+            );
+        }
+
+        Ok(serde_json::json!({
+            "value": value,
+            "from": from_name,
+            "to": to_name,
+            "result": convert(value, from, to),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert_names(value: f64, from: &str, to: &str) -> f64 {
+        convert(value, find_unit(from).unwrap(), find_unit(to).unwrap())
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= 1e-9 * expected.abs().max(1.0),
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn converts_within_each_dimension() {
+        assert_close(convert_names(5.0, "mi", "km"), 8.04672);
+        assert_close(convert_names(1.0, "lb", "g"), 453.59237);
+        assert_close(convert_names(1.0, "gal", "l"), 3.785411784);
+        assert_close(convert_names(1.0, "acre", "m2"), 4046.8564224);
+        assert_close(convert_names(100.0, "km/h", "m/s"), 27.777777777777778);
+        assert_close(convert_names(2.0, "hour", "min"), 120.0);
+        assert_close(convert_names(1.0, "gib", "mb"), 1073.741824);
+    }
+
+    #[test]
+    fn converts_temperatures_with_their_offsets() {
+        assert_close(convert_names(100.0, "c", "f"), 212.0);
+        assert_close(convert_names(-40.0, "f", "c"), -40.0);
+        assert_close(convert_names(0.0, "k", "c"), -273.15);
+        assert_close(convert_names(32.0, "fahrenheit", "kelvin"), 273.15);
+    }
+
+    #[test]
+    fn finds_units_by_any_spelling() {
+        assert_eq!(find_unit("Miles").unwrap().names[0], "mi");
+        assert_eq!(find_unit(" feet ").unwrap().names[0], "ft");
+        // A trailing `s` is only dropped when the full name is unknown
+        assert_eq!(find_unit("s").unwrap().names[0], "s");
+        assert_eq!(find_unit("°C").unwrap().dimension, Dimension::Temperature);
+        assert!(find_unit("furlong").is_none());
+    }
+}
@@ -1,5 +1,13 @@
-use crate::db::models::MessageRole;
+use crate::ai::error::ProviderResult;
+use crate::db::models::{MessageModel, MessageRole};
+use futures::Stream;
 use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Stream of incremental chunks returned by `AIProvider::stream_chat`.
+pub type ChatStream = Pin<Box<dyn Stream<Item = ProviderResult<ChatResponseChunk>> + Send>>;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatRequest {
@@ -8,27 +16,367 @@ pub struct ChatRequest {
     pub temperature: Option<f32>,
     pub max_tokens: Option<u32>,
     pub stream: bool,
+    /// Functions the model may call; empty when tools are not in use.
+    pub tools: Vec<ToolDefinition>,
+    pub tool_choice: Option<ToolChoice>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
     pub role: MessageRole,
-    pub content: String,
+    pub content: Vec<ContentPart>,
+}
+
+impl ChatMessage {
+    /// A message holding only `text`.
+    pub fn new(role: MessageRole, text: impl Into<String>) -> Self {
+        let text = text.into();
+        let content = if text.is_empty() {
+            Vec::new()
+        } else {
+            vec![ContentPart::Text { text }]
+        };
+
+        Self { role, content }
+    }
+
+    /// An assistant turn as the provider returned it.
+    pub fn assistant(
+        content: String,
+        reasoning: Option<String>,
+        tool_calls: Vec<ToolCall>,
+    ) -> Self {
+        let mut parts = Vec::with_capacity(2 + tool_calls.len());
+        if let Some(text) = reasoning.filter(|r| !r.is_empty()) {
+            parts.push(ContentPart::Reasoning { text });
+        }
+        if !content.is_empty() {
+            parts.push(ContentPart::Text { text: content });
+        }
+        parts.extend(tool_calls.into_iter().map(ContentPart::ToolCall));
+
+        Self {
+            role: MessageRole::Assistant,
+            content: parts,
+        }
+    }
+
+    /// A `tool` turn answering the call `tool_call_id`.
+    pub fn tool_result(tool_call_id: String, content: String, is_error: bool) -> Self {
+        Self {
+            role: MessageRole::Tool,
+            content: vec![ContentPart::ToolResult {
+                tool_call_id,
+                content,
+                is_error,
+            }],
+        }
+    }
+
+    /// The text parts, separated by blank lines.
+    pub fn text(&self) -> String {
+        ContentPart::join_text(&self.content)
+    }
+
+    pub fn tool_calls(&self) -> impl Iterator<Item = &ToolCall> {
+        self.content.iter().filter_map(|part| match part {
+            ContentPart::ToolCall(call) => Some(call),
+            _ => None,
+        })
+    }
+
+    pub fn has_tool_calls(&self) -> bool {
+        self.tool_calls().next().is_some()
+    }
+
+    pub fn has_images(&self) -> bool {
+        self.content
+            .iter()
+            .any(|part| matches!(part, ContentPart::Image { .. }))
+    }
+
+    /// Whether there is nothing to send; blank text and reasoning do not count.
+    pub fn is_empty(&self) -> bool {
+        self.content.iter().all(|part| match part {
+            ContentPart::Text { text } => text.trim().is_empty(),
+            ContentPart::Reasoning { .. } => true,
+            _ => false,
+        })
+    }
+}
+
+/// Replays a stored message, including the tool calls an assistant turn made.
+impl From<MessageModel> for ChatMessage {
+    fn from(message: MessageModel) -> Self {
+        Self {
+            role: message.role,
+            content: ContentPart::from_message(&message),
+        }
+    }
+}
+
+/// One piece of a message's content. Plain chats only use `Text`; the other parts
+/// carry what a string cannot. Providers skip parts they have no way to send.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ContentPart {
+    Text {
+        text: String,
+    },
+    /// An image by URL; a `data:` URL carries the image inline.
+    Image {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+        /// Set when the image is an upload; `url` then points at its download endpoint
+        /// and is replaced by the bytes before the message goes to a provider.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        attachment_id: Option<Uuid>,
+    },
+    /// A file by name and, when it can be fetched, URL.
+    File {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        url: Option<String>,
+        /// Set when the file is an upload.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        attachment_id: Option<Uuid>,
+    },
+    /// A call requested by an assistant turn.
+    ToolCall(ToolCall),
+    /// The result of a call, on a `tool` turn.
+    ToolResult {
+        tool_call_id: String,
+        content: String,
+        #[serde(default)]
+        is_error: bool,
+    },
+    /// What a reasoning model thought before answering. Kept for display only and never
+    /// sent back to a provider.
+    Reasoning {
+        text: String,
+    },
+}
+
+impl ContentPart {
+    /// The text parts of `parts`, separated by blank lines.
+    pub fn join_text(parts: &[ContentPart]) -> String {
+        parts
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Text { text } if !text.is_empty() => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// The parts stored in `messages.content_parts`: everything but the text and tool
+    /// results, which `content` and `tool_call_id` hold. `None` when nothing is left.
+    pub fn to_stored(parts: &[ContentPart]) -> Option<serde_json::Value> {
+        let stored: Vec<&ContentPart> = parts
+            .iter()
+            .filter(|part| {
+                !matches!(
+                    part,
+                    ContentPart::Text { .. } | ContentPart::ToolResult { .. }
+                )
+            })
+            .collect();
+        if stored.is_empty() {
+            return None;
+        }
+
+        serde_json::to_value(stored).ok()
+    }
+
+    /// The content of a stored message: reasoning first, then the text or tool result,
+    /// then the remaining stored parts. Reasoning of rows written before
+    /// `content_parts` existed is read from `metadata`.
+    pub fn from_message(message: &MessageModel) -> Vec<ContentPart> {
+        let stored: Vec<ContentPart> = message
+            .content_parts
+            .as_ref()
+            .and_then(|parts| serde_json::from_value(parts.clone()).ok())
+            .unwrap_or_default();
+        let (reasoning, rest): (Vec<ContentPart>, Vec<ContentPart>) = stored
+            .into_iter()
+            .partition(|part| matches!(part, ContentPart::Reasoning { .. }));
+        let metadata = |key: &str| message.metadata.as_ref().and_then(|m| m.get(key));
+
+        let mut parts = Vec::with_capacity(2 + reasoning.len() + rest.len());
+        if let Some(text) = metadata("reasoning").and_then(|r| r.as_str()) {
+            parts.push(ContentPart::Reasoning {
+                text: text.to_string(),
+            });
+        }
+        parts.extend(reasoning);
+
+        if message.role == MessageRole::Tool {
+            parts.push(ContentPart::ToolResult {
+                tool_call_id: message.tool_call_id.clone().unwrap_or_default(),
+                content: message.content.clone(),
+                is_error: metadata("is_error")
+                    .and_then(|e| e.as_bool())
+                    .unwrap_or(false),
+            });
+        } else if !message.content.is_empty() {
+            parts.push(ContentPart::Text {
+                text: message.content.clone(),
+            });
+        }
+        parts.extend(rest);
+
+        parts
+    }
+}
+
+/// Image formats every vision-capable provider accepts.
+pub const SUPPORTED_IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// An image carried inline in a `data:` URL, the form chat images are sent in.
+pub struct InlineImage<'a> {
+    pub mime_type: &'a str,
+    /// Base64-encoded bytes.
+    pub data: &'a str,
+}
+
+impl<'a> InlineImage<'a> {
+    /// Splits a `data:<mime type>;base64,<data>` URL; `None` for any other URL.
+    pub fn parse(url: &'a str) -> Option<Self> {
+        let (mime_type, data) = url.strip_prefix("data:")?.split_once(";base64,")?;
+        Some(Self { mime_type, data })
+    }
+}
+
+/// A function offered to the model. `parameters` is a JSON Schema object describing
+/// the arguments.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default = "empty_object_schema")]
+    pub parameters: serde_json::Value,
+}
+
+fn empty_object_schema() -> serde_json::Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+/// Whether and how the model should call the offered tools.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ToolChoice {
+    /// The model decides.
+    Auto,
+    /// The model must answer in text.
+    None,
+    /// The model must call at least one tool.
+    Required,
+    /// The model must call this tool.
+    Tool { name: String },
+}
+
+/// A function call requested by the model. `id` pairs it with the `tool` message
+/// carrying the result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: serde_json::Value,
+}
+
+impl ToolCall {
+    /// Builds a call from arguments received as a JSON string, as OpenAI returns them
+    /// and as every provider streams them. Models occasionally emit arguments that are
+    /// not valid JSON; those are kept as a string rather than dropped.
+    pub fn from_json_arguments(id: String, name: String, arguments: &str) -> Self {
+        let arguments = if arguments.trim().is_empty() {
+            serde_json::json!({})
+        } else {
+            serde_json::from_str(arguments)
+                .unwrap_or_else(|_| serde_json::Value::String(arguments.to_string()))
+        };
+
+        Self {
+            id,
+            name,
+            arguments,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatResponse {
     pub content: String,
+    /// Reasoning emitted separately from the answer by reasoning models.
+    pub reasoning: Option<String>,
+    pub tool_calls: Vec<ToolCall>,
     pub model: String,
-    pub tokens_used: Option<u32>,
+    pub usage: Option<TokenUsage>,
     pub finish_reason: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A single streamed delta. Only the last chunk of a stream has `done` set, and it is
+/// the one carrying the usage and finish reason reported by the provider.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ChatResponseChunk {
     pub content: String,
+    pub reasoning: Option<String>,
+    /// Tool calls whose arguments finished streaming in this chunk.
+    pub tool_calls: Vec<ToolCall>,
     pub done: bool,
     pub model: Option<String>,
+    pub usage: Option<TokenUsage>,
+    pub finish_reason: Option<String>,
+}
+
+/// Tokens a completion used, as its provider reported them. Every provider's counts are
+/// normalized so `prompt_tokens` covers the whole prompt: Anthropic reports cache reads
+/// and writes apart from `input_tokens`, and they are added back in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TokenUsage {
+    /// Tokens of the prompt, including `cached_tokens`
+    pub prompt_tokens: u32,
+    /// Tokens of the completion, including `reasoning_tokens`
+    pub completion_tokens: u32,
+    /// Prompt tokens read from the provider's prompt cache, when it reports them
+    pub cached_tokens: Option<u32>,
+    /// Completion tokens the model spent reasoning, when it reports them
+    pub reasoning_tokens: Option<u32>,
+}
+
+impl TokenUsage {
+    pub fn total_tokens(&self) -> u32 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// A prompt to turn into images.
+#[derive(Debug, Clone)]
+pub struct ImageGenerationRequest {
+    pub model: String,
+    pub prompt: String,
+    /// `WIDTHxHEIGHT` as OpenAI takes it; providers that work in aspect ratios pick the
+    /// nearest one.
+    pub size: Option<String>,
+    pub n: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratedImage {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+    /// The prompt the provider actually drew, when it rewrote the one it was given.
+    pub revised_prompt: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageGenerationResponse {
+    pub model: String,
+    pub images: Vec<GeneratedImage>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,4 +386,5 @@ pub struct ModelInfo {
     pub context_window: u32,
     pub supports_streaming: bool,
     pub supports_images: bool,
+    pub supports_functions: bool,
 }
@@ -1,5 +1,10 @@
+use crate::ai::error::ProviderError;
 use crate::db::prelude::*;
-use serde::Serialize;
+use axum::{
+    http::{StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Deserializer, Serialize};
 use utoipa::ToSchema;
 
 /// Common response type for user data
@@ -25,3 +30,145 @@ impl From<UserModel> for UserResponse {
         }
     }
 }
+
+/// For nullable fields of update requests: a field sent as `null` becomes `Some(None)`
+/// (clear it) while a missing one stays `None` (leave it unchanged). Use together with
+/// `#[serde(default)]`.
+pub fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Body of every structured error response
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    /// Stable, machine-readable identifier such as `invalid_api_key`
+    pub code: String,
+    /// Message suitable for showing to the user
+    pub message: String,
+    /// Provider that failed, for errors raised by an AI provider
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Seconds to wait before retrying a rate-limited request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u64>,
+    /// The provider's own error message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Error returned by handlers that report more than a bare status code. Converts
+/// from `StatusCode` so existing `?` on `Result<_, StatusCode>` keeps working.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub body: ErrorResponse,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: ErrorResponse {
+                code: code.to_string(),
+                message: message.into(),
+                provider: None,
+                retry_after: None,
+                detail: None,
+            },
+        }
+    }
+
+    pub fn provider(provider: AiProvider, error: &ProviderError) -> Self {
+        let name = provider.display_name();
+        let retry_after = error.retry_after().map(|d| d.as_secs().max(1));
+        let (status, message, detail) = match error {
+            // Not 401: that status means the user's own session is invalid
+            ProviderError::InvalidApiKey(detail) => (
+                StatusCode::BAD_REQUEST,
+                format!("Your {} API key is invalid or lacks access", name),
+                Some(detail.clone()),
+            ),
+            ProviderError::RateLimited { message, .. } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                match retry_after {
+                    Some(secs) => format!(
+                        "{} is rate limiting requests; try again in {} seconds",
+                        name, secs
+                    ),
+                    None => format!("{} is rate limiting requests; try again later", name),
+                },
+                Some(message.clone()),
+            ),
+            ProviderError::ContextLengthExceeded(detail) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "The conversation is too long for the selected model".to_string(),
+                Some(detail.clone()),
+            ),
+            ProviderError::ContentFiltered(detail) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("{} blocked the request with its content filter", name),
+                Some(detail.clone()),
+            ),
+            ProviderError::ModelNotFound(detail) => (
+                StatusCode::NOT_FOUND,
+                format!("The selected model is not available from {}", name),
+                Some(detail.clone()),
+            ),
+            ProviderError::InvalidRequest(detail) => (
+                StatusCode::BAD_REQUEST,
+                format!("{} rejected the request", name),
+                Some(detail.clone()),
+            ),
+            ProviderError::Upstream { message, .. } => (
+                StatusCode::BAD_GATEWAY,
+                format!("{} is unavailable right now", name),
+                Some(message.clone()),
+            ),
+            ProviderError::Timeout => (
+                StatusCode::GATEWAY_TIMEOUT,
+                format!("{} did not respond in time", name),
+                None,
+            ),
+            ProviderError::Other(e) => (
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to get a response from {}", name),
+                Some(e.to_string()),
+            ),
+        };
+
+        Self {
+            status,
+            body: ErrorResponse {
+                code: error.code().to_string(),
+                message,
+                provider: Some(provider.as_str().to_string()),
+                retry_after,
+                detail: detail.filter(|d| !d.is_empty()),
+            },
+        }
+    }
+}
+
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        let reason = status.canonical_reason().unwrap_or("Unknown error");
+        Self::new(status, &reason.to_lowercase().replace(' ', "_"), reason)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let retry_after = self.body.retry_after;
+        let mut response = (self.status, Json(self.body)).into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, secs.into());
+        }
+        response
+    }
+}
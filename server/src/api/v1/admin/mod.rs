@@ -0,0 +1,2 @@
+// Admin-only endpoints; every handler takes an `AdminUser`
+pub mod models;
@@ -0,0 +1,431 @@
+use crate::{
+    AppState,
+    ai::catalog,
+    api::common::{ApiError, ErrorResponse, double_option},
+    api::v1::models::ModelResponse,
+    db::prelude::*,
+    db::repositories::TAiModelRepository,
+    middleware::auth::AdminUser,
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateModelRequest {
+    pub provider: String,
+    pub model_id: String,
+    pub display_name: String,
+    pub description: Option<String>,
+    pub context_window: i32,
+    #[serde(default = "default_true")]
+    pub supports_streaming: bool,
+    #[serde(default)]
+    pub supports_images: bool,
+    #[serde(default)]
+    pub supports_functions: bool,
+    pub cost_per_token: Option<rust_decimal::Decimal>,
+    /// USD per prompt token
+    pub input_cost_per_token: Option<rust_decimal::Decimal>,
+    /// USD per completion token
+    pub output_cost_per_token: Option<rust_decimal::Decimal>,
+    /// USD per prompt token read from the provider's cache; the input price when unset
+    pub cached_input_cost_per_token: Option<rust_decimal::Decimal>,
+    /// Defaults for unset request parameters, e.g. `{"temperature": 0.7}`
+    pub default_parameters: Option<serde_json::Value>,
+    pub base_url: Option<String>,
+    pub extra_headers: Option<HashMap<String, String>>,
+    #[serde(default = "default_true")]
+    pub is_active: bool,
+}
+
+/// Fields left out are unchanged; nullable fields sent as `null` are cleared.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateModelRequest {
+    pub display_name: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<String>)]
+    pub description: Option<Option<String>>,
+    pub context_window: Option<i32>,
+    pub supports_streaming: Option<bool>,
+    pub supports_images: Option<bool>,
+    pub supports_functions: Option<bool>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<String>)]
+    pub cost_per_token: Option<Option<rust_decimal::Decimal>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<String>)]
+    pub input_cost_per_token: Option<Option<rust_decimal::Decimal>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<String>)]
+    pub output_cost_per_token: Option<Option<rust_decimal::Decimal>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<String>)]
+    pub cached_input_cost_per_token: Option<Option<rust_decimal::Decimal>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<Object>)]
+    pub default_parameters: Option<Option<serde_json::Value>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<String>)]
+    pub base_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<Object>)]
+    pub extra_headers: Option<Option<HashMap<String, String>>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SyncReportResponse {
+    pub provider: String,
+    /// Models the provider listed
+    pub synced: usize,
+    /// Models the provider no longer lists, now inactive
+    pub deactivated: usize,
+}
+
+impl From<catalog::SyncReport> for SyncReportResponse {
+    fn from(report: catalog::SyncReport) -> Self {
+        Self {
+            provider: report.provider.as_str().to_string(),
+            synced: report.synced,
+            deactivated: report.deactivated,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn validate(
+    context_window: Option<i32>,
+    default_parameters: Option<&serde_json::Value>,
+) -> Result<(), ApiError> {
+    if context_window.is_some_and(|size| size <= 0) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_context_window",
+            "context_window must be a positive number of tokens",
+        ));
+    }
+
+    if default_parameters.is_some_and(|params| !params.is_object()) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_default_parameters",
+            "default_parameters must be a JSON object",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Add a model to the catalog
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/models",
+    tag = "Admin",
+    security(("bearer_auth" = [])),
+    request_body = CreateModelRequest,
+    responses(
+        (status = 200, description = "Model created", body = ModelResponse),
+        (status = 400, description = "Invalid provider or payload", body = ErrorResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 409, description = "The provider already has a model with this id", body = ErrorResponse),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn create_model(
+    admin: AdminUser,
+    state: State<AppState>,
+    Json(payload): Json<CreateModelRequest>,
+) -> Result<Json<ModelResponse>, ApiError> {
+    let provider = AiProvider::from_str(&payload.provider).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_provider",
+            format!("Unknown provider '{}'", payload.provider),
+        )
+    })?;
+    validate(
+        Some(payload.context_window),
+        payload.default_parameters.as_ref(),
+    )?;
+
+    let existing = state
+        .ai_model_repository
+        .get_by_provider_and_model_id(&provider, &payload.model_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if existing.is_some() {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "model_exists",
+            format!(
+                "{} already has a model with id {}",
+                provider.display_name(),
+                payload.model_id
+            ),
+        ));
+    }
+
+    let model = state
+        .ai_model_repository
+        .create(CreateAiModelDto {
+            provider,
+            model_id: payload.model_id,
+            display_name: payload.display_name,
+            description: payload.description,
+            context_window: payload.context_window,
+            supports_streaming: payload.supports_streaming,
+            supports_images: payload.supports_images,
+            supports_functions: payload.supports_functions,
+            cost_per_token: payload.cost_per_token,
+            is_active: payload.is_active,
+            base_url: payload.base_url,
+            extra_headers: payload
+                .extra_headers
+                .map(|headers| serde_json::json!(headers)),
+            input_cost_per_token: payload.input_cost_per_token,
+            output_cost_per_token: payload.output_cost_per_token,
+            default_parameters: payload.default_parameters,
+            cached_input_cost_per_token: payload.cached_input_cost_per_token,
+        })
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    tracing::info!(
+        "Admin {} created model {}:{}",
+        admin.0.id,
+        model.provider.as_str(),
+        model.model_id
+    );
+    Ok(Json(ModelResponse::from(model)))
+}
+
+/// Update a catalog model
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/models/{id}",
+    tag = "Admin",
+    security(("bearer_auth" = [])),
+    params(
+        ("id" = Uuid, Path, description = "Model identifier")
+    ),
+    request_body = UpdateModelRequest,
+    responses(
+        (status = 200, description = "Model updated", body = ModelResponse),
+        (status = 400, description = "Invalid payload", body = ErrorResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "Model not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn update_model(
+    admin: AdminUser,
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateModelRequest>,
+) -> Result<Json<ModelResponse>, ApiError> {
+    validate(
+        payload.context_window,
+        payload.default_parameters.as_ref().and_then(|p| p.as_ref()),
+    )?;
+
+    let model = update(
+        &state,
+        id,
+        UpdateAiModelDto {
+            display_name: payload.display_name,
+            description: payload.description,
+            context_window: payload.context_window,
+            supports_streaming: payload.supports_streaming,
+            supports_images: payload.supports_images,
+            supports_functions: payload.supports_functions,
+            cost_per_token: payload.cost_per_token,
+            is_active: None,
+            base_url: payload.base_url,
+            extra_headers: payload
+                .extra_headers
+                .map(|headers| headers.map(|headers| serde_json::json!(headers))),
+            input_cost_per_token: payload.input_cost_per_token,
+            output_cost_per_token: payload.output_cost_per_token,
+            default_parameters: payload.default_parameters,
+            cached_input_cost_per_token: payload.cached_input_cost_per_token,
+        },
+    )
+    .await?;
+
+    tracing::info!("Admin {} updated model {}", admin.0.id, id);
+    Ok(Json(model))
+}
+
+/// Make a catalog model available again
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/models/{id}/activate",
+    tag = "Admin",
+    security(("bearer_auth" = [])),
+    params(
+        ("id" = Uuid, Path, description = "Model identifier")
+    ),
+    responses(
+        (status = 200, description = "Model activated", body = ModelResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "Model not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn activate_model(
+    admin: AdminUser,
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ModelResponse>, ApiError> {
+    let model = update(
+        &state,
+        id,
+        UpdateAiModelDto {
+            is_active: Some(true),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tracing::info!("Admin {} activated model {}", admin.0.id, id);
+    Ok(Json(model))
+}
+
+/// Hide a catalog model from model lists without deleting it
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/models/{id}/deactivate",
+    tag = "Admin",
+    security(("bearer_auth" = [])),
+    params(
+        ("id" = Uuid, Path, description = "Model identifier")
+    ),
+    responses(
+        (status = 200, description = "Model deactivated", body = ModelResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "Model not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn deactivate_model(
+    admin: AdminUser,
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ModelResponse>, ApiError> {
+    let model = update(
+        &state,
+        id,
+        UpdateAiModelDto {
+            is_active: Some(false),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tracing::info!("Admin {} deactivated model {}", admin.0.id, id);
+    Ok(Json(model))
+}
+
+/// Delete a catalog model
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/models/{id}",
+    tag = "Admin",
+    security(("bearer_auth" = [])),
+    params(
+        ("id" = Uuid, Path, description = "Model identifier")
+    ),
+    responses(
+        (status = 204, description = "Model deleted"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "Model not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn delete_model(
+    admin: AdminUser,
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    state
+        .ai_model_repository
+        .get(id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    state
+        .ai_model_repository
+        .delete(id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    tracing::info!("Admin {} deleted model {}", admin.0.id, id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Sync the catalog from the providers' model-listing APIs
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/models/sync",
+    tag = "Admin",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Providers that synced; failures are logged", body = [SyncReportResponse]),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn sync_models(
+    admin: AdminUser,
+    state: State<AppState>,
+) -> Result<Json<Vec<SyncReportResponse>>, StatusCode> {
+    tracing::info!("Admin {} started a model catalog sync", admin.0.id);
+
+    let reports = catalog::sync_configured(state.ai_model_repository.as_ref())
+        .await
+        .map_err(|e| {
+            tracing::error!("Model catalog sync failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(
+        reports.into_iter().map(SyncReportResponse::from).collect(),
+    ))
+}
+
+async fn update(
+    state: &AppState,
+    id: Uuid,
+    model: UpdateAiModelDto,
+) -> Result<ModelResponse, ApiError> {
+    state
+        .ai_model_repository
+        .get(id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let model = state
+        .ai_model_repository
+        .update(id, model)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(ModelResponse::from(model))
+}
@@ -0,0 +1,521 @@
+use crate::{
+    AppState,
+    ai::types::{ContentPart, SUPPORTED_IMAGE_TYPES},
+    api::common::{ApiError, ErrorResponse},
+    db::prelude::*,
+    db::repositories::{TAttachmentRepository, TChatRepository},
+    env, extract,
+    middleware::auth::AuthenticatedUser,
+    storage::StorageBackend,
+};
+use axum::{
+    extract::{Multipart, Path, State, multipart::Field},
+    http::{StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Document types accepted besides images and `text/*`.
+const DOCUMENT_TYPES: &[&str] = &[
+    "application/pdf",
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "application/json",
+    "application/xml",
+    "application/x-yaml",
+    "application/toml",
+];
+
+/// Types by file extension, for browsers that send a generic or odd type for the file.
+const EXTENSION_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("pdf", "application/pdf"),
+    (
+        "docx",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    ),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("yaml", "application/x-yaml"),
+    ("yml", "application/x-yaml"),
+    ("toml", "application/toml"),
+    ("md", "text/markdown"),
+    ("markdown", "text/markdown"),
+    ("csv", "text/csv"),
+    ("txt", "text/plain"),
+    ("log", "text/plain"),
+    ("html", "text/html"),
+    ("css", "text/css"),
+    ("rs", "text/plain"),
+    ("py", "text/plain"),
+    ("js", "text/plain"),
+    ("ts", "text/plain"),
+    ("tsx", "text/plain"),
+    ("jsx", "text/plain"),
+    ("go", "text/plain"),
+    ("java", "text/plain"),
+    ("kt", "text/plain"),
+    ("c", "text/plain"),
+    ("h", "text/plain"),
+    ("cpp", "text/plain"),
+    ("hpp", "text/plain"),
+    ("cs", "text/plain"),
+    ("rb", "text/plain"),
+    ("php", "text/plain"),
+    ("swift", "text/plain"),
+    ("sh", "text/plain"),
+    ("sql", "text/plain"),
+];
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AttachmentResponse {
+    pub id: Uuid,
+    pub chat_id: Option<Uuid>,
+    /// Message the attachment was sent with; `null` until it is used in a chat
+    pub message_id: Option<Uuid>,
+    pub file_name: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    /// Whether text was extracted from the file for models that cannot read it
+    pub has_text: bool,
+    /// Authorized download endpoint for the file's content
+    pub url: String,
+    pub created_at: String,
+}
+
+impl From<AttachmentModel> for AttachmentResponse {
+    fn from(attachment: AttachmentModel) -> Self {
+        Self {
+            url: content_url(attachment.id),
+            id: attachment.id,
+            chat_id: attachment.chat_id,
+            message_id: attachment.message_id,
+            file_name: attachment.file_name,
+            mime_type: attachment.mime_type,
+            size_bytes: attachment.size_bytes,
+            has_text: attachment.extracted_text.is_some(),
+            created_at: attachment.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Multipart form of an upload; only used to document the request body.
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct UploadAttachmentForm {
+    /// The file; its part's content type is checked against the accepted types
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+    /// Chat the file is meant for; set at the latest when it is sent with a message
+    pub chat_id: Option<Uuid>,
+}
+
+/// Where the content of attachment `id` can be downloaded.
+pub fn content_url(id: Uuid) -> String {
+    format!("/api/v1/attachments/{}/content", id)
+}
+
+/// The message part referencing `attachment`: an image for image types, a file
+/// otherwise.
+pub fn content_part(attachment: &AttachmentModel) -> ContentPart {
+    if SUPPORTED_IMAGE_TYPES.contains(&attachment.mime_type.as_str()) {
+        ContentPart::Image {
+            url: content_url(attachment.id),
+            mime_type: Some(attachment.mime_type.clone()),
+            attachment_id: Some(attachment.id),
+        }
+    } else {
+        ContentPart::File {
+            name: attachment.file_name.clone(),
+            mime_type: Some(attachment.mime_type.clone()),
+            url: Some(content_url(attachment.id)),
+            attachment_id: Some(attachment.id),
+        }
+    }
+}
+
+/// Removes the stored bytes of attachments whose rows are already gone. Failures are
+/// only logged; the rows no longer point at the files.
+pub async fn delete_stored(state: &AppState, attachments: &[AttachmentModel]) {
+    for attachment in attachments {
+        if let Err(e) = state.storage.delete(&attachment.storage_key).await {
+            tracing::warn!(
+                "Failed to delete stored attachment {}: {}",
+                attachment.id,
+                e
+            );
+        }
+    }
+}
+
+/// Writes a file to storage and records it. The stored bytes are removed again when the
+/// row cannot be saved.
+pub async fn store(
+    state: &AppState,
+    user_id: &str,
+    chat_id: Option<Uuid>,
+    file_name: String,
+    mime_type: String,
+    bytes: Vec<u8>,
+    extracted_text: Option<String>,
+) -> Result<AttachmentModel, ApiError> {
+    let id = Uuid::new_v4();
+    let storage_key = format!("{}/{}", user_id, id);
+    let size_bytes = bytes.len() as i64;
+    state
+        .storage
+        .put(&storage_key, bytes, &mime_type)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to store attachment: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let attachment = state
+        .attachment_repository
+        .create(CreateAttachmentDto {
+            id,
+            user_id: user_id.to_string(),
+            chat_id,
+            file_name,
+            mime_type,
+            size_bytes,
+            storage_key: storage_key.clone(),
+            extracted_text,
+        })
+        .await;
+    match attachment {
+        Ok(attachment) => Ok(attachment),
+        Err(e) => {
+            tracing::error!("Failed to save attachment: {}", e);
+            let _ = state.storage.delete(&storage_key).await;
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into())
+        }
+    }
+}
+
+fn is_allowed_type(mime_type: &str) -> bool {
+    mime_type.starts_with("text/")
+        || SUPPORTED_IMAGE_TYPES.contains(&mime_type)
+        || DOCUMENT_TYPES.contains(&mime_type)
+}
+
+/// The declared type of an upload when it is accepted, else the type its extension
+/// suggests.
+fn resolve_type(declared: Option<&str>, file_name: &str) -> Option<String> {
+    let declared = declared
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_lowercase())
+        .filter(|value| !value.is_empty());
+    if let Some(mime_type) = declared.filter(|value| is_allowed_type(value)) {
+        return Some(mime_type);
+    }
+
+    let extension = file_name.rsplit_once('.')?.1.to_lowercase();
+    EXTENSION_TYPES
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, mime_type)| mime_type.to_string())
+}
+
+/// The last path segment of a client-supplied file name, without control characters.
+fn clean_file_name(name: Option<&str>) -> String {
+    let name = name
+        .unwrap_or_default()
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(255)
+        .collect::<String>();
+    let name = name.trim();
+
+    if name.is_empty() {
+        "file".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Reads a multipart field, giving up as soon as it exceeds `max_bytes`.
+async fn read_field(mut field: Field<'_>, max_bytes: usize) -> Result<Vec<u8>, ApiError> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| ApiError::new(e.status(), "invalid_upload", e.body_text()))?
+    {
+        if bytes.len() + chunk.len() > max_bytes {
+            return Err(too_large(max_bytes));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+fn too_large(max_bytes: usize) -> ApiError {
+    ApiError::new(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        "attachment_too_large",
+        format!(
+            "Attachments are limited to {} MB",
+            max_bytes / (1024 * 1024)
+        ),
+    )
+}
+
+/// Upload a file to attach to a chat message
+#[utoipa::path(
+    post,
+    path = "/api/v1/attachments",
+    tag = "Attachments",
+    security(("bearer_auth" = [])),
+    request_body(content = UploadAttachmentForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Attachment stored", body = AttachmentResponse),
+        (status = 400, description = "Missing file, unsupported type or invalid form", body = ErrorResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Chat not found", body = ErrorResponse),
+        (status = 413, description = "File exceeds MAX_ATTACHMENT_BYTES", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn upload_attachment(
+    user: AuthenticatedUser,
+    state: State<AppState>,
+    mut multipart: Multipart,
+) -> Result<Json<AttachmentResponse>, ApiError> {
+    let max_bytes = env::get_max_attachment_bytes();
+    let invalid = |message: &str| ApiError::new(StatusCode::BAD_REQUEST, "invalid_upload", message);
+
+    let mut file = None;
+    let mut chat_id = None;
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            too_large(max_bytes)
+        } else {
+            ApiError::new(e.status(), "invalid_upload", e.body_text())
+        }
+    })? {
+        // Owned, as reading a field consumes it
+        let name = field.name().map(str::to_string);
+        match name.as_deref() {
+            Some("file") => {
+                let file_name = clean_file_name(field.file_name());
+                let declared = field.content_type().map(str::to_string);
+                let bytes = read_field(field, max_bytes).await?;
+                file = Some((file_name, declared, bytes));
+            }
+            Some("chat_id") => {
+                let value = field
+                    .text()
+                    .await
+                    .map_err(|_| invalid("chat_id must be text"))?;
+                let value = value.trim();
+                if !value.is_empty() {
+                    chat_id = Some(
+                        Uuid::parse_str(value).map_err(|_| invalid("chat_id is not a valid id"))?,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Some((file_name, declared, bytes)) = file else {
+        return Err(invalid("The form has no `file` field"));
+    };
+    if bytes.is_empty() {
+        return Err(invalid("The file is empty"));
+    }
+    let Some(mime_type) = resolve_type(declared.as_deref(), &file_name) else {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "unsupported_attachment_type",
+            format!(
+                "{} is not a supported file type; upload images, PDF, Word, text or code files",
+                declared.unwrap_or(file_name)
+            ),
+        ));
+    };
+
+    if let Some(chat_id) = chat_id {
+        state
+            .chat_repository
+            .get(chat_id, &user.0.id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+    }
+
+    // A document that fails to parse is still stored, just without its text
+    let extracted_text = {
+        let mime_type = mime_type.clone();
+        let bytes = bytes.clone();
+        tokio::task::spawn_blocking(move || extract::extract_text(&mime_type, &bytes)).await
+    };
+    let extracted_text = match extracted_text {
+        Ok(Ok(text)) => text,
+        Ok(Err(e)) => {
+            tracing::warn!("Failed to extract text from {}: {}", file_name, e);
+            None
+        }
+        Err(e) => {
+            tracing::warn!("Text extraction for {} panicked: {}", file_name, e);
+            None
+        }
+    };
+
+    let attachment = store(
+        &state,
+        &user.0.id,
+        chat_id,
+        file_name,
+        mime_type,
+        bytes,
+        extracted_text,
+    )
+    .await?;
+
+    Ok(Json(AttachmentResponse::from(attachment)))
+}
+
+/// Get an attachment's details
+#[utoipa::path(
+    get,
+    path = "/api/v1/attachments/{id}",
+    tag = "Attachments",
+    security(("bearer_auth" = [])),
+    params(
+        ("id" = Uuid, Path, description = "Attachment identifier")
+    ),
+    responses(
+        (status = 200, description = "Attachment details", body = AttachmentResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Attachment not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn get_attachment(
+    user: AuthenticatedUser,
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<AttachmentResponse>, StatusCode> {
+    let attachment = state
+        .attachment_repository
+        .get(id, &user.0.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(AttachmentResponse::from(attachment)))
+}
+
+/// Download an attachment's content
+#[utoipa::path(
+    get,
+    path = "/api/v1/attachments/{id}/content",
+    tag = "Attachments",
+    security(("bearer_auth" = [])),
+    params(
+        ("id" = Uuid, Path, description = "Attachment identifier")
+    ),
+    responses(
+        (status = 200, description = "The file, with its stored content type", content_type = "application/octet-stream"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Attachment not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn download_attachment(
+    user: AuthenticatedUser,
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Response, StatusCode> {
+    let attachment = state
+        .attachment_repository
+        .get(id, &user.0.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let bytes = state
+        .storage
+        .get(&attachment.storage_key)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to read attachment {}: {}", attachment.id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    // Images display in place; anything else, HTML included, is only ever downloaded
+    let disposition = if SUPPORTED_IMAGE_TYPES.contains(&attachment.mime_type.as_str()) {
+        "inline"
+    } else {
+        "attachment"
+    };
+    let file_name: String = attachment
+        .file_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && c != '"' && c != '\\' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, attachment.mime_type),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("{}; filename=\"{}\"", disposition, file_name),
+            ),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            (header::CACHE_CONTROL, "private, max-age=3600".to_string()),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+/// Delete an attachment
+#[utoipa::path(
+    delete,
+    path = "/api/v1/attachments/{id}",
+    tag = "Attachments",
+    security(("bearer_auth" = [])),
+    params(
+        ("id" = Uuid, Path, description = "Attachment identifier")
+    ),
+    responses(
+        (status = 204, description = "Attachment deleted"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Attachment not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn delete_attachment(
+    user: AuthenticatedUser,
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    let attachment = state
+        .attachment_repository
+        .delete(id, &user.0.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    delete_stored(&state, &[attachment]).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
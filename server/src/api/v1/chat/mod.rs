@@ -1,35 +1,97 @@
 use crate::{
     AppState,
-    ai::types::{ChatMessage, ChatRequest as AIChatRequest},
+    ai::context::ContextBuilder,
+    ai::error::ProviderError,
+    ai::fallback::{self, ModelTarget},
+    ai::manager::{ProviderConfig, ProviderManager},
+    ai::summary::{summary_message, summary_request},
+    ai::tokens::TokenCounter,
+    ai::tools::{ToolContext, ToolRegistry},
+    ai::types::{
+        ChatMessage, ChatRequest as AIChatRequest, ChatResponseChunk, ChatStream, ContentPart,
+        InlineImage, SUPPORTED_IMAGE_TYPES, TokenUsage, ToolCall, ToolChoice, ToolDefinition,
+    },
+    api::common::{ApiError, ErrorResponse},
+    api::v1::{attachments, user_api_keys},
     db::prelude::*,
-    db::repositories::{TChatRepository, TUserApiKeyRepository},
+    db::repositories::{
+        TAiModelRepository, TAttachmentRepository, TChatRepository, TUserApiKeyRepository,
+    },
+    extract,
     middleware::auth::AuthenticatedUser,
+    storage::StorageBackend,
 };
 use axum::{
-    body::Body,
     extract::State,
     http::StatusCode,
-    response::{IntoResponse, Json, Response},
+    response::{
+        Json,
+        sse::{Event, KeepAlive, Sse},
+    },
 };
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio_stream::wrappers::ReceiverStream;
 use utoipa::ToSchema;
 
+/// Largest image accepted with a message, decoded; OpenAI's limit.
+const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+/// Largest chat request body: one image of `MAX_IMAGE_BYTES` in base64, which grows it
+/// by a third, plus room for the text and the rest of the JSON.
+pub const MAX_REQUEST_BYTES: usize = MAX_IMAGE_BYTES / 3 * 4 + 1024 * 1024;
+
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct ChatRequest {
     pub chat_id: uuid::Uuid,
+    /// Left empty to let the model continue after tool results were posted to the chat
+    #[serde(default)]
     pub message: String,
+    /// Images sent with `message` as base64 `data:` URLs, e.g.
+    /// `data:image/png;base64,iVBORw0...`. PNG, JPEG, GIF and WebP up to 20 MB each;
+    /// rejected for models without image input
+    #[serde(default)]
+    pub images: Vec<String>,
+    /// Files uploaded to `/api/v1/attachments` to send with `message`. Images among
+    /// them follow the same rules as `images`
+    #[serde(default)]
+    pub attachment_ids: Vec<uuid::Uuid>,
     pub model_provider: String,
     pub model_id: String,
     pub temperature: Option<f32>,
     pub max_tokens: Option<u32>,
     pub stream: Option<bool>,
+    /// Functions the model may call. The client runs them and posts each result as a
+    /// `tool` message carrying the call's id.
+    #[serde(default)]
+    pub tools: Vec<ToolDefinition>,
+    pub tool_choice: Option<ToolChoice>,
+    /// Also offer the server's built-in tools (current time, calculator, unit
+    /// conversion, chat history). The server runs the calls the model makes to them and
+    /// lets it continue, for up to `AGENT_MAX_ITERATIONS` rounds; only calls to `tools`
+    /// are returned to the client.
+    #[serde(default)]
+    pub server_tools: bool,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct ChatCompletionResponse {
     pub content: String,
+    pub reasoning: Option<String>,
+    /// Calls the client has to run; calls to the server's tools were already handled
+    pub tool_calls: Vec<ToolCall>,
+    /// Provider that answered, which differs from the request's after a fallback
+    pub provider: String,
     pub model: String,
     pub tokens_used: Option<u32>,
+    pub usage: Option<TokenUsage>,
+    /// What the answer cost at the model's catalog prices; `None` when it is not priced
+    pub cost: Option<rust_decimal::Decimal>,
     pub finish_reason: Option<String>,
 }
 
@@ -42,147 +104,993 @@ pub struct ChatCompletionResponse {
     request_body = ChatRequest,
     responses(
         (status = 200, description = "Chat completion response", body = ChatCompletionResponse),
-        (status = 400, description = "Invalid provider or API key"),
+        (status = 400, description = "Invalid provider, missing or rejected API key, or tools sent to a model without function calling", body = ErrorResponse),
         (status = 401, description = "Unauthorized"),
-        (status = 404, description = "Chat not found"),
-        (status = 500, description = "Internal server error")
+        (status = 404, description = "Chat or model not found", body = ErrorResponse),
+        (status = 413, description = "Conversation exceeds the model's context window", body = ErrorResponse),
+        (status = 422, description = "Blocked by the provider's content filter", body = ErrorResponse),
+        (status = 429, description = "Rate limited by the provider", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+        (status = 502, description = "Provider failed", body = ErrorResponse),
+        (status = 504, description = "Provider timed out", body = ErrorResponse)
     )
 )]
 pub async fn chat(
     user: AuthenticatedUser,
     state: State<AppState>,
     Json(payload): Json<ChatRequest>,
-) -> Result<Json<ChatCompletionResponse>, StatusCode> {
-    // Verify chat belongs to user
-    let _chat = state
-        .chat_repository
-        .get(payload.chat_id, &user.0.id)
+) -> Result<Json<ChatCompletionResponse>, ApiError> {
+    let PreparedChat {
+        manager,
+        primary,
+        fallbacks,
+        mut request,
+        user_message,
+        server_tools,
+        catalog,
+    } = prepare_chat(&user, &state, &payload, false).await?;
+
+    let (mut answered_by, mut ai_response) = manager
+        .chat_with_fallback(&primary, &fallbacks, request.clone())
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .map_err(provider_failure)?;
+
+    // Save user message
+    let mut parent_message_id = None;
+    if let Some(message) = &user_message {
+        parent_message_id = Some(save_user_message(&state, &user.0.id, &payload, message).await?);
+    }
 
-    // Get user's API key for the provider
-    let provider = match payload.model_provider.as_str() {
-        "openai" => AiProvider::OpenAI,
-        "anthropic" => AiProvider::Anthropic,
-        "google" => AiProvider::Google,
-        "deepseek" => AiProvider::DeepSeek,
-        "ollama" => AiProvider::Ollama,
-        _ => return Err(StatusCode::BAD_REQUEST),
+    let context = ToolContext {
+        user_id: user.0.id.clone(),
+        chat_id: payload.chat_id,
+        chat_repository: state.chat_repository.clone(),
     };
+    let mut rounds = 0;
 
-    let api_key = state
-        .user_api_key_repository
-        .get_default_for_provider(&user.0.id, &provider)
+    loop {
+        // Save assistant response
+        let assistant_seq = state
+            .chat_repository
+            .get_next_sequence_number(payload.chat_id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        let reply = ChatMessage::assistant(
+            ai_response.content.clone(),
+            ai_response.reasoning.clone(),
+            ai_response.tool_calls.clone(),
+        );
+        let assistant_message = state
+            .chat_repository
+            .create_message(CreateMessageDto {
+                model_used: Some(answered_by.model_id.clone()),
+                ..new_message(
+                    payload.chat_id,
+                    assistant_seq,
+                    parent_message_id,
+                    &reply,
+                    Some(message_metadata(&primary, &answered_by, false)),
+                )
+            })
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let assistant_message_id = assistant_message.id;
+
+        // Update token usage if available
+        if let Some(usage) = &ai_response.usage {
+            state
+                .chat_repository
+                .update_usage(
+                    assistant_message_id,
+                    message_usage(&catalog, &answered_by, &ai_response.model, usage),
+                )
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        }
+
+        let Some(registry) = &server_tools else {
+            break;
+        };
+        let tool_messages = run_server_tools(
+            registry,
+            &context,
+            assistant_message_id,
+            &ai_response.tool_calls,
+        )
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::BAD_REQUEST)?;
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        if tool_messages.is_empty() {
+            break;
+        }
+        // The client answers its own calls and continues the chat itself
+        if tool_messages.len() < ai_response.tool_calls.len() {
+            ai_response
+                .tool_calls
+                .retain(|call| !registry.contains(&call.name));
+            break;
+        }
 
-    // TODO: Decrypt API key
-    // For now, assume encrypted_key is plaintext (not secure!)
-    let decrypted_key = api_key.encrypted_key;
+        request.messages.push(reply);
+        request
+            .messages
+            .extend(tool_messages.into_iter().map(ChatMessage::from));
+        rounds += 1;
+        request.tool_choice = next_tool_choice(rounds);
+        parent_message_id = Some(assistant_message_id);
+
+        (answered_by, ai_response) = manager
+            .chat_with_fallback(&primary, &fallbacks, request.clone())
+            .await
+            .map_err(provider_failure)?;
+    }
+
+    Ok(Json(ChatCompletionResponse {
+        content: ai_response.content,
+        reasoning: ai_response.reasoning,
+        tool_calls: ai_response.tool_calls,
+        provider: answered_by.provider.as_str().to_string(),
+        model: ai_response.model,
+        tokens_used: ai_response.usage.map(|u| u.total_tokens()),
+        usage: ai_response.usage,
+        cost: ai_response
+            .usage
+            .and_then(|u| answer_cost(&catalog, &answered_by, &u)),
+        finish_reason: ai_response.finish_reason,
+    }))
+}
 
-    // Get messages for context
-    let messages = state
+fn provider_failure((target, e): (ModelTarget, ProviderError)) -> ApiError {
+    tracing::error!("AI provider error from {}: {}", target, e);
+    ApiError::provider(target.provider, &e)
+}
+
+/// Tool choice for the model call after `rounds` rounds of server-side tool calls. A
+/// forced choice only applies to the first call, and once the limit is reached the
+/// model has to answer in text.
+fn next_tool_choice(rounds: usize) -> Option<ToolChoice> {
+    (rounds >= crate::env::get_agent_max_iterations()).then_some(ToolChoice::None)
+}
+
+/// Runs the calls the server's registry handles, stores each result as a `tool` message
+/// answering `assistant_message_id` and returns the stored messages. Calls to client
+/// tools are left alone.
+async fn run_server_tools(
+    registry: &ToolRegistry,
+    context: &ToolContext,
+    assistant_message_id: uuid::Uuid,
+    tool_calls: &[ToolCall],
+) -> emixdiesel::Result<Vec<MessageModel>> {
+    let mut messages = Vec::new();
+
+    for call in tool_calls
+        .iter()
+        .filter(|call| registry.contains(&call.name))
+    {
+        let output = registry.execute(call, context).await;
+        let sequence_number = context
+            .chat_repository
+            .get_next_sequence_number(context.chat_id)
+            .await?;
+
+        let result = ChatMessage::tool_result(call.id.clone(), output.content, output.is_error);
+        let message = context
+            .chat_repository
+            .create_message(new_message(
+                context.chat_id,
+                sequence_number,
+                Some(assistant_message_id),
+                &result,
+                Some(serde_json::json!({
+                    "tool_name": call.name,
+                    "executed_by": "server",
+                    "is_error": output.is_error,
+                })),
+            ))
+            .await?;
+        messages.push(message);
+    }
+
+    Ok(messages)
+}
+
+/// Saves the new user turn and links the uploads sent with it to the saved message.
+async fn save_user_message(
+    state: &AppState,
+    user_id: &str,
+    payload: &ChatRequest,
+    message: &ChatMessage,
+) -> Result<uuid::Uuid, ApiError> {
+    let sequence_number = state
         .chat_repository
-        .list_messages(payload.chat_id, &user.0.id)
+        .get_next_sequence_number(payload.chat_id)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Convert to AI provider format
-    let ai_messages: Vec<ChatMessage> = messages
-        .into_iter()
-        .map(|m| ChatMessage {
-            role: m.role,
-            content: m.content,
+    let saved = state
+        .chat_repository
+        .create_message(new_message(
+            payload.chat_id,
+            sequence_number,
+            None,
+            message,
+            None,
+        ))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    state
+        .attachment_repository
+        .link_to_message(&payload.attachment_ids, user_id, payload.chat_id, saved.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(saved.id)
+}
+
+/// Loads the uploads named by the request, in request order, and checks that they may
+/// be sent in this chat.
+async fn load_attachments(
+    state: &AppState,
+    user_id: &str,
+    payload: &ChatRequest,
+) -> Result<Vec<AttachmentModel>, ApiError> {
+    if payload.attachment_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let found = state
+        .attachment_repository
+        .get_many(&payload.attachment_ids, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut attachments = Vec::with_capacity(payload.attachment_ids.len());
+    for id in &payload.attachment_ids {
+        let Some(attachment) = found.iter().find(|a| a.id == *id) else {
+            return Err(ApiError::new(
+                StatusCode::NOT_FOUND,
+                "attachment_not_found",
+                format!("Attachment {} not found", id),
+            ));
+        };
+        // Each upload belongs to one message; resending it would move it off the first
+        if attachment.message_id.is_some()
+            || attachment
+                .chat_id
+                .is_some_and(|chat_id| chat_id != payload.chat_id)
+        {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "attachment_in_use",
+                format!(
+                    "{} was already sent in a chat; upload it again to send it here",
+                    attachment.file_name
+                ),
+            ));
+        }
+        if SUPPORTED_IMAGE_TYPES.contains(&attachment.mime_type.as_str())
+            && attachment.size_bytes as usize > MAX_IMAGE_BYTES
+        {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_image",
+                format!(
+                    "Images are limited to {} MB",
+                    MAX_IMAGE_BYTES / (1024 * 1024)
+                ),
+            ));
+        }
+        attachments.push(attachment.clone());
+    }
+
+    Ok(attachments)
+}
+
+/// Resolves uploads in `messages` for the provider: images become `data:` URLs, the one
+/// form every provider accepts, and files become their extracted text between
+/// `<attachment>` tags. Uploads that are gone are left out.
+async fn inline_attachments(
+    state: &AppState,
+    user_id: &str,
+    messages: &mut [ChatMessage],
+) -> Result<(), ApiError> {
+    let ids: Vec<uuid::Uuid> = messages
+        .iter()
+        .flat_map(|message| &message.content)
+        .filter_map(|part| match part {
+            ContentPart::Image {
+                attachment_id: Some(id),
+                ..
+            }
+            | ContentPart::File {
+                attachment_id: Some(id),
+                ..
+            } => Some(*id),
+            _ => None,
         })
         .collect();
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let found: HashMap<uuid::Uuid, AttachmentModel> = state
+        .attachment_repository
+        .get_many(&ids, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .map(|attachment| (attachment.id, attachment))
+        .collect();
+    let mut image_urls = HashMap::new();
+    for attachment in found
+        .values()
+        .filter(|a| SUPPORTED_IMAGE_TYPES.contains(&a.mime_type.as_str()))
+    {
+        match state.storage.get(&attachment.storage_key).await {
+            Ok(bytes) => {
+                let url = format!(
+                    "data:{};base64,{}",
+                    attachment.mime_type,
+                    STANDARD.encode(bytes)
+                );
+                image_urls.insert(attachment.id, url);
+            }
+            Err(e) => tracing::warn!("Failed to read attachment {}: {}", attachment.id, e),
+        }
+    }
+
+    let max_chars = crate::env::get_attachment_context_max_chars();
+    for message in messages {
+        let parts = std::mem::take(&mut message.content);
+        for part in parts {
+            let part = match part {
+                ContentPart::Image {
+                    mime_type,
+                    attachment_id: Some(id),
+                    ..
+                } => match image_urls.get(&id) {
+                    Some(url) => ContentPart::Image {
+                        url: url.clone(),
+                        mime_type,
+                        attachment_id: Some(id),
+                    },
+                    None => continue,
+                },
+                ContentPart::File {
+                    attachment_id: Some(id),
+                    ..
+                } => match found.get(&id) {
+                    Some(attachment) => ContentPart::Text {
+                        text: extract::context_block(
+                            &attachment.file_name,
+                            &attachment.mime_type,
+                            attachment.extracted_text.as_deref(),
+                            max_chars,
+                        ),
+                    },
+                    None => continue,
+                },
+                part => part,
+            };
+            message.content.push(part);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks an image sent with a message and turns it into a content part.
+fn image_part(url: &str) -> Result<ContentPart, ApiError> {
+    let invalid =
+        |message: String| ApiError::new(StatusCode::BAD_REQUEST, "invalid_image", message);
+
+    let Some(image) = InlineImage::parse(url) else {
+        return Err(invalid(
+            "Images must be sent as base64 data: URLs".to_string(),
+        ));
+    };
+    if !SUPPORTED_IMAGE_TYPES.contains(&image.mime_type) {
+        return Err(invalid(format!(
+            "Unsupported image type {}; use PNG, JPEG, GIF or WebP",
+            image.mime_type
+        )));
+    }
+    let bytes = STANDARD
+        .decode(image.data)
+        .map_err(|_| invalid("Image data is not valid base64".to_string()))?;
+    if bytes.len() > MAX_IMAGE_BYTES {
+        return Err(invalid(format!(
+            "Images are limited to {} MB",
+            MAX_IMAGE_BYTES / (1024 * 1024)
+        )));
+    }
+
+    Ok(ContentPart::Image {
+        url: url.to_string(),
+        mime_type: Some(image.mime_type.to_string()),
+        attachment_id: None,
+    })
+}
+
+/// A chat's history as it is sent to a provider, ending with the new user turn.
+struct History {
+    messages: Vec<ChatMessage>,
+    /// Per message, whether it is pinned.
+    pinned: Vec<bool>,
+    /// Per message, its sequence number if it is stored.
+    sequence_numbers: Vec<Option<i32>>,
+    /// The chat's summary, the first of `messages` when set.
+    summary: Option<String>,
+    /// The new user turn; `None` when the model continues after tool results.
+    user_message: Option<ChatMessage>,
+}
+
+impl History {
+    fn push(&mut self, message: ChatMessage, pinned: bool, sequence_number: Option<i32>) {
+        self.messages.push(message);
+        self.pinned.push(pinned);
+        self.sequence_numbers.push(sequence_number);
+    }
+
+    /// Replaces the messages at `indices`, in ascending order, and any earlier summary
+    /// by `summary`.
+    fn fold_into_summary(&mut self, indices: &[usize], summary: String) {
+        for index in indices.iter().rev() {
+            self.messages.remove(*index);
+            self.pinned.remove(*index);
+            self.sequence_numbers.remove(*index);
+        }
+
+        let message = summary_message(&summary);
+        if self.summary.is_some() {
+            self.messages[0] = message;
+        } else {
+            self.messages.insert(0, message);
+            self.pinned.insert(0, true);
+            self.sequence_numbers.insert(0, None);
+        }
+        self.summary = Some(summary);
+    }
+}
+
+/// Loads the stored history, appends the request's message and resolves the uploads
+/// it refers to. Messages covered by the chat's summary are replaced by it.
+async fn load_history(
+    state: &AppState,
+    user_id: &str,
+    chat: &ChatModel,
+    payload: &ChatRequest,
+    uploads: &[AttachmentModel],
+    supports_images: bool,
+) -> Result<History, ApiError> {
+    let stored = state
+        .chat_repository
+        .list_messages(payload.chat_id, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let summarized_through = chat.summary.as_ref().and(chat.summarized_through);
+    let mut history = History {
+        messages: Vec::with_capacity(stored.len() + 2),
+        pinned: Vec::with_capacity(stored.len() + 2),
+        sequence_numbers: Vec::with_capacity(stored.len() + 2),
+        summary: chat
+            .summary
+            .clone()
+            .filter(|_| summarized_through.is_some()),
+        user_message: None,
+    };
+    if let Some(message) = history.summary.as_deref().map(summary_message) {
+        history.push(message, true, None);
+    }
+
+    for message in stored {
+        let pinned = message.is_pinned;
+        let sequence_number = message.sequence_number;
+        // System and pinned messages are sent even when the summary covers them
+        if summarized_through.is_some_and(|through| sequence_number <= through)
+            && message.role != MessageRole::System
+            && !pinned
+        {
+            continue;
+        }
+
+        let mut message = ChatMessage::from(message);
+        // Images from earlier turns are left out for models that cannot see them, and
+        // generated images always are: providers only take images on user turns
+        if !(supports_images && message.role == MessageRole::User) && message.has_images() {
+            message
+                .content
+                .retain(|part| !matches!(part, ContentPart::Image { .. }));
+            // A turn that was nothing but images is dropped rather than sent empty
+            if message.is_empty() {
+                continue;
+            }
+        }
+        history.push(message, pinned, Some(sequence_number));
+    }
 
     // Add user's new message
-    let mut ai_messages = ai_messages;
-    ai_messages.push(ChatMessage {
+    let mut content = Vec::with_capacity(1 + payload.images.len() + uploads.len());
+    if !payload.message.is_empty() {
+        content.push(ContentPart::Text {
+            text: payload.message.clone(),
+        });
+    }
+    for url in &payload.images {
+        content.push(image_part(url)?);
+    }
+    content.extend(uploads.iter().map(attachments::content_part));
+    history.user_message = (!content.is_empty()).then(|| ChatMessage {
         role: MessageRole::User,
-        content: payload.message.clone(),
+        content,
     });
+    if let Some(message) = history.user_message.clone() {
+        history.push(message, false, None);
+    }
+    inline_attachments(state, user_id, &mut history.messages).await?;
 
-    // Create provider instance
-    let mut api_keys = std::collections::HashMap::new();
-    api_keys.insert(provider.clone(), decrypted_key);
-    let provider_manager = crate::ai::manager::ProviderManager::new(api_keys)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(history)
+}
 
-    let ai_provider = provider_manager
-        .get_provider(&provider)
-        .ok_or(StatusCode::BAD_REQUEST)?;
+/// The model that summarizes long chats: `AI_SUMMARY_MODEL` when the user has a key
+/// for its provider, otherwise the chat's own model.
+async fn summary_model(
+    state: &AppState,
+    user_id: &str,
+    primary: &ModelTarget,
+    catalog: &[AiModelModel],
+    manager: &mut ProviderManager,
+) -> Result<ModelTarget, ApiError> {
+    let Some(target) =
+        crate::env::get_ai_summary_model().and_then(|entry| ModelTarget::resolve(&entry, catalog))
+    else {
+        return Ok(primary.clone());
+    };
 
-    // Call AI provider
-    let ai_request = AIChatRequest {
-        model: payload.model_id.clone(),
-        messages: ai_messages,
-        temperature: payload.temperature,
-        max_tokens: payload.max_tokens,
-        stream: false,
+    if manager.get_provider(&target.provider).is_none() {
+        let Some(config) = provider_config(state, user_id, &target).await? else {
+            return Ok(primary.clone());
+        };
+        if let Err(e) = manager.insert(target.provider, config) {
+            tracing::warn!("Summarizing with {} instead of {}: {}", primary, target, e);
+            return Ok(primary.clone());
+        }
+    }
+
+    Ok(target)
+}
+
+/// Folds the messages at `indices` into the chat's summary with `model`, saves it and
+/// puts it in their place in `history`.
+async fn summarize(
+    state: &AppState,
+    manager: &ProviderManager,
+    model: &ModelTarget,
+    chat_id: uuid::Uuid,
+    history: &mut History,
+    indices: &[usize],
+) -> anyhow::Result<()> {
+    let Some(summarized_through) = indices
+        .iter()
+        .filter_map(|index| history.sequence_numbers[*index])
+        .max()
+    else {
+        return Ok(());
     };
 
-    let ai_response = ai_provider.chat(ai_request).await.map_err(|e| {
-        tracing::error!("AI provider error: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let covered: Vec<&ChatMessage> = indices
+        .iter()
+        .map(|index| &history.messages[*index])
+        .collect();
+    let request = summary_request(&model.model_id, history.summary.as_deref(), &covered);
+    let (_, response) = manager
+        .chat_with_fallback(model, &[], request)
+        .await
+        .map_err(|(_, e)| e)?;
+    let summary = response.content.trim().to_string();
+    if summary.is_empty() {
+        anyhow::bail!("{} returned an empty summary", model);
+    }
 
-    // Save user message
-    let user_seq = state
+    state
         .chat_repository
-        .get_next_sequence_number(payload.chat_id)
+        .update_summary(chat_id, &summary, summarized_through)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|_| anyhow::anyhow!("Failed to save the summary of chat {}", chat_id))?;
+    history.fold_into_summary(indices, summary);
 
-    state
+    Ok(())
+}
+
+/// Fits prompts into the context window of `model`, leaving room for `max_tokens`.
+/// Models missing from the catalog are sent the whole history.
+fn context_builder(
+    target: &ModelTarget,
+    model: Option<&AiModelModel>,
+    max_tokens: Option<u32>,
+) -> ContextBuilder {
+    let context_window = model.and_then(|m| u32::try_from(m.context_window).ok());
+
+    ContextBuilder::new(
+        TokenCounter::new(target.provider, &target.model_id),
+        context_window,
+        max_tokens,
+    )
+}
+
+/// Everything needed to send a chat turn: configured providers, the chat's own model
+/// and the fallbacks to try when it fails.
+struct PreparedChat {
+    manager: ProviderManager,
+    primary: ModelTarget,
+    fallbacks: Vec<ModelTarget>,
+    request: AIChatRequest,
+    /// The new user turn, already the last message of `request`; `None` when the model
+    /// continues after tool results.
+    user_message: Option<ChatMessage>,
+    /// Set when the server runs its built-in tools for this request.
+    server_tools: Option<Arc<ToolRegistry>>,
+    /// Active models, whose prices cost the answers.
+    catalog: Vec<AiModelModel>,
+}
+
+/// Verifies the chat, resolves the user's provider keys for the model and its fallback
+/// chain, and builds the provider request from the stored history plus the new message,
+/// if any.
+async fn prepare_chat(
+    user: &AuthenticatedUser,
+    state: &AppState,
+    payload: &ChatRequest,
+    stream: bool,
+) -> Result<PreparedChat, ApiError> {
+    // Verify chat belongs to user
+    let chat = state
         .chat_repository
-        .create_message(CreateMessageDto {
-            chat_id: payload.chat_id,
-            role: MessageRole::User,
-            content: payload.message,
-            metadata: None,
-            parent_message_id: None,
-            sequence_number: user_seq,
-        })
+        .get(payload.chat_id, &user.0.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let provider = AiProvider::from_str(&payload.model_provider).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_provider",
+            format!("Unknown provider '{}'", payload.model_provider),
+        )
+    })?;
+    let primary = ModelTarget::new(provider, payload.model_id.clone());
+
+    // Self-hosted providers such as Ollama work without a stored key
+    let Some(config) = provider_config(state, &user.0.id, &primary).await? else {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "missing_api_key",
+            format!(
+                "No {} API key is configured; add one to use this model",
+                provider.display_name()
+            ),
+        ));
+    };
+
+    let mut manager = ProviderManager::default();
+    manager.insert(provider, config).map_err(|e| {
+        tracing::error!("Failed to configure AI provider: {}", e);
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "provider_not_configured",
+            e.to_string(),
+        )
+    })?;
+
+    // Fallbacks on providers the user has no key for are skipped
+    let catalog = state
+        .ai_model_repository
+        .list_active()
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut fallbacks = Vec::new();
+    for target in fallback::fallbacks_for(&primary, chat.fallback_models.as_ref(), &catalog) {
+        if manager.get_provider(&target.provider).is_none() {
+            let Some(config) = provider_config(state, &user.0.id, &target).await? else {
+                continue;
+            };
+            if let Err(e) = manager.insert(target.provider, config) {
+                tracing::warn!("Skipping fallback model {}: {}", target, e);
+                continue;
+            }
+        }
+        fallbacks.push(target);
+    }
+
+    // The catalog's defaults fill in parameters the request leaves unset
+    let model = catalog
+        .iter()
+        .find(|m| m.provider == provider && m.model_id == payload.model_id);
+
+    let uploads = load_attachments(state, &user.0.id, payload).await?;
+    let sends_images = !payload.images.is_empty()
+        || uploads
+            .iter()
+            .any(|a| SUPPORTED_IMAGE_TYPES.contains(&a.mime_type.as_str()));
+    let supports_images = model.is_none_or(|m| m.supports_images);
+    if sends_images && !supports_images {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "images_not_supported",
+            format!("{} does not accept images", payload.model_id),
+        ));
+    }
+
+    let supports_functions = model.is_none_or(|m| m.supports_functions);
+    if !payload.tools.is_empty() && !supports_functions {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "tools_not_supported",
+            format!("{} does not support function calling", payload.model_id),
+        ));
+    }
+
+    // Built-in tools are simply not offered to models without function calling
+    let server_tools =
+        (payload.server_tools && supports_functions).then(|| state.tool_registry.clone());
+    let mut tools = payload.tools.clone();
+    if let Some(registry) = &server_tools {
+        if let Some(tool) = payload.tools.iter().find(|t| registry.contains(&t.name)) {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "duplicate_tool",
+                format!("`{}` is the name of a built-in tool", tool.name),
+            ));
+        }
+        tools.extend(registry.definitions());
+    }
+
+    let mut history =
+        load_history(state, &user.0.id, &chat, payload, &uploads, supports_images).await?;
+
+    let max_tokens = payload
+        .max_tokens
+        .or_else(|| model.and_then(|m| m.default_max_tokens()));
+    let builder = context_builder(&primary, model, max_tokens);
+
+    // Turns that no longer fit are folded into the chat's summary. It is made to cover
+    // enough of them that the rest fills half the budget, leaving room for the turns to
+    // come before it has to be updated again.
+    if let Some(budget) = builder
+        .budget()
+        .filter(|_| crate::env::is_chat_summarization_enabled())
+    {
+        let overflow = builder.select(&history.messages, &history.pinned, &tools, budget);
+        if !overflow.left_out.is_empty() {
+            let selection = builder.select(&history.messages, &history.pinned, &tools, budget / 2);
+            let summarizer =
+                summary_model(state, &user.0.id, &primary, &catalog, &mut manager).await?;
+            if let Err(e) = summarize(
+                state,
+                &manager,
+                &summarizer,
+                chat.id,
+                &mut history,
+                &selection.left_out,
+            )
+            .await
+            {
+                tracing::warn!("Failed to summarize chat {}: {}", chat.id, e);
+            }
+        }
+    }
+
+    // The oldest turns are left out once the chat outgrows the model's context window
+    let History {
+        messages,
+        pinned,
+        user_message,
+        ..
+    } = history;
+    let context = builder.fit(messages, &pinned, &tools);
+    if !context.fits() {
+        return Err(ApiError::provider(
+            provider,
+            &ProviderError::ContextLengthExceeded(format!(
+                "The prompt takes about {} tokens, but at most {} are available",
+                context.prompt_tokens,
+                context.budget.unwrap_or_default()
+            )),
+        ));
+    }
+    if context.dropped_messages > 0 {
+        tracing::debug!(
+            "Left {} messages of chat {} out of the context",
+            context.dropped_messages,
+            payload.chat_id
+        );
+    }
+    let ai_messages = context.messages;
+
+    // A fallback that cannot see the images would fail or answer without them
+    if ai_messages.iter().any(ChatMessage::has_images) {
+        fallbacks.retain(|target| {
+            catalog
+                .iter()
+                .find(|m| m.provider == target.provider && m.model_id == target.model_id)
+                .is_none_or(|m| m.supports_images)
+        });
+    }
+
+    // Build provider request
+    let request = AIChatRequest {
+        model: payload.model_id.clone(),
+        messages: ai_messages,
+        temperature: payload
+            .temperature
+            .or_else(|| model.and_then(|m| m.default_temperature())),
+        max_tokens,
+        stream,
+        tools,
+        tool_choice: payload.tool_choice.clone(),
+    };
+
+    Ok(PreparedChat {
+        manager,
+        primary,
+        fallbacks,
+        request,
+        user_message,
+        server_tools,
+        catalog,
+    })
+}
 
-    // Save assistant response
-    let assistant_seq = state
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChatEstimateResponse {
+    /// Tokens the request would send, after older messages are left out to fit
+    pub prompt_tokens: u32,
+    /// Tokens of the whole chat plus the new message
+    pub history_tokens: u32,
+    /// Tokens the prompt may take: the model's context window minus `max_tokens`, or
+    /// minus 1024 when that is unset. `null` when the context window is unknown
+    pub budget: Option<u32>,
+    pub context_window: Option<u32>,
+    pub max_tokens: Option<u32>,
+    /// Messages that would be sent, including the new one
+    pub messages: usize,
+    /// Older messages that would be left out, or folded into the chat's summary when
+    /// summarization is on
+    pub dropped_messages: usize,
+    /// False when the system, pinned and latest messages alone exceed `budget`; the
+    /// request would be rejected
+    pub fits: bool,
+    /// True when counted with the model's own tokenizer; other counts are estimates
+    pub exact: bool,
+}
+
+/// Count the tokens a chat request would send, without sending it
+#[utoipa::path(
+    post,
+    path = "/api/v1/chat/estimate",
+    tag = "Chat",
+    security(("bearer_auth" = [])),
+    request_body = ChatRequest,
+    responses(
+        (status = 200, description = "Prompt token counts", body = ChatEstimateResponse),
+        (status = 400, description = "Invalid provider, image or attachment", body = ErrorResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Chat or attachment not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn estimate_chat(
+    user: AuthenticatedUser,
+    state: State<AppState>,
+    Json(payload): Json<ChatRequest>,
+) -> Result<Json<ChatEstimateResponse>, ApiError> {
+    // Verify chat belongs to user
+    let chat = state
         .chat_repository
-        .get_next_sequence_number(payload.chat_id)
+        .get(payload.chat_id, &user.0.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let provider = AiProvider::from_str(&payload.model_provider).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_provider",
+            format!("Unknown provider '{}'", payload.model_provider),
+        )
+    })?;
+    let target = ModelTarget::new(provider, payload.model_id.clone());
+    let model = state
+        .ai_model_repository
+        .get_by_provider_and_model_id(&provider, &payload.model_id)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let model = model.as_ref();
 
-    let assistant_message = state
-        .chat_repository
-        .create_message(CreateMessageDto {
-            chat_id: payload.chat_id,
-            role: MessageRole::Assistant,
-            content: ai_response.content.clone(),
-            metadata: None,
-            parent_message_id: None,
-            sequence_number: assistant_seq,
-        })
+    let uploads = load_attachments(&state, &user.0.id, &payload).await?;
+    let supports_images = model.is_none_or(|m| m.supports_images);
+    let mut tools = payload.tools.clone();
+    if payload.server_tools && model.is_none_or(|m| m.supports_functions) {
+        tools.extend(state.tool_registry.definitions());
+    }
+
+    let History {
+        messages, pinned, ..
+    } = load_history(
+        &state,
+        &user.0.id,
+        &chat,
+        &payload,
+        &uploads,
+        supports_images,
+    )
+    .await?;
+    let max_tokens = payload
+        .max_tokens
+        .or_else(|| model.and_then(|m| m.default_max_tokens()));
+    let context = context_builder(&target, model, max_tokens).fit(messages, &pinned, &tools);
+
+    Ok(Json(ChatEstimateResponse {
+        prompt_tokens: context.prompt_tokens,
+        history_tokens: context.history_tokens,
+        budget: context.budget,
+        context_window: model.and_then(|m| u32::try_from(m.context_window).ok()),
+        max_tokens,
+        messages: context.messages.len(),
+        dropped_messages: context.dropped_messages,
+        fits: context.fits(),
+        exact: TokenCounter::new(provider, &payload.model_id).is_exact(),
+    }))
+}
+
+/// Connection settings for `target`'s provider from the user's default key, or `None`
+/// when the provider needs a key and the user has not stored one. Endpoint settings on
+/// the key take precedence over those of the catalog entry.
+pub async fn provider_config(
+    state: &AppState,
+    user_id: &str,
+    target: &ModelTarget,
+) -> Result<Option<ProviderConfig>, ApiError> {
+    let api_key = state
+        .user_api_key_repository
+        .get_default_for_provider(user_id, &target.provider)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Update token usage if available
-    if let Some(tokens) = ai_response.tokens_used {
-        state
-            .chat_repository
-            .update_tokens_used(assistant_message.id, tokens as i32, &ai_response.model)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if api_key.is_none() && target.provider.requires_api_key() {
+        return Ok(None);
+    }
+    // Checked on every use: the host may resolve elsewhere now, or the key predates the
+    // checks
+    if let Some(key) = &api_key {
+        user_api_keys::check_endpoint(key.base_url.as_deref(), &user_api_keys::extra_headers(key))
+            .await?;
     }
 
-    Ok(Json(ChatCompletionResponse {
-        content: ai_response.content,
-        model: ai_response.model,
-        tokens_used: ai_response.tokens_used,
-        finish_reason: ai_response.finish_reason,
+    let model = state
+        .ai_model_repository
+        .get_by_provider_and_model_id(&target.provider, &target.model_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let base_url = api_key
+        .as_ref()
+        .and_then(|k| k.base_url.clone())
+        .or_else(|| model.as_ref().and_then(|m| m.base_url.clone()));
+    let extra_headers = api_key
+        .as_ref()
+        .and_then(|k| k.extra_headers.clone())
+        .or_else(|| model.as_ref().and_then(|m| m.extra_headers.clone()))
+        .and_then(|headers| serde_json::from_value(headers).ok())
+        .unwrap_or_default();
+
+    // TODO: Decrypt API key
+    // For now, assume encrypted_key is plaintext (not secure!)
+    Ok(Some(ProviderConfig {
+        api_key: api_key.map(|k| k.encrypted_key),
+        base_url,
+        extra_headers,
     }))
 }
 
@@ -194,25 +1102,449 @@ pub async fn chat(
     security(("bearer_auth" = [])),
     request_body = ChatRequest,
     responses(
-        (status = 200, description = "Streaming chat completion response"),
-        (status = 400, description = "Invalid provider or API key"),
+        (status = 200, description = "Server-sent events: message_start, reasoning, delta, tool_call, tool_result, step_start, usage, done or error", content_type = "text/event-stream"),
+        (status = 400, description = "Invalid provider, missing or rejected API key, or tools sent to a model without function calling", body = ErrorResponse),
         (status = 401, description = "Unauthorized"),
-        (status = 404, description = "Chat not found"),
-        (status = 500, description = "Internal server error")
+        (status = 404, description = "Chat or model not found", body = ErrorResponse),
+        (status = 413, description = "Conversation exceeds the model's context window", body = ErrorResponse),
+        (status = 422, description = "Blocked by the provider's content filter", body = ErrorResponse),
+        (status = 429, description = "Rate limited by the provider", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+        (status = 502, description = "Provider failed", body = ErrorResponse),
+        (status = 504, description = "Provider timed out", body = ErrorResponse)
     )
 )]
 pub async fn stream_chat(
     user: AuthenticatedUser,
     state: State<AppState>,
     Json(payload): Json<ChatRequest>,
-) -> Result<impl IntoResponse, StatusCode> {
-    // For now, return a simple non-streaming response
-    // Full SSE streaming implementation would go here
-    let response = chat(user, state, Json(payload)).await?;
-
-    Ok(Response::builder()
-        .status(StatusCode::OK)
-        .header("Content-Type", "application/json")
-        .body(Body::from(serde_json::to_string(&response.0).unwrap()))
-        .unwrap())
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let PreparedChat {
+        manager,
+        primary,
+        fallbacks,
+        mut request,
+        user_message,
+        server_tools,
+        catalog,
+    } = prepare_chat(&user, &state, &payload, true).await?;
+
+    // Open the provider stream before persisting anything so a rejected request
+    // (bad key, unknown model) leaves the chat untouched
+    let (mut answered_by, mut ai_stream) = manager
+        .stream_chat_with_fallback(&primary, &fallbacks, request.clone())
+        .await
+        .map_err(provider_failure)?;
+
+    let mut user_message_id = None;
+    if let Some(message) = &user_message {
+        user_message_id = Some(save_user_message(&state, &user.0.id, &payload, message).await?);
+    }
+
+    let (tx, rx) = tokio::sync::mpsc::channel::<Event>(64);
+    let context = ToolContext {
+        user_id: user.0.id.clone(),
+        chat_id: payload.chat_id,
+        chat_repository: state.chat_repository.clone(),
+    };
+
+    // The provider stream is driven by a detached task rather than by the response body
+    // so the partial answer can still be saved when the client goes away mid-stream.
+    tokio::spawn(async move {
+        let chat_id = context.chat_id;
+        let _ = tx
+            .send(sse_event(
+                "message_start",
+                serde_json::json!({
+                    "user_message_id": user_message_id,
+                    "provider": answered_by.provider.as_str(),
+                    "model": answered_by.model_id,
+                }),
+            ))
+            .await;
+
+        let mut parent_message_id = user_message_id;
+        let mut rounds = 0;
+
+        loop {
+            let step = forward_stream(&mut ai_stream, &tx, server_tools.as_deref()).await;
+
+            if step.disconnected {
+                tracing::info!("Client disconnected from chat {} stream", chat_id);
+            }
+
+            let usage = step.final_chunk.as_ref().and_then(|chunk| {
+                let model = chunk.model.as_deref().unwrap_or(&answered_by.model_id);
+                Some(message_usage(
+                    &catalog,
+                    &answered_by,
+                    model,
+                    chunk.usage.as_ref()?,
+                ))
+            });
+
+            let mut assistant_message_id = None;
+            if step.has_output() {
+                let interrupted =
+                    step.disconnected || step.error.is_some() || step.final_chunk.is_none();
+                let metadata = message_metadata(&primary, &answered_by, interrupted);
+                match save_assistant_message(
+                    context.chat_repository.as_ref(),
+                    chat_id,
+                    parent_message_id,
+                    &step,
+                    metadata,
+                    &answered_by.model_id,
+                    usage.clone(),
+                )
+                .await
+                {
+                    Ok(id) => assistant_message_id = Some(id),
+                    Err(e) => tracing::error!("Failed to save streamed assistant message: {}", e),
+                }
+            }
+
+            if step.disconnected {
+                return;
+            }
+
+            if let Some(e) = step.error {
+                let mut payload =
+                    serde_json::to_value(ApiError::provider(answered_by.provider, &e).body)
+                        .unwrap_or_else(|_| serde_json::json!({ "message": e.to_string() }));
+                payload["message_id"] = serde_json::json!(assistant_message_id);
+                let _ = tx.send(sse_event("error", payload)).await;
+                return;
+            }
+
+            let tool_messages = match (&server_tools, assistant_message_id) {
+                (Some(registry), Some(id)) => {
+                    match run_server_tools(registry, &context, id, &step.tool_calls).await {
+                        Ok(messages) => messages,
+                        Err(e) => {
+                            tracing::error!("Failed to save tool results: {}", e);
+                            let error = ApiError::new(
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                "internal_error",
+                                "Failed to save tool results",
+                            );
+                            let _ = tx
+                                .send(sse_event("error", serde_json::json!(error.body)))
+                                .await;
+                            return;
+                        }
+                    }
+                }
+                _ => Vec::new(),
+            };
+            for message in &tool_messages {
+                let event = sse_event(
+                    "tool_result",
+                    serde_json::json!({
+                        "message_id": message.id,
+                        "tool_call_id": message.tool_call_id,
+                        "content": message.content,
+                        "metadata": message.metadata,
+                    }),
+                );
+                let _ = tx.send(event).await;
+            }
+
+            // The model continues only when the server answered all of its calls; the
+            // client answers its own and continues the chat itself
+            if tool_messages.is_empty() || tool_messages.len() < step.tool_calls.len() {
+                if let Some(usage) = &usage {
+                    let _ = tx
+                        .send(sse_event(
+                            "usage",
+                            serde_json::json!({
+                                "tokens_used": usage.prompt_tokens + usage.completion_tokens,
+                                "prompt_tokens": usage.prompt_tokens,
+                                "completion_tokens": usage.completion_tokens,
+                                "cached_tokens": usage.cached_tokens,
+                                "reasoning_tokens": usage.reasoning_tokens,
+                                "cost": usage.cost,
+                            }),
+                        ))
+                        .await;
+                }
+
+                let model = step
+                    .final_chunk
+                    .as_ref()
+                    .and_then(|c| c.model.clone())
+                    .unwrap_or(answered_by.model_id);
+                let _ = tx
+                    .send(sse_event(
+                        "done",
+                        serde_json::json!({
+                            "message_id": assistant_message_id,
+                            "model": model,
+                            "finish_reason": step.final_chunk.and_then(|c| c.finish_reason),
+                        }),
+                    ))
+                    .await;
+                return;
+            }
+
+            request.messages.push(ChatMessage::assistant(
+                step.content,
+                Some(step.reasoning),
+                step.tool_calls,
+            ));
+            request
+                .messages
+                .extend(tool_messages.into_iter().map(ChatMessage::from));
+            rounds += 1;
+            request.tool_choice = next_tool_choice(rounds);
+            parent_message_id = assistant_message_id;
+
+            match manager
+                .stream_chat_with_fallback(&primary, &fallbacks, request.clone())
+                .await
+            {
+                Ok((target, stream)) => {
+                    answered_by = target;
+                    ai_stream = stream;
+                }
+                Err(failure) => {
+                    let error = provider_failure(failure);
+                    let _ = tx
+                        .send(sse_event("error", serde_json::json!(error.body)))
+                        .await;
+                    return;
+                }
+            }
+
+            let _ = tx
+                .send(sse_event(
+                    "step_start",
+                    serde_json::json!({
+                        "provider": answered_by.provider.as_str(),
+                        "model": answered_by.model_id,
+                    }),
+                ))
+                .await;
+        }
+    });
+
+    let events = ReceiverStream::new(rx).map(Ok::<_, Infallible>);
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// What one provider stream produced before it ended, failed or the client went away.
+#[derive(Default)]
+struct StreamedStep {
+    content: String,
+    reasoning: String,
+    tool_calls: Vec<ToolCall>,
+    final_chunk: Option<ChatResponseChunk>,
+    error: Option<ProviderError>,
+    disconnected: bool,
+}
+
+impl StreamedStep {
+    fn has_output(&self) -> bool {
+        !self.content.is_empty() || !self.reasoning.is_empty() || !self.tool_calls.is_empty()
+    }
+}
+
+/// Relays one provider stream to the client as reasoning, delta and tool_call events
+/// and collects what it produced.
+async fn forward_stream(
+    ai_stream: &mut ChatStream,
+    tx: &tokio::sync::mpsc::Sender<Event>,
+    server_tools: Option<&ToolRegistry>,
+) -> StreamedStep {
+    let mut step = StreamedStep::default();
+
+    loop {
+        let item = tokio::select! {
+            item = ai_stream.next() => item,
+            _ = tx.closed() => {
+                step.disconnected = true;
+                break;
+            }
+        };
+
+        let chunk = match item {
+            Some(Ok(chunk)) => chunk,
+            Some(Err(e)) => {
+                tracing::error!("AI provider stream error: {}", e);
+                step.error = Some(e);
+                break;
+            }
+            None => break,
+        };
+
+        let mut events = Vec::new();
+        if let Some(delta) = &chunk.reasoning {
+            step.reasoning.push_str(delta);
+            events.push(sse_event(
+                "reasoning",
+                serde_json::json!({ "content": delta }),
+            ));
+        }
+        if !chunk.content.is_empty() {
+            step.content.push_str(&chunk.content);
+            events.push(sse_event(
+                "delta",
+                serde_json::json!({ "content": chunk.content }),
+            ));
+        }
+        for call in &chunk.tool_calls {
+            step.tool_calls.push(call.clone());
+            let mut payload = serde_json::json!(call);
+            payload["runs_on_server"] = server_tools
+                .is_some_and(|registry| registry.contains(&call.name))
+                .into();
+            events.push(sse_event("tool_call", payload));
+        }
+
+        for event in events {
+            if tx.send(event).await.is_err() {
+                step.disconnected = true;
+                return step;
+            }
+        }
+
+        if chunk.done {
+            step.final_chunk = Some(chunk);
+            break;
+        }
+    }
+
+    step
+}
+
+fn sse_event(name: &str, data: serde_json::Value) -> Event {
+    Event::default().event(name).data(data.to_string())
+}
+
+/// Metadata stored with an assistant reply: the provider and model that answered (and
+/// the one originally asked when a fallback answered instead), and a flag on replies
+/// cut short by a disconnect or an error.
+fn message_metadata(
+    primary: &ModelTarget,
+    answered_by: &ModelTarget,
+    interrupted: bool,
+) -> serde_json::Value {
+    let mut metadata = serde_json::Map::new();
+    metadata.insert("provider".to_string(), answered_by.provider.as_str().into());
+    metadata.insert("model_id".to_string(), answered_by.model_id.clone().into());
+    if answered_by != primary {
+        metadata.insert("fallback_from".to_string(), primary.to_string().into());
+    }
+    if interrupted {
+        metadata.insert("interrupted".to_string(), true.into());
+    }
+
+    metadata.into()
+}
+
+/// The row for `message`: its text, or its tool result, goes in `content` and the
+/// other parts in `content_parts`.
+pub fn new_message(
+    chat_id: uuid::Uuid,
+    sequence_number: i32,
+    parent_message_id: Option<uuid::Uuid>,
+    message: &ChatMessage,
+    metadata: Option<serde_json::Value>,
+) -> CreateMessageDto {
+    let tool_result = message.content.iter().find_map(|part| match part {
+        ContentPart::ToolResult {
+            tool_call_id,
+            content,
+            ..
+        } => Some((tool_call_id.clone(), content.clone())),
+        _ => None,
+    });
+    let (tool_call_id, content) = match tool_result {
+        Some((id, content)) => (Some(id), content),
+        None => (None, message.text()),
+    };
+
+    CreateMessageDto {
+        chat_id,
+        role: message.role,
+        content,
+        metadata,
+        parent_message_id,
+        sequence_number,
+        tool_call_id,
+        content_parts: ContentPart::to_stored(&message.content),
+        model_used: None,
+    }
+}
+
+/// Persists the text, reasoning and tool calls collected from a stream as an assistant
+/// reply of `model`.
+async fn save_assistant_message(
+    chat_repository: &impl TChatRepository,
+    chat_id: uuid::Uuid,
+    parent_message_id: Option<uuid::Uuid>,
+    step: &StreamedStep,
+    metadata: serde_json::Value,
+    model: &str,
+    usage: Option<MessageUsageDto>,
+) -> emixdiesel::Result<uuid::Uuid> {
+    let sequence_number = chat_repository.get_next_sequence_number(chat_id).await?;
+
+    let reply = ChatMessage::assistant(
+        step.content.clone(),
+        Some(step.reasoning.clone()),
+        step.tool_calls.clone(),
+    );
+    let message = chat_repository
+        .create_message(CreateMessageDto {
+            model_used: Some(model.to_string()),
+            ..new_message(
+                chat_id,
+                sequence_number,
+                parent_message_id,
+                &reply,
+                Some(metadata),
+            )
+        })
+        .await?;
+
+    if let Some(usage) = usage {
+        chat_repository.update_usage(message.id, usage).await?;
+    }
+
+    Ok(message.id)
+}
+
+/// What `usage` cost at the catalog prices of the model that answered.
+fn answer_cost(
+    catalog: &[AiModelModel],
+    answered_by: &ModelTarget,
+    usage: &TokenUsage,
+) -> Option<rust_decimal::Decimal> {
+    catalog
+        .iter()
+        .find(|m| m.provider == answered_by.provider && m.model_id == answered_by.model_id)?
+        .cost(
+            usage.prompt_tokens,
+            usage.cached_tokens.unwrap_or(0),
+            usage.completion_tokens,
+        )
+}
+
+/// The usage saved on an assistant message; `model` is the model the provider reports,
+/// which may name a more specific version than `answered_by`.
+fn message_usage(
+    catalog: &[AiModelModel],
+    answered_by: &ModelTarget,
+    model: &str,
+    usage: &TokenUsage,
+) -> MessageUsageDto {
+    MessageUsageDto {
+        model: model.to_string(),
+        prompt_tokens: usage.prompt_tokens as i32,
+        completion_tokens: usage.completion_tokens as i32,
+        cached_tokens: usage.cached_tokens.map(|t| t as i32),
+        reasoning_tokens: usage.reasoning_tokens.map(|t| t as i32),
+        cost: answer_cost(catalog, answered_by, usage),
+    }
 }
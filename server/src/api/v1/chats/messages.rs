@@ -1,6 +1,7 @@
 use super::MessageResponse;
 use crate::{
     AppState,
+    api::common::{ApiError, ErrorResponse},
     db::prelude::*,
     db::repositories::TChatRepository,
     middleware::auth::AuthenticatedUser,
@@ -17,7 +18,10 @@ use uuid::Uuid;
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateMessageRequest {
     pub content: String,
+    /// `user` (default), `assistant`, `system` or `tool`
     pub role: Option<String>,
+    /// Required for `tool` messages: the id of the tool call this result answers
+    pub tool_call_id: Option<String>,
 }
 
 /// Get all messages for a chat
@@ -64,6 +68,7 @@ pub async fn get_messages(
     request_body = CreateMessageRequest,
     responses(
         (status = 200, description = "Message created", body = super::MessageResponse),
+        (status = 400, description = "Tool message without a tool_call_id", body = ErrorResponse),
         (status = 401, description = "Unauthorized"),
         (status = 404, description = "Chat not found"),
         (status = 500, description = "Internal server error")
@@ -74,7 +79,7 @@ pub async fn create_message(
     state: State<AppState>,
     Path(chat_id): Path<Uuid>,
     Json(payload): Json<CreateMessageRequest>,
-) -> Result<Json<MessageResponse>, StatusCode> {
+) -> Result<Json<MessageResponse>, ApiError> {
     // Verify chat belongs to user
     let _chat = state
         .chat_repository
@@ -86,9 +91,18 @@ pub async fn create_message(
     let role = match payload.role.as_deref() {
         Some("system") => MessageRole::System,
         Some("assistant") => MessageRole::Assistant,
+        Some("tool") => MessageRole::Tool,
         _ => MessageRole::User,
     };
 
+    if role == MessageRole::Tool && payload.tool_call_id.is_none() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "missing_tool_call_id",
+            "Tool messages must name the tool call they answer",
+        ));
+    }
+
     let sequence_number = state
         .chat_repository
         .get_next_sequence_number(chat_id)
@@ -104,6 +118,11 @@ pub async fn create_message(
             metadata: None,
             parent_message_id: None,
             sequence_number,
+            tool_call_id: (role == MessageRole::Tool)
+                .then_some(payload.tool_call_id)
+                .flatten(),
+            content_parts: None,
+            model_used: None,
         })
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -115,6 +134,8 @@ pub async fn create_message(
 pub struct UpdateMessageRequest {
     pub content: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    /// Pinned messages stay in the model's context when older ones are left out
+    pub is_pinned: Option<bool>,
 }
 
 /// Update a message in a chat
@@ -150,6 +171,7 @@ pub async fn update_message(
             UpdateMessageDto {
                 content: payload.content,
                 metadata: payload.metadata,
+                is_pinned: payload.is_pinned,
             },
         )
         .await
@@ -2,9 +2,12 @@ pub mod messages;
 
 use crate::{
     AppState,
+    ai::types::{ContentPart, ToolCall},
+    api::common::{ApiError, ErrorResponse},
+    api::v1::attachments,
     db::dto::Pagination,
     db::prelude::*,
-    db::repositories::TChatRepository,
+    db::repositories::{TAiModelRepository, TAttachmentRepository, TChatRepository},
     middleware::auth::AuthenticatedUser,
 };
 use axum::{
@@ -23,6 +26,12 @@ pub struct ChatResponse {
     pub title: String,
     pub model_provider: String,
     pub model_id: String,
+    /// Models tried when this chat's model fails; `null` means the global chain applies
+    pub fallback_models: Option<Vec<String>>,
+    /// Summary sent to the model in place of the messages up to `summarized_through`
+    pub summary: Option<String>,
+    /// Sequence number of the last message `summary` covers
+    pub summarized_through: Option<i32>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -39,8 +48,14 @@ impl From<ChatModel> for ChatResponse {
                 AiProvider::Google => "google".to_string(),
                 AiProvider::DeepSeek => "deepseek".to_string(),
                 AiProvider::Ollama => "ollama".to_string(),
+                AiProvider::OpenAICompatible => "openai_compatible".to_string(),
             },
             model_id: chat.model_id,
+            fallback_models: chat
+                .fallback_models
+                .and_then(|models| serde_json::from_value(models).ok()),
+            summary: chat.summary,
+            summarized_through: chat.summarized_through,
             created_at: chat.created_at.to_rfc3339(),
             updated_at: chat.updated_at.to_rfc3339(),
         }
@@ -58,11 +73,36 @@ pub struct MessageResponse {
     pub sequence_number: i32,
     pub created_at: String,
     pub tokens_used: Option<i32>,
+    pub prompt_tokens: Option<i32>,
+    pub completion_tokens: Option<i32>,
+    /// Part of `prompt_tokens`, read from the provider's prompt cache
+    pub cached_tokens: Option<i32>,
+    /// Part of `completion_tokens`, spent reasoning
+    pub reasoning_tokens: Option<i32>,
+    /// What the answer cost at the model's catalog prices
+    pub cost: Option<rust_decimal::Decimal>,
     pub model_used: Option<String>,
+    /// Calls requested by an assistant message
+    pub tool_calls: Option<Vec<ToolCall>>,
+    /// On a `tool` message, the id of the call it answers
+    pub tool_call_id: Option<String>,
+    /// The full content; `content` is its text
+    pub content_parts: Vec<ContentPart>,
+    /// Always sent to the model, however long the chat grows
+    pub is_pinned: bool,
 }
 
 impl From<MessageModel> for MessageResponse {
     fn from(message: MessageModel) -> Self {
+        let content_parts = ContentPart::from_message(&message);
+        let tool_calls: Vec<ToolCall> = content_parts
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::ToolCall(call) => Some(call.clone()),
+                _ => None,
+            })
+            .collect();
+
         Self {
             id: message.id,
             chat_id: message.chat_id,
@@ -70,6 +110,7 @@ impl From<MessageModel> for MessageResponse {
                 MessageRole::User => "user".to_string(),
                 MessageRole::Assistant => "assistant".to_string(),
                 MessageRole::System => "system".to_string(),
+                MessageRole::Tool => "tool".to_string(),
             },
             content: message.content,
             metadata: message.metadata,
@@ -77,7 +118,16 @@ impl From<MessageModel> for MessageResponse {
             sequence_number: message.sequence_number,
             created_at: message.created_at.to_rfc3339(),
             tokens_used: message.tokens_used,
+            prompt_tokens: message.prompt_tokens,
+            completion_tokens: message.completion_tokens,
+            cached_tokens: message.cached_tokens,
+            reasoning_tokens: message.reasoning_tokens,
+            cost: message.cost,
             model_used: message.model_used,
+            tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+            tool_call_id: message.tool_call_id,
+            content_parts,
+            is_pinned: message.is_pinned,
         }
     }
 }
@@ -105,13 +155,18 @@ pub struct ChatListResponse {
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateChatRequest {
     pub title: Option<String>,
-    pub model_provider: String,
-    pub model_id: String,
+    /// Leave out together with `model_id` to use the user's default model
+    pub model_provider: Option<String>,
+    pub model_id: Option<String>,
+    /// Fallback chain entries, `provider:model_id` or a catalog model id
+    pub fallback_models: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateChatRequest {
     pub title: Option<String>,
+    /// Replaces the chat's fallback chain; an empty list disables fallbacks
+    pub fallback_models: Option<Vec<String>>,
 }
 
 /// List all chats for the authenticated user
@@ -158,7 +213,7 @@ pub async fn list_chats(
     request_body = CreateChatRequest,
     responses(
         (status = 200, description = "Chat created", body = ChatResponse),
-        (status = 400, description = "Invalid provider or payload"),
+        (status = 400, description = "Invalid provider or payload, or no model given and no default model set", body = ErrorResponse),
         (status = 401, description = "Unauthorized"),
         (status = 500, description = "Internal server error")
     )
@@ -167,14 +222,34 @@ pub async fn create_chat(
     user: AuthenticatedUser,
     state: State<AppState>,
     Json(payload): Json<CreateChatRequest>,
-) -> Result<Json<ChatResponse>, StatusCode> {
-    let provider = match payload.model_provider.as_str() {
-        "openai" => AiProvider::OpenAI,
-        "anthropic" => AiProvider::Anthropic,
-        "google" => AiProvider::Google,
-        "deepseek" => AiProvider::DeepSeek,
-        "ollama" => AiProvider::Ollama,
-        _ => return Err(StatusCode::BAD_REQUEST),
+) -> Result<Json<ChatResponse>, ApiError> {
+    let (provider, model_id) = match (payload.model_provider, payload.model_id) {
+        (Some(model_provider), Some(model_id)) => {
+            let provider = AiProvider::from_str(&model_provider).ok_or_else(|| {
+                ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_provider",
+                    format!("Unknown provider '{}'", model_provider),
+                )
+            })?;
+            (provider, model_id)
+        }
+        (None, None) => {
+            let model = state
+                .ai_model_repository
+                .get_default_for_user(&user.0.id)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .ok_or_else(|| {
+                    ApiError::new(
+                        StatusCode::BAD_REQUEST,
+                        "no_default_model",
+                        "Choose a model or set a default model first",
+                    )
+                })?;
+            (model.provider, model.model_id)
+        }
+        _ => return Err(StatusCode::BAD_REQUEST.into()),
     };
 
     let title = payload.title.unwrap_or_else(|| "New Chat".to_string());
@@ -185,7 +260,8 @@ pub async fn create_chat(
             user_id: user.0.id,
             title,
             model_provider: provider,
-            model_id: payload.model_id,
+            model_id,
+            fallback_models: payload.fallback_models.map(|models| serde_json::json!(models)),
         })
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -265,6 +341,9 @@ pub async fn update_chat(
                 title: payload.title,
                 model_provider: None,
                 model_id: None,
+                fallback_models: payload
+                    .fallback_models
+                    .map(|models| Some(serde_json::json!(models))),
             },
         )
         .await
@@ -300,5 +379,13 @@ pub async fn delete_chat(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    // The chat row is only soft-deleted, so its uploads are removed here
+    let removed = state
+        .attachment_repository
+        .delete_for_chat(id, &user.0.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    attachments::delete_stored(&state, &removed).await;
+
     Ok(StatusCode::NO_CONTENT)
 }
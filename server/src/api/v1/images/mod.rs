@@ -0,0 +1,244 @@
+use crate::{
+    AppState,
+    ai::fallback::ModelTarget,
+    ai::manager::ProviderManager,
+    ai::types::{ChatMessage, ContentPart, ImageGenerationRequest as AIImageRequest},
+    api::common::{ApiError, ErrorResponse},
+    api::v1::{
+        attachments::{self, AttachmentResponse},
+        chat::{new_message, provider_config},
+    },
+    db::prelude::*,
+    db::repositories::{TAttachmentRepository, TChatRepository},
+    middleware::auth::AuthenticatedUser,
+};
+use axum::{extract::State, http::StatusCode, response::Json};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Most images one request may ask for.
+const MAX_IMAGES: u32 = 4;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ImageGenerationRequest {
+    /// Chat the prompt and the images are added to
+    pub chat_id: Uuid,
+    pub prompt: String,
+    /// `openai` (DALL·E, gpt-image) or `google` (Imagen)
+    pub model_provider: String,
+    /// Defaults to `gpt-image-1` for OpenAI and `imagen-3.0-generate-002` for Google
+    pub model_id: Option<String>,
+    /// `WIDTHxHEIGHT`, e.g. `1024x1024`; Imagen draws the nearest aspect ratio and also
+    /// takes one directly, e.g. `16:9`
+    pub size: Option<String>,
+    /// Number of images, 1 to 4; defaults to 1
+    pub n: Option<u32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImageGenerationResponse {
+    /// The prompt, saved as a user message
+    pub user_message_id: Uuid,
+    /// The assistant message carrying the images
+    pub message_id: Uuid,
+    pub provider: String,
+    pub model: String,
+    /// The prompt the provider actually drew, when it rewrote the one it was given
+    pub revised_prompt: Option<String>,
+    pub images: Vec<AttachmentResponse>,
+}
+
+/// Generate images from a prompt and add them to a chat
+#[utoipa::path(
+    post,
+    path = "/api/v1/images/generations",
+    tag = "Images",
+    security(("bearer_auth" = [])),
+    request_body = ImageGenerationRequest,
+    responses(
+        (status = 200, description = "Images generated and saved to the chat", body = ImageGenerationResponse),
+        (status = 400, description = "Invalid prompt or count, missing or rejected API key, or a provider without image generation", body = ErrorResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Chat or model not found", body = ErrorResponse),
+        (status = 422, description = "Blocked by the provider's content filter", body = ErrorResponse),
+        (status = 429, description = "Rate limited by the provider", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+        (status = 502, description = "Provider failed", body = ErrorResponse),
+        (status = 504, description = "Provider timed out", body = ErrorResponse)
+    )
+)]
+pub async fn generate_images(
+    user: AuthenticatedUser,
+    state: State<AppState>,
+    Json(payload): Json<ImageGenerationRequest>,
+) -> Result<Json<ImageGenerationResponse>, ApiError> {
+    let prompt = payload.prompt.trim();
+    if prompt.is_empty() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_prompt",
+            "The prompt is empty",
+        ));
+    }
+    let n = payload.n.unwrap_or(1);
+    if !(1..=MAX_IMAGES).contains(&n) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_image_count",
+            format!("n must be between 1 and {}", MAX_IMAGES),
+        ));
+    }
+
+    // Verify chat belongs to user
+    state
+        .chat_repository
+        .get(payload.chat_id, &user.0.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let provider = AiProvider::from_str(&payload.model_provider).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_provider",
+            format!("Unknown provider '{}'", payload.model_provider),
+        )
+    })?;
+    let target = ModelTarget::new(provider, payload.model_id.clone().unwrap_or_default());
+    let Some(config) = provider_config(&state, &user.0.id, &target).await? else {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "missing_api_key",
+            format!(
+                "No {} API key is configured; add one to use this model",
+                provider.display_name()
+            ),
+        ));
+    };
+
+    let mut manager = ProviderManager::default();
+    manager.insert(provider, config).map_err(|e| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "provider_not_configured",
+            e.to_string(),
+        )
+    })?;
+    let Some(wrapper) = manager.get_provider(&provider) else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+    };
+    let Some(image_provider) = wrapper.image_provider() else {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "image_generation_not_supported",
+            format!("{} does not generate images", provider.display_name()),
+        ));
+    };
+    let model = payload
+        .model_id
+        .clone()
+        .unwrap_or_else(|| image_provider.default_image_model().to_string());
+
+    let generated = wrapper
+        .generate_images(AIImageRequest {
+            model: model.clone(),
+            prompt: prompt.to_string(),
+            size: payload.size.clone(),
+            n,
+        })
+        .await
+        .map_err(|e| ApiError::provider(provider, &e))?;
+
+    // The prompt goes in as a user turn so the chat reads as a conversation
+    let sequence_number = state
+        .chat_repository
+        .get_next_sequence_number(payload.chat_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let user_message = state
+        .chat_repository
+        .create_message(new_message(
+            payload.chat_id,
+            sequence_number,
+            None,
+            &ChatMessage::new(MessageRole::User, prompt.to_string()),
+            None,
+        ))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut images = Vec::with_capacity(generated.images.len());
+    for (index, image) in generated.images.into_iter().enumerate() {
+        let extension = image.mime_type.trim_start_matches("image/");
+        let file_name = format!("image-{}.{}", index + 1, extension);
+        let attachment = attachments::store(
+            &state,
+            &user.0.id,
+            Some(payload.chat_id),
+            file_name,
+            image.mime_type,
+            image.data,
+            None,
+        )
+        .await?;
+        images.push((attachment, image.revised_prompt));
+    }
+    let revised_prompt = images
+        .iter()
+        .find_map(|(_, revised_prompt)| revised_prompt.clone());
+
+    let sequence_number = state
+        .chat_repository
+        .get_next_sequence_number(payload.chat_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let assistant_message = ChatMessage {
+        role: MessageRole::Assistant,
+        content: images
+            .iter()
+            .map(|(attachment, _)| attachments::content_part(attachment))
+            .collect::<Vec<ContentPart>>(),
+    };
+    let metadata = serde_json::json!({
+        "type": "image_generation",
+        "prompt": prompt,
+        "size": payload.size,
+        "provider": provider.as_str(),
+        "model": generated.model,
+        "revised_prompt": revised_prompt,
+    });
+    let assistant_message = state
+        .chat_repository
+        .create_message(new_message(
+            payload.chat_id,
+            sequence_number,
+            Some(user_message.id),
+            &assistant_message,
+            Some(metadata),
+        ))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let ids: Vec<Uuid> = images.iter().map(|(attachment, _)| attachment.id).collect();
+    state
+        .attachment_repository
+        .link_to_message(&ids, &user.0.id, payload.chat_id, assistant_message.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(ImageGenerationResponse {
+        user_message_id: user_message.id,
+        message_id: assistant_message.id,
+        provider: provider.as_str().to_string(),
+        model: generated.model,
+        revised_prompt,
+        images: images
+            .into_iter()
+            .map(|(mut attachment, _)| {
+                attachment.message_id = Some(assistant_message.id);
+                AttachmentResponse::from(attachment)
+            })
+            .collect(),
+    }))
+}
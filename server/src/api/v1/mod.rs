@@ -1,8 +1,11 @@
 // API v1 module - all v1 endpoints organized by resource hierarchy
+pub mod admin;
+pub mod attachments;
 pub mod chat;
 pub mod chats;
 pub mod features;
 pub mod health;
+pub mod images;
 pub mod models;
 pub mod user;
 pub mod user_api_keys;
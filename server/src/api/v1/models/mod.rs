@@ -1,5 +1,5 @@
 use crate::{
-    AppState, db::prelude::*, db::repositories::TAiModelRepository,
+    AppState, api::common::double_option, db::prelude::*, db::repositories::TAiModelRepository,
     middleware::auth::AuthenticatedUser,
 };
 use axum::{
@@ -7,7 +7,7 @@ use axum::{
     http::StatusCode,
     response::Json,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
@@ -23,6 +23,14 @@ pub struct ModelResponse {
     pub supports_images: bool,
     pub supports_functions: bool,
     pub cost_per_token: Option<rust_decimal::Decimal>,
+    /// USD per prompt token
+    pub input_cost_per_token: Option<rust_decimal::Decimal>,
+    /// USD per completion token
+    pub output_cost_per_token: Option<rust_decimal::Decimal>,
+    /// USD per prompt token read from the provider's cache
+    pub cached_input_cost_per_token: Option<rust_decimal::Decimal>,
+    /// Parameters used when a chat request leaves them unset
+    pub default_parameters: Option<serde_json::Value>,
     pub is_active: bool,
     pub created_at: String,
     pub updated_at: String,
@@ -38,6 +46,7 @@ impl From<AiModelModel> for ModelResponse {
                 AiProvider::Google => "google".to_string(),
                 AiProvider::DeepSeek => "deepseek".to_string(),
                 AiProvider::Ollama => "ollama".to_string(),
+                AiProvider::OpenAICompatible => "openai_compatible".to_string(),
             },
             model_id: model.model_id,
             display_name: model.display_name,
@@ -47,6 +56,10 @@ impl From<AiModelModel> for ModelResponse {
             supports_images: model.supports_images,
             supports_functions: model.supports_functions,
             cost_per_token: model.cost_per_token,
+            input_cost_per_token: model.input_cost_per_token,
+            output_cost_per_token: model.output_cost_per_token,
+            cached_input_cost_per_token: model.cached_input_cost_per_token,
+            default_parameters: model.default_parameters,
             is_active: model.is_active,
             created_at: model.created_at.to_rfc3339(),
             updated_at: model.updated_at.to_rfc3339(),
@@ -54,6 +67,38 @@ impl From<AiModelModel> for ModelResponse {
     }
 }
 
+/// A catalog model with the authenticated user's preferences for it
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserModelResponse {
+    #[serde(flatten)]
+    pub model: ModelResponse,
+    pub is_favorite: bool,
+    /// Position chosen by the user; `null` sorts after positioned models, by name
+    pub sort_order: Option<i32>,
+    /// Used for chats created without a model
+    pub is_default: bool,
+}
+
+impl From<(AiModelModel, Option<UserAiModelModel>)> for UserModelResponse {
+    fn from((model, preferences): (AiModelModel, Option<UserAiModelModel>)) -> Self {
+        Self {
+            model: ModelResponse::from(model),
+            is_favorite: preferences.as_ref().is_some_and(|p| p.is_favorite),
+            sort_order: preferences.as_ref().and_then(|p| p.sort_order),
+            is_default: preferences.is_some_and(|p| p.is_default),
+        }
+    }
+}
+
+/// Fields left out are unchanged; `sort_order: null` clears the position.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateModelPreferencesRequest {
+    pub is_favorite: Option<bool>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<i32>)]
+    pub sort_order: Option<Option<i32>>,
+}
+
 /// List all active AI models
 #[utoipa::path(
     get,
@@ -124,14 +169,15 @@ pub async fn get_model(
     Ok(Json(ModelResponse::from(model)))
 }
 
-/// List models enabled for the authenticated user
+/// List models enabled for the authenticated user, favorites first, then by the
+/// user's sort order and name
 #[utoipa::path(
     get,
     path = "/api/v1/models/my",
     tag = "Models",
     security(("bearer_auth" = [])),
     responses(
-        (status = 200, description = "List of user's enabled models", body = [ModelResponse]),
+        (status = 200, description = "List of user's enabled models", body = [UserModelResponse]),
         (status = 401, description = "Unauthorized"),
         (status = 500, description = "Internal server error")
     )
@@ -139,14 +185,16 @@ pub async fn get_model(
 pub async fn list_my_models(
     user: AuthenticatedUser,
     state: State<AppState>,
-) -> Result<Json<Vec<ModelResponse>>, StatusCode> {
+) -> Result<Json<Vec<UserModelResponse>>, StatusCode> {
     let models = state
         .ai_model_repository
         .list_for_user(&user.0.id)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    Ok(Json(models.into_iter().map(ModelResponse::from).collect()))
+    Ok(Json(
+        models.into_iter().map(UserModelResponse::from).collect(),
+    ))
 }
 
 /// Enable a model for the authenticated user
@@ -224,3 +272,114 @@ pub async fn disable_model(
 
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// Update the authenticated user's favorite flag and sort order for a model
+#[utoipa::path(
+    put,
+    path = "/api/v1/models/{id}/preferences",
+    tag = "Models",
+    security(("bearer_auth" = [])),
+    params(
+        ("id" = Uuid, Path, description = "Model identifier")
+    ),
+    request_body = UpdateModelPreferencesRequest,
+    responses(
+        (status = 204, description = "Preferences updated"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Model not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn update_model_preferences(
+    user: AuthenticatedUser,
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateModelPreferencesRequest>,
+) -> Result<StatusCode, StatusCode> {
+    // Verify model exists
+    let _model = state
+        .ai_model_repository
+        .get(id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    state
+        .ai_model_repository
+        .update_preferences_for_user(
+            &user.0.id,
+            id,
+            UpdateUserAiModelDto {
+                is_favorite: payload.is_favorite,
+                sort_order: payload.sort_order,
+            },
+        )
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Make a model the authenticated user's default, enabling it if needed
+#[utoipa::path(
+    post,
+    path = "/api/v1/models/{id}/default",
+    tag = "Models",
+    security(("bearer_auth" = [])),
+    params(
+        ("id" = Uuid, Path, description = "Model identifier")
+    ),
+    responses(
+        (status = 204, description = "Default model set"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Model not found or inactive"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn set_default_model(
+    user: AuthenticatedUser,
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    // Verify model exists and can be used
+    let _model = state
+        .ai_model_repository
+        .get(id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .filter(|m| m.is_active)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    state
+        .ai_model_repository
+        .set_default_for_user(&user.0.id, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Clear the authenticated user's default model
+#[utoipa::path(
+    delete,
+    path = "/api/v1/models/default",
+    tag = "Models",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Default model cleared"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn clear_default_model(
+    user: AuthenticatedUser,
+    state: State<AppState>,
+) -> Result<StatusCode, StatusCode> {
+    state
+        .ai_model_repository
+        .clear_default_for_user(&user.0.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,9 +1,15 @@
 use crate::{
-    AppState, db::prelude::*, db::repositories::TUserApiKeyRepository,
+    AppState,
+    ai::endpoint,
+    ai::manager::{ProviderConfig, ProviderManager},
+    api::common::{ApiError, ErrorResponse},
+    db::prelude::*,
+    db::repositories::TUserApiKeyRepository,
     middleware::auth::AuthenticatedUser,
 };
 use axum::{extract::Path, extract::State, http::StatusCode, response::Json};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
@@ -13,6 +19,10 @@ pub struct UserApiKeyResponse {
     pub user_id: String,
     pub provider: String,
     pub is_default: bool,
+    pub base_url: Option<String>,
+    pub extra_headers: Option<serde_json::Value>,
+    /// Models served through this key
+    pub models: Option<Vec<String>>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -28,8 +38,14 @@ impl From<UserApiKeyModel> for UserApiKeyResponse {
                 AiProvider::Google => "google".to_string(),
                 AiProvider::DeepSeek => "deepseek".to_string(),
                 AiProvider::Ollama => "ollama".to_string(),
+                AiProvider::OpenAICompatible => "openai_compatible".to_string(),
             },
             is_default: key.is_default,
+            base_url: key.base_url,
+            extra_headers: key.extra_headers,
+            models: key
+                .models
+                .and_then(|models| serde_json::from_value(models).ok()),
             created_at: key.created_at.to_rfc3339(),
             updated_at: key.updated_at.to_rfc3339(),
         }
@@ -41,6 +57,36 @@ pub struct CreateUserApiKeyRequest {
     pub provider: String,
     pub api_key: String,
     pub is_default: Option<bool>,
+    /// Endpoint for self-hosted or OpenAI-compatible providers
+    pub base_url: Option<String>,
+    /// Headers sent with every request to an OpenAI-compatible endpoint
+    pub extra_headers: Option<HashMap<String, String>>,
+    /// Models served through this key; `POST /api/v1/user-api-keys/{id}/models/sync`
+    /// discovers them from the endpoint instead
+    pub models: Option<Vec<String>>,
+}
+
+/// Rejects endpoint settings that would point the server at its own network. Hosts on
+/// a private network are only reachable when an admin allows them.
+pub async fn check_endpoint(
+    base_url: Option<&str>,
+    extra_headers: &HashMap<String, String>,
+) -> Result<(), ApiError> {
+    if let Some(base_url) = base_url {
+        endpoint::check_base_url(base_url).await.map_err(|e| {
+            ApiError::new(StatusCode::BAD_REQUEST, "invalid_base_url", e.to_string())
+        })?;
+    }
+    endpoint::check_extra_headers(extra_headers)
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, "invalid_headers", e.to_string()))
+}
+
+/// `key`'s extra headers; malformed JSON counts as none.
+pub fn extra_headers(key: &UserApiKeyModel) -> HashMap<String, String> {
+    key.extra_headers
+        .clone()
+        .and_then(|headers| serde_json::from_value(headers).ok())
+        .unwrap_or_default()
 }
 
 /// List all API keys for the authenticated user
@@ -79,24 +125,36 @@ pub async fn list_keys(
     request_body = CreateUserApiKeyRequest,
     responses(
         (status = 200, description = "API key created", body = UserApiKeyResponse),
-        (status = 400, description = "Invalid provider"),
+        (status = 400, description = "Invalid provider, missing or disallowed base URL, or invalid headers", body = ErrorResponse),
         (status = 401, description = "Unauthorized"),
-        (status = 500, description = "Internal server error")
+        (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
 pub async fn create_key(
     user: AuthenticatedUser,
     state: State<AppState>,
     Json(payload): Json<CreateUserApiKeyRequest>,
-) -> Result<Json<UserApiKeyResponse>, StatusCode> {
-    let provider = match payload.provider.as_str() {
-        "openai" => AiProvider::OpenAI,
-        "anthropic" => AiProvider::Anthropic,
-        "google" => AiProvider::Google,
-        "deepseek" => AiProvider::DeepSeek,
-        "ollama" => AiProvider::Ollama,
-        _ => return Err(StatusCode::BAD_REQUEST),
-    };
+) -> Result<Json<UserApiKeyResponse>, ApiError> {
+    let provider = AiProvider::from_str(&payload.provider).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_provider",
+            format!("Unknown provider '{}'", payload.provider),
+        )
+    })?;
+
+    if provider == AiProvider::OpenAICompatible && payload.base_url.is_none() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "missing_base_url",
+            "OpenAI-compatible keys need a base URL",
+        ));
+    }
+    check_endpoint(
+        payload.base_url.as_deref(),
+        payload.extra_headers.as_ref().unwrap_or(&HashMap::new()),
+    )
+    .await?;
 
     // TODO: Encrypt API key
     // For now, store as plaintext (not secure!)
@@ -109,6 +167,11 @@ pub async fn create_key(
             provider,
             encrypted_key,
             is_default: payload.is_default.unwrap_or(false),
+            base_url: payload.base_url,
+            extra_headers: payload
+                .extra_headers
+                .map(|headers| serde_json::json!(headers)),
+            models: payload.models.map(|models| serde_json::json!(models)),
         })
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -125,6 +188,88 @@ pub async fn create_key(
     Ok(Json(UserApiKeyResponse::from(key)))
 }
 
+/// Discover the models served through an API key and store them on the key
+#[utoipa::path(
+    post,
+    path = "/api/v1/user-api-keys/{id}/models/sync",
+    tag = "User API Keys",
+    security(("bearer_auth" = [])),
+    params(
+        ("id" = Uuid, Path, description = "API key identifier")
+    ),
+    responses(
+        (status = 200, description = "Key with its discovered models", body = UserApiKeyResponse),
+        (status = 400, description = "Disallowed base URL or invalid headers, or the key was rejected", body = ErrorResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "API key not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+        (status = 502, description = "Provider failed", body = ErrorResponse)
+    )
+)]
+pub async fn sync_key_models(
+    user: AuthenticatedUser,
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<UserApiKeyResponse>, ApiError> {
+    let key = state
+        .user_api_key_repository
+        .list(&user.0.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .find(|k| k.id == id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    // Checked again: the host may resolve elsewhere now, or the key predates the checks
+    let extra_headers = extra_headers(&key);
+    check_endpoint(key.base_url.as_deref(), &extra_headers).await?;
+
+    let mut manager = ProviderManager::default();
+    manager
+        .insert(
+            key.provider,
+            ProviderConfig {
+                api_key: Some(key.encrypted_key.clone()),
+                base_url: key.base_url.clone(),
+                extra_headers,
+            },
+        )
+        .map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "provider_not_configured",
+                e.to_string(),
+            )
+        })?;
+    let Some(provider) = manager.get_provider(&key.provider) else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+    };
+    let model_ids: Vec<String> = provider
+        .discover_models()
+        .await
+        .map_err(|e| ApiError::provider(key.provider, &e))?
+        .into_iter()
+        .map(|model| model.id)
+        .collect();
+
+    let key = state
+        .user_api_key_repository
+        .update(
+            key.id,
+            UpdateUserApiKeyDto {
+                encrypted_key: None,
+                is_default: None,
+                base_url: None,
+                extra_headers: None,
+                models: Some(Some(serde_json::json!(model_ids))),
+            },
+        )
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(UserApiKeyResponse::from(key)))
+}
+
 /// Delete an API key
 #[utoipa::path(
     delete,
@@ -17,6 +17,7 @@ pub enum AiProvider {
     Google,
     DeepSeek,
     Ollama,
+    OpenAICompatible,
 }
 
 impl AiProvider {
@@ -27,9 +28,27 @@ impl AiProvider {
             AiProvider::Google => "google",
             AiProvider::DeepSeek => "deepseek",
             AiProvider::Ollama => "ollama",
+            AiProvider::OpenAICompatible => "openai_compatible",
         }
     }
 
+    /// Name shown to users in error messages.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            AiProvider::OpenAI => "OpenAI",
+            AiProvider::Anthropic => "Anthropic",
+            AiProvider::Google => "Google Gemini",
+            AiProvider::DeepSeek => "DeepSeek",
+            AiProvider::Ollama => "Ollama",
+            AiProvider::OpenAICompatible => "OpenAI-compatible endpoint",
+        }
+    }
+
+    /// Self-hosted providers can be reached without a key.
+    pub fn requires_api_key(&self) -> bool {
+        !matches!(self, AiProvider::Ollama | AiProvider::OpenAICompatible)
+    }
+
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "openai" => Some(AiProvider::OpenAI),
@@ -37,6 +56,7 @@ impl AiProvider {
             "google" => Some(AiProvider::Google),
             "deepseek" => Some(AiProvider::DeepSeek),
             "ollama" => Some(AiProvider::Ollama),
+            "openai_compatible" => Some(AiProvider::OpenAICompatible),
             _ => None,
         }
     }
@@ -83,4 +103,183 @@ pub struct AiModelModel {
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub base_url: Option<String>,
+    pub extra_headers: Option<serde_json::Value>,
+    pub input_cost_per_token: Option<rust_decimal::Decimal>,
+    pub output_cost_per_token: Option<rust_decimal::Decimal>,
+    /// Request parameters applied when a chat request leaves them unset,
+    /// e.g. `{"temperature": 0.7, "max_tokens": 4096}`
+    pub default_parameters: Option<serde_json::Value>,
+    /// Price of a prompt token read from the provider's prompt cache
+    pub cached_input_cost_per_token: Option<rust_decimal::Decimal>,
+}
+
+impl AiModelModel {
+    pub fn default_temperature(&self) -> Option<f32> {
+        self.default_parameter("temperature")
+            .and_then(|v| v.as_f64())
+            .map(|v| v as f32)
+    }
+
+    pub fn default_max_tokens(&self) -> Option<u32> {
+        self.default_parameter("max_tokens")
+            .and_then(|v| v.as_u64())
+            .map(|v| v.min(u32::MAX as u64) as u32)
+    }
+
+    /// What `prompt_tokens` and `completion_tokens` cost at this model's input and
+    /// output prices, or at `cost_per_token` where one of them is unset. The
+    /// `cached_tokens` among `prompt_tokens` are charged at the cached input price when
+    /// there is one. `None` when the model is not priced.
+    pub fn cost(
+        &self,
+        prompt_tokens: u32,
+        cached_tokens: u32,
+        completion_tokens: u32,
+    ) -> Option<rust_decimal::Decimal> {
+        let input = self.input_cost_per_token.or(self.cost_per_token)?;
+        let cached_input = self.cached_input_cost_per_token.unwrap_or(input);
+        let output = self.output_cost_per_token.or(self.cost_per_token)?;
+        let cached_tokens = cached_tokens.min(prompt_tokens);
+        Some(
+            input * rust_decimal::Decimal::from(prompt_tokens - cached_tokens)
+                + cached_input * rust_decimal::Decimal::from(cached_tokens)
+                + output * rust_decimal::Decimal::from(completion_tokens),
+        )
+    }
+
+    fn default_parameter(&self, name: &str) -> Option<&serde_json::Value> {
+        self.default_parameters.as_ref()?.get(name)
+    }
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = ai_models)]
+pub struct NewAiModel {
+    pub id: Uuid,
+    pub provider: AiProvider,
+    pub model_id: String,
+    pub display_name: String,
+    pub description: Option<String>,
+    pub context_window: i32,
+    pub supports_streaming: bool,
+    pub supports_images: bool,
+    pub supports_functions: bool,
+    pub cost_per_token: Option<rust_decimal::Decimal>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub base_url: Option<String>,
+    pub extra_headers: Option<serde_json::Value>,
+    pub input_cost_per_token: Option<rust_decimal::Decimal>,
+    pub output_cost_per_token: Option<rust_decimal::Decimal>,
+    pub default_parameters: Option<serde_json::Value>,
+    pub cached_input_cost_per_token: Option<rust_decimal::Decimal>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateAiModelDto {
+    pub provider: AiProvider,
+    pub model_id: String,
+    pub display_name: String,
+    pub description: Option<String>,
+    pub context_window: i32,
+    pub supports_streaming: bool,
+    pub supports_images: bool,
+    pub supports_functions: bool,
+    pub cost_per_token: Option<rust_decimal::Decimal>,
+    pub is_active: bool,
+    pub base_url: Option<String>,
+    pub extra_headers: Option<serde_json::Value>,
+    pub input_cost_per_token: Option<rust_decimal::Decimal>,
+    pub output_cost_per_token: Option<rust_decimal::Decimal>,
+    pub default_parameters: Option<serde_json::Value>,
+    pub cached_input_cost_per_token: Option<rust_decimal::Decimal>,
+}
+
+impl From<CreateAiModelDto> for NewAiModel {
+    fn from(dto: CreateAiModelDto) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            provider: dto.provider,
+            model_id: dto.model_id,
+            display_name: dto.display_name,
+            description: dto.description,
+            context_window: dto.context_window,
+            supports_streaming: dto.supports_streaming,
+            supports_images: dto.supports_images,
+            supports_functions: dto.supports_functions,
+            cost_per_token: dto.cost_per_token,
+            is_active: dto.is_active,
+            created_at: now,
+            updated_at: now,
+            base_url: dto.base_url,
+            extra_headers: dto.extra_headers,
+            input_cost_per_token: dto.input_cost_per_token,
+            output_cost_per_token: dto.output_cost_per_token,
+            default_parameters: dto.default_parameters,
+            cached_input_cost_per_token: dto.cached_input_cost_per_token,
+        }
+    }
+}
+
+#[derive(Debug, Clone, AsChangeset)]
+#[diesel(table_name = ai_models)]
+pub struct UpdateAiModel {
+    pub display_name: Option<String>,
+    pub description: Option<Option<String>>,
+    pub context_window: Option<i32>,
+    pub supports_streaming: Option<bool>,
+    pub supports_images: Option<bool>,
+    pub supports_functions: Option<bool>,
+    pub cost_per_token: Option<Option<rust_decimal::Decimal>>,
+    pub is_active: Option<bool>,
+    pub updated_at: DateTime<Utc>,
+    pub base_url: Option<Option<String>>,
+    pub extra_headers: Option<Option<serde_json::Value>>,
+    pub input_cost_per_token: Option<Option<rust_decimal::Decimal>>,
+    pub output_cost_per_token: Option<Option<rust_decimal::Decimal>>,
+    pub default_parameters: Option<Option<serde_json::Value>>,
+    pub cached_input_cost_per_token: Option<Option<rust_decimal::Decimal>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpdateAiModelDto {
+    pub display_name: Option<String>,
+    pub description: Option<Option<String>>,
+    pub context_window: Option<i32>,
+    pub supports_streaming: Option<bool>,
+    pub supports_images: Option<bool>,
+    pub supports_functions: Option<bool>,
+    pub cost_per_token: Option<Option<rust_decimal::Decimal>>,
+    pub is_active: Option<bool>,
+    pub base_url: Option<Option<String>>,
+    pub extra_headers: Option<Option<serde_json::Value>>,
+    pub input_cost_per_token: Option<Option<rust_decimal::Decimal>>,
+    pub output_cost_per_token: Option<Option<rust_decimal::Decimal>>,
+    pub default_parameters: Option<Option<serde_json::Value>>,
+    pub cached_input_cost_per_token: Option<Option<rust_decimal::Decimal>>,
+}
+
+impl From<UpdateAiModelDto> for UpdateAiModel {
+    fn from(dto: UpdateAiModelDto) -> Self {
+        Self {
+            display_name: dto.display_name,
+            description: dto.description,
+            context_window: dto.context_window,
+            supports_streaming: dto.supports_streaming,
+            supports_images: dto.supports_images,
+            supports_functions: dto.supports_functions,
+            cost_per_token: dto.cost_per_token,
+            is_active: dto.is_active,
+            updated_at: Utc::now(),
+            base_url: dto.base_url,
+            extra_headers: dto.extra_headers,
+            input_cost_per_token: dto.input_cost_per_token,
+            output_cost_per_token: dto.output_cost_per_token,
+            default_parameters: dto.default_parameters,
+            cached_input_cost_per_token: dto.cached_input_cost_per_token,
+        }
+    }
 }
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::schema::attachments;
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, Queryable, Selectable, Identifiable, Serialize, Deserialize,
+)]
+#[diesel(table_name = attachments)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AttachmentModel {
+    pub id: Uuid,
+    pub user_id: String,
+    pub chat_id: Option<Uuid>,
+    pub message_id: Option<Uuid>,
+    pub file_name: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    /// Where the bytes live in the storage backend.
+    pub storage_key: String,
+    pub created_at: DateTime<Utc>,
+    /// Text read from the file at upload, for models that cannot read the file itself.
+    pub extracted_text: Option<String>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = attachments)]
+pub struct NewAttachment {
+    pub id: Uuid,
+    pub user_id: String,
+    pub chat_id: Option<Uuid>,
+    pub message_id: Option<Uuid>,
+    pub file_name: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    pub storage_key: String,
+    pub created_at: DateTime<Utc>,
+    pub extracted_text: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateAttachmentDto {
+    /// Chosen up front so the storage key can be derived from it before the row exists.
+    pub id: Uuid,
+    pub user_id: String,
+    pub chat_id: Option<Uuid>,
+    pub file_name: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    pub storage_key: String,
+    pub extracted_text: Option<String>,
+}
+
+impl From<CreateAttachmentDto> for NewAttachment {
+    fn from(dto: CreateAttachmentDto) -> Self {
+        Self {
+            id: dto.id,
+            user_id: dto.user_id,
+            chat_id: dto.chat_id,
+            message_id: None,
+            file_name: dto.file_name,
+            mime_type: dto.mime_type,
+            size_bytes: dto.size_bytes,
+            storage_key: dto.storage_key,
+            created_at: Utc::now(),
+            extracted_text: dto.extracted_text,
+        }
+    }
+}
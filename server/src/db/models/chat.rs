@@ -20,6 +20,12 @@ pub struct ChatModel {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    /// Fallback chain entries, see `ai::fallback`
+    pub fallback_models: Option<serde_json::Value>,
+    /// Summary of the messages up to `summarized_through`, sent in their place
+    pub summary: Option<String>,
+    /// Sequence number of the last message `summary` covers
+    pub summarized_through: Option<i32>,
 }
 
 #[derive(Debug, Clone, Insertable)]
@@ -33,6 +39,9 @@ pub struct NewChat {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    pub fallback_models: Option<serde_json::Value>,
+    pub summary: Option<String>,
+    pub summarized_through: Option<i32>,
 }
 
 #[derive(Debug, Clone, AsChangeset)]
@@ -41,6 +50,7 @@ pub struct UpdateChat {
     pub title: Option<String>,
     pub model_provider: Option<AiProvider>,
     pub model_id: Option<String>,
+    pub fallback_models: Option<Option<serde_json::Value>>,
     pub updated_at: DateTime<Utc>,
 }
 
@@ -50,6 +60,7 @@ pub struct CreateChatDto {
     pub title: String,
     pub model_provider: AiProvider,
     pub model_id: String,
+    pub fallback_models: Option<serde_json::Value>,
 }
 
 impl From<CreateChatDto> for NewChat {
@@ -64,6 +75,9 @@ impl From<CreateChatDto> for NewChat {
             created_at: now,
             updated_at: now,
             deleted_at: None,
+            fallback_models: dto.fallback_models,
+            summary: None,
+            summarized_through: None,
         }
     }
 }
@@ -73,6 +87,8 @@ pub struct UpdateChatDto {
     pub title: Option<String>,
     pub model_provider: Option<AiProvider>,
     pub model_id: Option<String>,
+    /// `Some(None)` clears the chat's chain so the global one applies again
+    pub fallback_models: Option<Option<serde_json::Value>>,
 }
 
 impl From<UpdateChatDto> for UpdateChat {
@@ -81,6 +97,7 @@ impl From<UpdateChatDto> for UpdateChat {
             title: dto.title,
             model_provider: dto.model_provider,
             model_id: dto.model_id,
+            fallback_models: dto.fallback_models,
             updated_at: Utc::now(),
         }
     }
@@ -13,6 +13,8 @@ pub enum MessageRole {
     User,
     Assistant,
     System,
+    /// The result of a tool call requested by an assistant turn.
+    Tool,
 }
 
 impl MessageRole {
@@ -21,6 +23,7 @@ impl MessageRole {
             MessageRole::User => "user",
             MessageRole::Assistant => "assistant",
             MessageRole::System => "system",
+            MessageRole::Tool => "tool",
         }
     }
 
@@ -29,6 +32,7 @@ impl MessageRole {
             "user" => Some(MessageRole::User),
             "assistant" => Some(MessageRole::Assistant),
             "system" => Some(MessageRole::System),
+            "tool" => Some(MessageRole::Tool),
             _ => None,
         }
     }
@@ -72,6 +76,21 @@ pub struct MessageModel {
     pub created_at: DateTime<Utc>,
     pub tokens_used: Option<i32>,
     pub model_used: Option<String>,
+    /// On a `tool` message, the id of the call it answers.
+    pub tool_call_id: Option<String>,
+    /// `ai::types::ContentPart`s other than the text in `content`, see
+    /// `ContentPart::from_message`.
+    pub content_parts: Option<serde_json::Value>,
+    /// Kept in the model's context when older messages are left out to fit it.
+    pub is_pinned: bool,
+    pub prompt_tokens: Option<i32>,
+    pub completion_tokens: Option<i32>,
+    /// Part of `prompt_tokens`, read from the provider's prompt cache.
+    pub cached_tokens: Option<i32>,
+    /// Part of `completion_tokens`, spent reasoning.
+    pub reasoning_tokens: Option<i32>,
+    /// What the answer cost at the model's `ai_models` prices.
+    pub cost: Option<rust_decimal::Decimal>,
 }
 
 #[derive(Debug, Clone, Insertable)]
@@ -87,6 +106,14 @@ pub struct NewMessage {
     pub created_at: DateTime<Utc>,
     pub tokens_used: Option<i32>,
     pub model_used: Option<String>,
+    pub tool_call_id: Option<String>,
+    pub content_parts: Option<serde_json::Value>,
+    pub is_pinned: bool,
+    pub prompt_tokens: Option<i32>,
+    pub completion_tokens: Option<i32>,
+    pub cached_tokens: Option<i32>,
+    pub reasoning_tokens: Option<i32>,
+    pub cost: Option<rust_decimal::Decimal>,
 }
 
 #[derive(Debug, Clone, AsChangeset)]
@@ -96,12 +123,19 @@ pub struct UpdateMessage {
     pub metadata: Option<Option<serde_json::Value>>,
     pub tokens_used: Option<i32>,
     pub model_used: Option<String>,
+    pub is_pinned: Option<bool>,
+    pub prompt_tokens: Option<i32>,
+    pub completion_tokens: Option<i32>,
+    pub cached_tokens: Option<i32>,
+    pub reasoning_tokens: Option<i32>,
+    pub cost: Option<rust_decimal::Decimal>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateMessageDto {
     pub content: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    pub is_pinned: Option<bool>,
 }
 
 impl From<UpdateMessageDto> for UpdateMessage {
@@ -111,6 +145,40 @@ impl From<UpdateMessageDto> for UpdateMessage {
             metadata: dto.metadata.map(Some),
             tokens_used: None,
             model_used: None,
+            is_pinned: dto.is_pinned,
+            prompt_tokens: None,
+            completion_tokens: None,
+            cached_tokens: None,
+            reasoning_tokens: None,
+            cost: None,
+        }
+    }
+}
+
+/// Tokens an answer used and what it cost, saved on its assistant message.
+#[derive(Debug, Clone, Default)]
+pub struct MessageUsageDto {
+    pub model: String,
+    pub prompt_tokens: i32,
+    pub completion_tokens: i32,
+    pub cached_tokens: Option<i32>,
+    pub reasoning_tokens: Option<i32>,
+    pub cost: Option<rust_decimal::Decimal>,
+}
+
+impl From<MessageUsageDto> for UpdateMessage {
+    fn from(dto: MessageUsageDto) -> Self {
+        Self {
+            content: None,
+            metadata: None,
+            tokens_used: Some(dto.prompt_tokens + dto.completion_tokens),
+            model_used: Some(dto.model),
+            is_pinned: None,
+            prompt_tokens: Some(dto.prompt_tokens),
+            completion_tokens: Some(dto.completion_tokens),
+            cached_tokens: dto.cached_tokens,
+            reasoning_tokens: dto.reasoning_tokens,
+            cost: dto.cost,
         }
     }
 }
@@ -123,6 +191,10 @@ pub struct CreateMessageDto {
     pub metadata: Option<serde_json::Value>,
     pub parent_message_id: Option<Uuid>,
     pub sequence_number: i32,
+    pub tool_call_id: Option<String>,
+    pub content_parts: Option<serde_json::Value>,
+    /// On an assistant message, the model that answered.
+    pub model_used: Option<String>,
 }
 
 impl From<CreateMessageDto> for NewMessage {
@@ -137,7 +209,15 @@ impl From<CreateMessageDto> for NewMessage {
             sequence_number: dto.sequence_number,
             created_at: Utc::now(),
             tokens_used: None,
-            model_used: None,
+            model_used: dto.model_used,
+            tool_call_id: dto.tool_call_id,
+            content_parts: dto.content_parts,
+            is_pinned: false,
+            prompt_tokens: None,
+            completion_tokens: None,
+            cached_tokens: None,
+            reasoning_tokens: None,
+            cost: None,
         }
     }
 }
@@ -2,6 +2,8 @@ mod user;
 pub use user::*;
 mod ai_model;
 pub use ai_model::*;
+mod user_ai_model;
+pub use user_ai_model::*;
 mod user_api_key;
 pub use user_api_key::*;
 mod chat;
@@ -10,3 +12,5 @@ mod message;
 pub use message::*;
 mod feature;
 pub use feature::*;
+mod attachment;
+pub use attachment::*;
@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::schema::user_models;
+
+/// A user's settings for one catalog model. Models without a row are enabled and
+/// have no preferences.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize, Deserialize)]
+#[diesel(table_name = user_models)]
+#[diesel(primary_key(user_id, model_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UserAiModelModel {
+    pub user_id: String,
+    pub model_id: Uuid,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub is_favorite: bool,
+    pub sort_order: Option<i32>,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = user_models)]
+pub struct NewUserAiModel {
+    pub user_id: String,
+    pub model_id: Uuid,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub is_favorite: bool,
+    pub sort_order: Option<i32>,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, AsChangeset)]
+#[diesel(table_name = user_models)]
+pub struct UpdateUserAiModel {
+    pub is_favorite: Option<bool>,
+    pub sort_order: Option<Option<i32>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpdateUserAiModelDto {
+    pub is_favorite: Option<bool>,
+    /// `Some(None)` clears the position so the model sorts by name
+    pub sort_order: Option<Option<i32>>,
+}
+
+impl From<UpdateUserAiModelDto> for UpdateUserAiModel {
+    fn from(dto: UpdateUserAiModelDto) -> Self {
+        Self {
+            is_favorite: dto.is_favorite,
+            sort_order: dto.sort_order,
+            updated_at: Utc::now(),
+        }
+    }
+}
@@ -19,6 +19,10 @@ pub struct UserApiKeyModel {
     pub is_default: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub base_url: Option<String>,
+    pub extra_headers: Option<serde_json::Value>,
+    /// Model ids served through the key, as a JSON array.
+    pub models: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Insertable)]
@@ -31,6 +35,9 @@ pub struct NewUserApiKey {
     pub is_default: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub base_url: Option<String>,
+    pub extra_headers: Option<serde_json::Value>,
+    pub models: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, AsChangeset)]
@@ -38,6 +45,9 @@ pub struct NewUserApiKey {
 pub struct UpdateUserApiKey {
     pub encrypted_key: Option<String>,
     pub is_default: Option<bool>,
+    pub base_url: Option<Option<String>>,
+    pub extra_headers: Option<Option<serde_json::Value>>,
+    pub models: Option<Option<serde_json::Value>>,
     pub updated_at: DateTime<Utc>,
 }
 
@@ -47,6 +57,9 @@ pub struct CreateUserApiKeyDto {
     pub provider: AiProvider,
     pub encrypted_key: String,
     pub is_default: bool,
+    pub base_url: Option<String>,
+    pub extra_headers: Option<serde_json::Value>,
+    pub models: Option<serde_json::Value>,
 }
 
 impl From<CreateUserApiKeyDto> for NewUserApiKey {
@@ -60,6 +73,9 @@ impl From<CreateUserApiKeyDto> for NewUserApiKey {
             is_default: dto.is_default,
             created_at: now,
             updated_at: now,
+            base_url: dto.base_url,
+            extra_headers: dto.extra_headers,
+            models: dto.models,
         }
     }
 }
@@ -68,6 +84,9 @@ impl From<CreateUserApiKeyDto> for NewUserApiKey {
 pub struct UpdateUserApiKeyDto {
     pub encrypted_key: Option<String>,
     pub is_default: Option<bool>,
+    pub base_url: Option<Option<String>>,
+    pub extra_headers: Option<Option<serde_json::Value>>,
+    pub models: Option<Option<serde_json::Value>>,
 }
 
 impl From<UpdateUserApiKeyDto> for UpdateUserApiKey {
@@ -75,6 +94,9 @@ impl From<UpdateUserApiKeyDto> for UpdateUserApiKey {
         Self {
             encrypted_key: dto.encrypted_key,
             is_default: dto.is_default,
+            base_url: dto.base_url,
+            extra_headers: dto.extra_headers,
+            models: dto.models,
             updated_at: Utc::now(),
         }
     }
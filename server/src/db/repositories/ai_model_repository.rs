@@ -1,11 +1,18 @@
 use async_trait::async_trait;
 use diesel::prelude::*;
-use diesel_async::RunQueryDsl;
+use diesel::upsert::excluded;
+use diesel_async::{AsyncConnection, RunQueryDsl, scoped_futures::ScopedFutureExt};
 use emixdiesel::{Error, Result};
 use uuid::Uuid;
 
-use crate::db::models::{AiModelModel, AiProvider};
-use crate::db::{DbPool, schema::ai_models};
+use crate::db::models::{
+    AiModelModel, AiProvider, CreateAiModelDto, NewAiModel, NewUserAiModel, UpdateAiModel,
+    UpdateAiModelDto, UpdateUserAiModel, UpdateUserAiModelDto, UserAiModelModel,
+};
+use crate::db::{
+    DbPool,
+    schema::{ai_models, user_models},
+};
 
 #[async_trait]
 pub trait TAiModelRepository: Send + Sync {
@@ -17,9 +24,31 @@ pub trait TAiModelRepository: Send + Sync {
         model_id: &str,
     ) -> Result<Option<AiModelModel>>;
     async fn get(&self, id: Uuid) -> Result<Option<AiModelModel>>;
+    async fn create(&self, model: CreateAiModelDto) -> Result<AiModelModel>;
+    async fn update(&self, id: Uuid, model: UpdateAiModelDto) -> Result<AiModelModel>;
+    async fn delete(&self, id: Uuid) -> Result<()>;
+    async fn insert_if_missing(&self, model: CreateAiModelDto) -> Result<bool>;
+    async fn insert_or_reactivate(&self, model: CreateAiModelDto) -> Result<AiModelModel>;
+    async fn deactivate_missing(
+        &self,
+        provider: &AiProvider,
+        model_ids: &[String],
+    ) -> Result<usize>;
     async fn enable_for_user(&self, user_id: &str, model_id: Uuid) -> Result<()>;
     async fn disable_for_user(&self, user_id: &str, model_id: Uuid) -> Result<()>;
-    async fn list_for_user(&self, user_id: &str) -> Result<Vec<AiModelModel>>;
+    async fn list_for_user(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<(AiModelModel, Option<UserAiModelModel>)>>;
+    async fn update_preferences_for_user(
+        &self,
+        user_id: &str,
+        model_id: Uuid,
+        preferences: UpdateUserAiModelDto,
+    ) -> Result<UserAiModelModel>;
+    async fn get_default_for_user(&self, user_id: &str) -> Result<Option<AiModelModel>>;
+    async fn set_default_for_user(&self, user_id: &str, model_id: Uuid) -> Result<()>;
+    async fn clear_default_for_user(&self, user_id: &str) -> Result<()>;
 }
 
 pub struct AiModelRepository {
@@ -30,6 +59,61 @@ impl AiModelRepository {
     pub fn new(pool: DbPool) -> Self {
         Self { pool }
     }
+
+    /// Models have no `user_models` row until the user changes something, so this
+    /// inserts one when needed. A disabled model cannot stay the user's default.
+    async fn set_enabled_for_user(
+        &self,
+        user_id: &str,
+        model_id: Uuid,
+        enabled: bool,
+    ) -> Result<()> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        let now = chrono::Utc::now();
+        let new_row = NewUserAiModel {
+            user_id: user_id.to_string(),
+            model_id,
+            enabled,
+            created_at: now,
+            updated_at: now,
+            is_favorite: false,
+            sort_order: None,
+            is_default: false,
+        };
+
+        if enabled {
+            diesel::insert_into(user_models::table)
+                .values(&new_row)
+                .on_conflict((user_models::user_id, user_models::model_id))
+                .do_update()
+                .set((
+                    user_models::enabled.eq(true),
+                    user_models::updated_at.eq(now),
+                ))
+                .execute(&mut conn)
+                .await
+        } else {
+            diesel::insert_into(user_models::table)
+                .values(&new_row)
+                .on_conflict((user_models::user_id, user_models::model_id))
+                .do_update()
+                .set((
+                    user_models::enabled.eq(false),
+                    user_models::is_default.eq(false),
+                    user_models::updated_at.eq(now),
+                ))
+                .execute(&mut conn)
+                .await
+        }
+        .map_err(Error::from_std_error)?;
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -96,65 +180,302 @@ impl TAiModelRepository for AiModelRepository {
             .map_err(Error::from_std_error)
     }
 
-    async fn enable_for_user(&self, user_id: &str, model_id: Uuid) -> Result<()> {
+    async fn create(&self, model: CreateAiModelDto) -> Result<AiModelModel> {
         let mut conn = self
             .pool
             .get()
             .await
             .map_err(|e| Error::from_std_error(e))?;
 
-        // Check if user_models table exists - we'll create migration for this
-        // For now, this is a placeholder that will be implemented after migration
-        diesel::sql_query(
-            "INSERT INTO user_models (user_id, model_id, enabled, created_at, updated_at) 
-             VALUES ($1, $2, true, NOW(), NOW())
-             ON CONFLICT (user_id, model_id) 
-             DO UPDATE SET enabled = true, updated_at = NOW()",
-        )
-        .bind::<diesel::sql_types::Text, _>(user_id)
-        .bind::<diesel::sql_types::Uuid, _>(&model_id)
-        .execute(&mut conn)
-        .await
-        .map_err(Error::from_std_error)?;
+        let new_model: NewAiModel = model.into();
+
+        diesel::insert_into(ai_models::table)
+            .values(&new_model)
+            .get_result(&mut conn)
+            .await
+            .map_err(Error::from_std_error)
+    }
+
+    async fn update(&self, id: Uuid, model: UpdateAiModelDto) -> Result<AiModelModel> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        let update_model: UpdateAiModel = model.into();
+
+        diesel::update(ai_models::table.find(id))
+            .set(&update_model)
+            .get_result(&mut conn)
+            .await
+            .map_err(Error::from_std_error)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<()> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        // user_models rows go with it (ON DELETE CASCADE); chats keep the model id as text
+        diesel::delete(ai_models::table.find(id))
+            .execute(&mut conn)
+            .await
+            .map_err(Error::from_std_error)?;
 
         Ok(())
     }
 
-    async fn disable_for_user(&self, user_id: &str, model_id: Uuid) -> Result<()> {
+    async fn insert_if_missing(&self, model: CreateAiModelDto) -> Result<bool> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        let new_model: NewAiModel = model.into();
+
+        // Keyed on (provider, model_id); an existing row is left exactly as it is, so
+        // admin edits and sync deactivations survive restarts
+        let inserted = diesel::insert_into(ai_models::table)
+            .values(&new_model)
+            .on_conflict((ai_models::provider, ai_models::model_id))
+            .do_nothing()
+            .execute(&mut conn)
+            .await
+            .map_err(Error::from_std_error)?;
+
+        Ok(inserted > 0)
+    }
+
+    async fn insert_or_reactivate(&self, model: CreateAiModelDto) -> Result<AiModelModel> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        let new_model: NewAiModel = model.into();
+
+        // Existing rows keep their curated details and are reactivated; a capability is
+        // switched on when either the row or the new listing has it
+        diesel::insert_into(ai_models::table)
+            .values(&new_model)
+            .on_conflict((ai_models::provider, ai_models::model_id))
+            .do_update()
+            .set((
+                ai_models::is_active.eq(true),
+                ai_models::supports_streaming
+                    .eq(ai_models::supports_streaming.or(excluded(ai_models::supports_streaming))),
+                ai_models::supports_images
+                    .eq(ai_models::supports_images.or(excluded(ai_models::supports_images))),
+                ai_models::supports_functions
+                    .eq(ai_models::supports_functions.or(excluded(ai_models::supports_functions))),
+                ai_models::updated_at.eq(new_model.updated_at),
+            ))
+            .get_result(&mut conn)
+            .await
+            .map_err(Error::from_std_error)
+    }
+
+    async fn deactivate_missing(
+        &self,
+        provider: &AiProvider,
+        model_ids: &[String],
+    ) -> Result<usize> {
         let mut conn = self
             .pool
             .get()
             .await
             .map_err(|e| Error::from_std_error(e))?;
 
-        diesel::sql_query(
-            "UPDATE user_models 
-             SET enabled = false, updated_at = NOW()
-             WHERE user_id = $1 AND model_id = $2",
+        diesel::update(
+            ai_models::table
+                .filter(ai_models::provider.eq(provider))
+                .filter(ai_models::is_active.eq(true))
+                .filter(ai_models::model_id.ne_all(model_ids)),
         )
-        .bind::<diesel::sql_types::Text, _>(user_id)
-        .bind::<diesel::sql_types::Uuid, _>(&model_id)
+        .set((
+            ai_models::is_active.eq(false),
+            ai_models::updated_at.eq(chrono::Utc::now()),
+        ))
         .execute(&mut conn)
         .await
-        .map_err(Error::from_std_error)?;
+        .map_err(Error::from_std_error)
+    }
 
-        Ok(())
+    async fn enable_for_user(&self, user_id: &str, model_id: Uuid) -> Result<()> {
+        self.set_enabled_for_user(user_id, model_id, true).await
     }
 
-    async fn list_for_user(&self, _user_id: &str) -> Result<Vec<AiModelModel>> {
+    async fn disable_for_user(&self, user_id: &str, model_id: Uuid) -> Result<()> {
+        self.set_enabled_for_user(user_id, model_id, false).await
+    }
+
+    async fn list_for_user(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<(AiModelModel, Option<UserAiModelModel>)>> {
         let mut conn = self
             .pool
             .get()
             .await
             .map_err(|e| Error::from_std_error(e))?;
 
-        // TODO: Once user_models table exists, join with it to filter by user
-        // For now, return all active models
-        // This will be updated when the user_models migration is created
+        // Models the user never touched have no row and count as enabled
         ai_models::table
+            .left_join(
+                user_models::table.on(user_models::model_id
+                    .eq(ai_models::id)
+                    .and(user_models::user_id.eq(user_id))),
+            )
             .filter(ai_models::is_active.eq(true))
-            .load::<AiModelModel>(&mut conn)
+            .filter(
+                user_models::enabled
+                    .is_null()
+                    .or(user_models::enabled.eq(true)),
+            )
+            .order((
+                user_models::is_favorite.desc().nulls_last(),
+                user_models::sort_order.asc().nulls_last(),
+                ai_models::display_name.asc(),
+            ))
+            .select((
+                AiModelModel::as_select(),
+                Option::<UserAiModelModel>::as_select(),
+            ))
+            .load::<(AiModelModel, Option<UserAiModelModel>)>(&mut conn)
             .await
             .map_err(Error::from_std_error)
     }
+
+    async fn update_preferences_for_user(
+        &self,
+        user_id: &str,
+        model_id: Uuid,
+        preferences: UpdateUserAiModelDto,
+    ) -> Result<UserAiModelModel> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        let now = chrono::Utc::now();
+        let new_row = NewUserAiModel {
+            user_id: user_id.to_string(),
+            model_id,
+            enabled: true,
+            created_at: now,
+            updated_at: now,
+            is_favorite: preferences.is_favorite.unwrap_or(false),
+            sort_order: preferences.sort_order.flatten(),
+            is_default: false,
+        };
+        let update_row: UpdateUserAiModel = preferences.into();
+
+        diesel::insert_into(user_models::table)
+            .values(&new_row)
+            .on_conflict((user_models::user_id, user_models::model_id))
+            .do_update()
+            .set(&update_row)
+            .get_result(&mut conn)
+            .await
+            .map_err(Error::from_std_error)
+    }
+
+    async fn get_default_for_user(&self, user_id: &str) -> Result<Option<AiModelModel>> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        ai_models::table
+            .inner_join(user_models::table)
+            .filter(user_models::user_id.eq(user_id))
+            .filter(user_models::is_default.eq(true))
+            .filter(user_models::enabled.eq(true))
+            .filter(ai_models::is_active.eq(true))
+            .select(AiModelModel::as_select())
+            .first::<AiModelModel>(&mut conn)
+            .await
+            .optional()
+            .map_err(Error::from_std_error)
+    }
+
+    async fn set_default_for_user(&self, user_id: &str, model_id: Uuid) -> Result<()> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            async move {
+                // Unset the user's current default
+                diesel::update(
+                    user_models::table
+                        .filter(user_models::user_id.eq(user_id))
+                        .filter(user_models::is_default.eq(true)),
+                )
+                .set(user_models::is_default.eq(false))
+                .execute(conn)
+                .await?;
+
+                // The default model is also enabled, creating the row if needed
+                let now = chrono::Utc::now();
+                diesel::insert_into(user_models::table)
+                    .values(&NewUserAiModel {
+                        user_id: user_id.to_string(),
+                        model_id,
+                        enabled: true,
+                        created_at: now,
+                        updated_at: now,
+                        is_favorite: false,
+                        sort_order: None,
+                        is_default: true,
+                    })
+                    .on_conflict((user_models::user_id, user_models::model_id))
+                    .do_update()
+                    .set((
+                        user_models::enabled.eq(true),
+                        user_models::is_default.eq(true),
+                        user_models::updated_at.eq(now),
+                    ))
+                    .execute(conn)
+                    .await?;
+
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+        .map_err(Error::from_std_error)
+    }
+
+    async fn clear_default_for_user(&self, user_id: &str) -> Result<()> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        diesel::update(
+            user_models::table
+                .filter(user_models::user_id.eq(user_id))
+                .filter(user_models::is_default.eq(true)),
+        )
+        .set((
+            user_models::is_default.eq(false),
+            user_models::updated_at.eq(chrono::Utc::now()),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(Error::from_std_error)?;
+
+        Ok(())
+    }
 }
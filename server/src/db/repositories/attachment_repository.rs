@@ -0,0 +1,174 @@
+use async_trait::async_trait;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use emixdiesel::{Error, Result};
+use uuid::Uuid;
+
+use crate::db::models::{AttachmentModel, CreateAttachmentDto, NewAttachment};
+use crate::db::{DbPool, schema::attachments};
+
+#[async_trait]
+pub trait TAttachmentRepository: Send + Sync {
+    async fn get(&self, id: Uuid, user_id: &str) -> Result<Option<AttachmentModel>>;
+    /// The user's attachments among `ids`; ids that don't exist or belong to someone
+    /// else are left out.
+    async fn get_many(&self, ids: &[Uuid], user_id: &str) -> Result<Vec<AttachmentModel>>;
+    async fn list_for_chat(&self, chat_id: Uuid, user_id: &str) -> Result<Vec<AttachmentModel>>;
+    async fn create(&self, model: CreateAttachmentDto) -> Result<AttachmentModel>;
+    /// Attaches uploads to the message that sent them, and to its chat.
+    async fn link_to_message(
+        &self,
+        ids: &[Uuid],
+        user_id: &str,
+        chat_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<()>;
+    async fn delete(&self, id: Uuid, user_id: &str) -> Result<Option<AttachmentModel>>;
+    /// Deletes the rows of a chat's attachments and returns them so their bytes can be
+    /// removed from storage.
+    async fn delete_for_chat(&self, chat_id: Uuid, user_id: &str) -> Result<Vec<AttachmentModel>>;
+}
+
+pub struct AttachmentRepository {
+    pool: DbPool,
+}
+
+impl AttachmentRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl TAttachmentRepository for AttachmentRepository {
+    async fn get(&self, id: Uuid, user_id: &str) -> Result<Option<AttachmentModel>> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        attachments::table
+            .filter(attachments::id.eq(id))
+            .filter(attachments::user_id.eq(user_id))
+            .first::<AttachmentModel>(&mut conn)
+            .await
+            .optional()
+            .map_err(Error::from_std_error)
+    }
+
+    async fn get_many(&self, ids: &[Uuid], user_id: &str) -> Result<Vec<AttachmentModel>> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        attachments::table
+            .filter(attachments::id.eq_any(ids))
+            .filter(attachments::user_id.eq(user_id))
+            .load::<AttachmentModel>(&mut conn)
+            .await
+            .map_err(Error::from_std_error)
+    }
+
+    async fn list_for_chat(&self, chat_id: Uuid, user_id: &str) -> Result<Vec<AttachmentModel>> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        attachments::table
+            .filter(attachments::chat_id.eq(chat_id))
+            .filter(attachments::user_id.eq(user_id))
+            .order(attachments::created_at.asc())
+            .load::<AttachmentModel>(&mut conn)
+            .await
+            .map_err(Error::from_std_error)
+    }
+
+    async fn create(&self, model: CreateAttachmentDto) -> Result<AttachmentModel> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        let new_attachment: NewAttachment = model.into();
+
+        diesel::insert_into(attachments::table)
+            .values(&new_attachment)
+            .get_result(&mut conn)
+            .await
+            .map_err(Error::from_std_error)
+    }
+
+    async fn link_to_message(
+        &self,
+        ids: &[Uuid],
+        user_id: &str,
+        chat_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        diesel::update(
+            attachments::table
+                .filter(attachments::id.eq_any(ids))
+                .filter(attachments::user_id.eq(user_id)),
+        )
+        .set((
+            attachments::chat_id.eq(Some(chat_id)),
+            attachments::message_id.eq(Some(message_id)),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(Error::from_std_error)?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, id: Uuid, user_id: &str) -> Result<Option<AttachmentModel>> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        diesel::delete(
+            attachments::table
+                .filter(attachments::id.eq(id))
+                .filter(attachments::user_id.eq(user_id)),
+        )
+        .get_result::<AttachmentModel>(&mut conn)
+        .await
+        .optional()
+        .map_err(Error::from_std_error)
+    }
+
+    async fn delete_for_chat(&self, chat_id: Uuid, user_id: &str) -> Result<Vec<AttachmentModel>> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        diesel::delete(
+            attachments::table
+                .filter(attachments::chat_id.eq(chat_id))
+                .filter(attachments::user_id.eq(user_id)),
+        )
+        .get_results::<AttachmentModel>(&mut conn)
+        .await
+        .map_err(Error::from_std_error)
+    }
+}
@@ -1,14 +1,14 @@
 use async_trait::async_trait;
 use diesel::dsl::max;
 use diesel::prelude::*;
-use diesel_async::RunQueryDsl;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use emixdiesel::{Error, Result};
 use uuid::Uuid;
 
 use crate::db::dto::{Pagination, ResultSet};
 use crate::db::models::{
-    ChatModel, CreateChatDto, CreateMessageDto, MessageModel, NewChat, NewMessage, UpdateChat,
-    UpdateChatDto, UpdateMessage, UpdateMessageDto,
+    ChatModel, CreateChatDto, CreateMessageDto, MessageModel, MessageUsageDto, NewChat, NewMessage,
+    UpdateChat, UpdateChatDto, UpdateMessage, UpdateMessageDto,
 };
 use crate::db::{
     DbPool,
@@ -32,7 +32,7 @@ pub trait TChatRepository: Send + Sync {
     async fn list_messages(&self, chat_id: Uuid, user_id: &str) -> Result<Vec<MessageModel>>;
     async fn create_message(&self, model: CreateMessageDto) -> Result<MessageModel>;
     async fn get_next_sequence_number(&self, chat_id: Uuid) -> Result<i32>;
-    async fn update_tokens_used(&self, id: Uuid, tokens: i32, model: &str) -> Result<()>;
+    async fn update_usage(&self, id: Uuid, usage: MessageUsageDto) -> Result<()>;
     async fn update_message(
         &self,
         id: Uuid,
@@ -42,6 +42,12 @@ pub trait TChatRepository: Send + Sync {
     ) -> Result<MessageModel>;
     async fn delete_message(&self, id: Uuid, chat_id: Uuid, user_id: &str) -> Result<()>;
     async fn clear_messages(&self, chat_id: Uuid, user_id: &str) -> Result<()>;
+    async fn update_summary(
+        &self,
+        chat_id: Uuid,
+        summary: &str,
+        summarized_through: i32,
+    ) -> Result<()>;
 }
 
 pub struct ChatRepository {
@@ -247,7 +253,7 @@ impl TChatRepository for ChatRepository {
         Ok(max_seq.unwrap_or(0) + 1)
     }
 
-    async fn update_tokens_used(&self, id: Uuid, tokens: i32, model: &str) -> Result<()> {
+    async fn update_usage(&self, id: Uuid, usage: MessageUsageDto) -> Result<()> {
         let mut conn = self
             .pool
             .get()
@@ -263,12 +269,7 @@ impl TChatRepository for ChatRepository {
             .map_err(Error::from_std_error)?
             .ok_or_else(|| Error::from_other_error("Message not found".to_string()))?;
 
-        let update = UpdateMessage {
-            content: None,
-            metadata: None,
-            tokens_used: Some(tokens),
-            model_used: Some(model.to_string()),
-        };
+        let update = UpdateMessage::from(usage);
 
         diesel::update(messages::table.find(id))
             .set(&update)
@@ -293,7 +294,7 @@ impl TChatRepository for ChatRepository {
             .map_err(|e| Error::from_std_error(e))?;
 
         // Verify chat belongs to user
-        let _chat = chats::table
+        let chat = chats::table
             .filter(chats::id.eq(chat_id))
             .filter(chats::user_id.eq(user_id))
             .filter(chats::deleted_at.is_null())
@@ -304,7 +305,7 @@ impl TChatRepository for ChatRepository {
             .ok_or_else(|| Error::from_other_error("Chat not found".to_string()))?;
 
         // Check if message exists and belongs to the chat
-        let _existing = messages::table
+        let existing = messages::table
             .filter(messages::id.eq(id))
             .filter(messages::chat_id.eq(chat_id))
             .first::<MessageModel>(&mut conn)
@@ -315,11 +316,17 @@ impl TChatRepository for ChatRepository {
 
         let update: UpdateMessage = model.into();
 
-        diesel::update(messages::table.filter(messages::id.eq(id)))
+        let message: MessageModel = diesel::update(messages::table.filter(messages::id.eq(id)))
             .set(&update)
             .get_result(&mut conn)
             .await
-            .map_err(Error::from_std_error)
+            .map_err(Error::from_std_error)?;
+
+        if covered_by_summary(&chat, &existing) {
+            reset_summary(&mut conn, chat_id).await?;
+        }
+
+        Ok(message)
     }
 
     async fn delete_message(&self, id: Uuid, chat_id: Uuid, user_id: &str) -> Result<()> {
@@ -330,7 +337,7 @@ impl TChatRepository for ChatRepository {
             .map_err(|e| Error::from_std_error(e))?;
 
         // Verify chat belongs to user
-        let _chat = chats::table
+        let chat = chats::table
             .filter(chats::id.eq(chat_id))
             .filter(chats::user_id.eq(user_id))
             .filter(chats::deleted_at.is_null())
@@ -341,7 +348,7 @@ impl TChatRepository for ChatRepository {
             .ok_or_else(|| Error::from_other_error("Chat not found".to_string()))?;
 
         // Check if message exists and belongs to the chat
-        let _existing = messages::table
+        let existing = messages::table
             .filter(messages::id.eq(id))
             .filter(messages::chat_id.eq(chat_id))
             .first::<MessageModel>(&mut conn)
@@ -355,6 +362,10 @@ impl TChatRepository for ChatRepository {
             .await
             .map_err(Error::from_std_error)?;
 
+        if covered_by_summary(&chat, &existing) {
+            reset_summary(&mut conn, chat_id).await?;
+        }
+
         Ok(())
     }
 
@@ -381,6 +392,51 @@ impl TChatRepository for ChatRepository {
             .await
             .map_err(Error::from_std_error)?;
 
+        reset_summary(&mut conn, chat_id).await
+    }
+
+    async fn update_summary(
+        &self,
+        chat_id: Uuid,
+        summary: &str,
+        summarized_through: i32,
+    ) -> Result<()> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| Error::from_std_error(e))?;
+
+        diesel::update(chats::table.filter(chats::id.eq(chat_id)))
+            .set((
+                chats::summary.eq(Some(summary)),
+                chats::summarized_through.eq(Some(summarized_through)),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(Error::from_std_error)?;
+
         Ok(())
     }
 }
+
+/// Whether `message` is one of those the chat's summary stands in for.
+fn covered_by_summary(chat: &ChatModel, message: &MessageModel) -> bool {
+    chat.summarized_through
+        .is_some_and(|through| message.sequence_number <= through)
+}
+
+/// Drops a chat's summary once the messages it covers have changed; the next request
+/// summarizes them again.
+async fn reset_summary(conn: &mut AsyncPgConnection, chat_id: Uuid) -> Result<()> {
+    diesel::update(chats::table.filter(chats::id.eq(chat_id)))
+        .set((
+            chats::summary.eq(None::<String>),
+            chats::summarized_through.eq(None::<i32>),
+        ))
+        .execute(conn)
+        .await
+        .map_err(Error::from_std_error)?;
+
+    Ok(())
+}
@@ -8,3 +8,5 @@ mod chat_repository;
 pub use chat_repository::*;
 mod user_feature_repository;
 pub use user_feature_repository::*;
+mod attachment_repository;
+pub use attachment_repository::*;
@@ -26,6 +26,12 @@ diesel::table! {
         is_active -> Bool,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        base_url -> Nullable<Text>,
+        extra_headers -> Nullable<Jsonb>,
+        input_cost_per_token -> Nullable<Numeric>,
+        output_cost_per_token -> Nullable<Numeric>,
+        default_parameters -> Nullable<Jsonb>,
+        cached_input_cost_per_token -> Nullable<Numeric>,
     }
 }
 
@@ -38,6 +44,9 @@ diesel::table! {
         is_default -> Bool,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        base_url -> Nullable<Text>,
+        extra_headers -> Nullable<Jsonb>,
+        models -> Nullable<Jsonb>,
     }
 }
 
@@ -51,6 +60,9 @@ diesel::table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         deleted_at -> Nullable<Timestamptz>,
+        fallback_models -> Nullable<Jsonb>,
+        summary -> Nullable<Text>,
+        summarized_through -> Nullable<Int4>,
     }
 }
 
@@ -66,6 +78,14 @@ diesel::table! {
         created_at -> Timestamptz,
         tokens_used -> Nullable<Int4>,
         model_used -> Nullable<Text>,
+        tool_call_id -> Nullable<Text>,
+        content_parts -> Nullable<Jsonb>,
+        is_pinned -> Bool,
+        prompt_tokens -> Nullable<Int4>,
+        completion_tokens -> Nullable<Int4>,
+        cached_tokens -> Nullable<Int4>,
+        reasoning_tokens -> Nullable<Int4>,
+        cost -> Nullable<Numeric>,
     }
 }
 
@@ -80,9 +100,42 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    user_models (user_id, model_id) {
+        user_id -> Text,
+        model_id -> Uuid,
+        enabled -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        is_favorite -> Bool,
+        sort_order -> Nullable<Int4>,
+        is_default -> Bool,
+    }
+}
+
+diesel::table! {
+    attachments (id) {
+        id -> Uuid,
+        user_id -> Text,
+        chat_id -> Nullable<Uuid>,
+        message_id -> Nullable<Uuid>,
+        file_name -> Text,
+        mime_type -> Text,
+        size_bytes -> Int8,
+        storage_key -> Text,
+        created_at -> Timestamptz,
+        extracted_text -> Nullable<Text>,
+    }
+}
+
+diesel::joinable!(attachments -> chats (chat_id));
+diesel::joinable!(attachments -> messages (message_id));
+diesel::joinable!(attachments -> users (user_id));
 diesel::joinable!(chats -> users (user_id));
 diesel::joinable!(messages -> chats (chat_id));
 diesel::joinable!(user_api_keys -> users (user_id));
 diesel::joinable!(user_features -> users (user_id));
+diesel::joinable!(user_models -> ai_models (model_id));
+diesel::joinable!(user_models -> users (user_id));
 
-diesel::allow_tables_to_appear_in_same_query!(users, ai_models, user_api_keys, chats, messages, user_features);
+diesel::allow_tables_to_appear_in_same_query!(users, ai_models, user_api_keys, chats, messages, user_features, user_models, attachments);
@@ -31,6 +31,15 @@ impl Modify for BearerAuthAddon {
         crate::api::v1::models::list_my_models,
         crate::api::v1::models::enable_model,
         crate::api::v1::models::disable_model,
+        crate::api::v1::models::update_model_preferences,
+        crate::api::v1::models::set_default_model,
+        crate::api::v1::models::clear_default_model,
+        crate::api::v1::admin::models::create_model,
+        crate::api::v1::admin::models::update_model,
+        crate::api::v1::admin::models::activate_model,
+        crate::api::v1::admin::models::deactivate_model,
+        crate::api::v1::admin::models::delete_model,
+        crate::api::v1::admin::models::sync_models,
         crate::api::v1::chats::list_chats,
         crate::api::v1::chats::create_chat,
         crate::api::v1::chats::get_chat,
@@ -43,11 +52,18 @@ impl Modify for BearerAuthAddon {
         crate::api::v1::chats::messages::clear_messages,
         crate::api::v1::chat::chat,
         crate::api::v1::chat::stream_chat,
+        crate::api::v1::chat::estimate_chat,
+        crate::api::v1::attachments::upload_attachment,
+        crate::api::v1::attachments::get_attachment,
+        crate::api::v1::attachments::download_attachment,
+        crate::api::v1::attachments::delete_attachment,
+        crate::api::v1::images::generate_images,
         crate::api::v1::user::profile,
         crate::api::v1::user::update_profile,
         crate::api::v1::user_api_keys::list_keys,
         crate::api::v1::user_api_keys::create_key,
         crate::api::v1::user_api_keys::delete_key,
+        crate::api::v1::user_api_keys::sync_key_models,
         crate::api::v1::features::list_features,
         crate::api::v1::features::update_feature
     ),
@@ -55,7 +71,13 @@ impl Modify for BearerAuthAddon {
         schemas(
             crate::api::v1::health::HealthResponse,
             crate::api::v1::models::ModelResponse,
+            crate::api::v1::models::UserModelResponse,
+            crate::api::v1::models::UpdateModelPreferencesRequest,
+            crate::api::v1::admin::models::CreateModelRequest,
+            crate::api::v1::admin::models::UpdateModelRequest,
+            crate::api::v1::admin::models::SyncReportResponse,
             crate::api::common::UserResponse,
+            crate::api::common::ErrorResponse,
             crate::api::v1::chats::ChatResponse,
             crate::api::v1::chats::MessageResponse,
             crate::api::v1::chats::ChatWithMessagesResponse,
@@ -66,6 +88,11 @@ impl Modify for BearerAuthAddon {
             crate::api::v1::chats::messages::UpdateMessageRequest,
             crate::api::v1::chat::ChatRequest,
             crate::api::v1::chat::ChatCompletionResponse,
+            crate::api::v1::chat::ChatEstimateResponse,
+            crate::api::v1::attachments::AttachmentResponse,
+            crate::api::v1::attachments::UploadAttachmentForm,
+            crate::api::v1::images::ImageGenerationRequest,
+            crate::api::v1::images::ImageGenerationResponse,
             crate::api::v1::user::UpdateUserRequest,
             crate::api::v1::user_api_keys::UserApiKeyResponse,
             crate::api::v1::user_api_keys::CreateUserApiKeyRequest,
@@ -77,9 +104,12 @@ impl Modify for BearerAuthAddon {
     tags(
         (name = "Health", description = "Health check endpoints"),
         (name = "Models", description = "AI model catalogue"),
+        (name = "Admin", description = "Model catalogue administration (ADMIN_USER_IDS only)"),
         (name = "Chats", description = "Chat management"),
         (name = "Messages", description = "Chat message management"),
         (name = "Chat", description = "Chat completion endpoints"),
+        (name = "Attachments", description = "File uploads for chat messages"),
+        (name = "Images", description = "Image generation"),
         (name = "User", description = "Authenticated user profile"),
         (name = "User API Keys", description = "API key management"),
         (name = "Features", description = "User feature preferences")
@@ -53,6 +53,134 @@ pub fn get_firebase_auth_emulator_host() -> Option<String> {
     get_env("FIREBASE_AUTH_EMULATOR_HOST")
 }
 
+/// Base URL of the Ollama server; unset means local models are not discovered at startup.
+pub fn get_ollama_base_url() -> Option<String> {
+    get_env("OLLAMA_BASE_URL")
+}
+
+/// Whether user keys may point at `host` although it is on a private network, from the
+/// comma-separated `AI_ALLOWED_BASE_URL_HOSTS`, e.g. `vllm.internal,10.0.0.5`.
+pub fn is_allowed_base_url_host(host: &str) -> bool {
+    get_env("AI_ALLOWED_BASE_URL_HOSTS")
+        .map(|hosts| {
+            hosts
+                .split(',')
+                .any(|allowed| allowed.trim().eq_ignore_ascii_case(host))
+        })
+        .unwrap_or(false)
+}
+
+/// Server-wide key for a hosted provider, read from `<PROVIDER>_API_KEY`. Only the
+/// model catalog sync uses it; chats always run on the user's own key.
+pub fn get_ai_provider_api_key(provider: &str) -> Option<String> {
+    get_env(&format!("{}_API_KEY", provider.to_uppercase())).filter(|key| !key.is_empty())
+}
+
+/// Declarative model catalog reconciled into `ai_models` at startup; skipped when the
+/// file does not exist.
+pub fn get_model_catalog_path() -> String {
+    get_env("MODEL_CATALOG_PATH").unwrap_or_else(|| "models.json".to_string())
+}
+
+/// Firebase UIDs allowed to use the admin API, from the comma-separated `ADMIN_USER_IDS`.
+pub fn is_admin_user(user_id: &str) -> bool {
+    get_env("ADMIN_USER_IDS")
+        .map(|ids| ids.split(',').any(|id| id.trim() == user_id))
+        .unwrap_or(false)
+}
+
+/// Whether the model catalog is synced from the providers when the server starts.
+pub fn is_model_sync_on_startup() -> bool {
+    get_env("MODEL_SYNC_ON_STARTUP")
+        .map(|s| s.to_lowercase() != "false")
+        .unwrap_or(true)
+}
+
+/// Global fallback chain, e.g. `anthropic:claude-3-opus -> openai:gpt-4 -> gemini-pro`.
+/// Chats with their own chain ignore it.
+pub fn get_ai_fallback_models() -> Option<String> {
+    get_env("AI_FALLBACK_MODELS")
+}
+
+/// Whether chats that outgrow their model's context window are summarized rather than
+/// only cut short.
+pub fn is_chat_summarization_enabled() -> bool {
+    get_env("CHAT_SUMMARIZATION")
+        .map(|s| s.to_lowercase() != "false")
+        .unwrap_or(true)
+}
+
+/// Model that summarizes long chats, e.g. `openai:gpt-4o-mini`; an entry like those of
+/// `AI_FALLBACK_MODELS`. When unset, or the user has no key for it, the chat's own model
+/// summarizes.
+pub fn get_ai_summary_model() -> Option<String> {
+    get_env("AI_SUMMARY_MODEL")
+}
+
+/// How many rounds of server-side tool calls one chat request may run before the
+/// model has to answer in text.
+pub fn get_agent_max_iterations() -> usize {
+    get_env("AGENT_MAX_ITERATIONS")
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(5)
+}
+
+/// Largest file `POST /api/v1/attachments` accepts, in bytes; defaults to 20 MiB.
+pub fn get_max_attachment_bytes() -> usize {
+    get_env("MAX_ATTACHMENT_BYTES")
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(20 * 1024 * 1024)
+}
+
+/// Most characters of one attachment's text placed in a chat request; longer files are
+/// cut off with a note.
+pub fn get_attachment_context_max_chars() -> usize {
+    get_env("ATTACHMENT_CONTEXT_MAX_CHARS")
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(40_000)
+}
+
+/// Where attachments are stored: `local` (the default) or `s3`.
+pub fn get_storage_backend() -> String {
+    get_env("STORAGE_BACKEND")
+        .map(|value| value.trim().to_lowercase())
+        .unwrap_or_else(|| "local".to_string())
+}
+
+/// Directory the local storage backend writes to.
+pub fn get_storage_local_path() -> String {
+    get_env("STORAGE_LOCAL_PATH").unwrap_or_else(|| "uploads".to_string())
+}
+
+/// Custom S3 endpoint, e.g. `http://localhost:9000` for MinIO; unset means AWS.
+pub fn get_s3_endpoint() -> Option<String> {
+    get_env("S3_ENDPOINT")
+}
+
+pub fn get_s3_bucket() -> Option<String> {
+    get_env("S3_BUCKET")
+}
+
+pub fn get_s3_region() -> String {
+    get_env("S3_REGION").unwrap_or_else(|| "us-east-1".to_string())
+}
+
+pub fn get_s3_access_key_id() -> Option<String> {
+    get_env("S3_ACCESS_KEY_ID")
+}
+
+pub fn get_s3_secret_access_key() -> Option<String> {
+    get_env("S3_SECRET_ACCESS_KEY")
+}
+
+/// Retry setting for calls to `provider`: `<PROVIDER>_RETRY_<NAME>` wins over
+/// `AI_RETRY_<NAME>`.
+pub fn get_ai_retry_setting(provider: &str, name: &str) -> Option<u64> {
+    get_env(&format!("{}_RETRY_{}", provider.to_uppercase(), name))
+        .or_else(|| get_env(&format!("AI_RETRY_{}", name)))
+        .and_then(|value| value.trim().parse().ok())
+}
+
 pub fn app_env() -> String {
     get_required_env("APP_ENV")
         .and_then(|e| Ok(e.trim().to_lowercase()))
@@ -0,0 +1,94 @@
+use anyhow::{Context, Result};
+use std::io::{Cursor, Read};
+
+/// The text of a Word document's body, one paragraph per line. Tables come out one
+/// cell per line; headers, footers and comments are skipped.
+pub fn extract_text(bytes: &[u8]) -> Result<String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    let mut xml = String::new();
+    archive
+        .by_name("word/document.xml")
+        .context("Not a Word document: word/document.xml is missing")?
+        .read_to_string(&mut xml)?;
+
+    Ok(document_text(&xml))
+}
+
+/// Walks the WordprocessingML markup, keeping the content of `<w:t>` runs and turning
+/// paragraph ends, breaks and tabs into whitespace.
+fn document_text(xml: &str) -> String {
+    let mut text = String::new();
+    let mut in_run_text = false;
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        if in_run_text {
+            text.push_str(&unescape(&rest[..start]));
+        }
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        match (name, closing) {
+            ("w:t", false) => in_run_text = !tag.ends_with('/'),
+            ("w:t", true) => in_run_text = false,
+            ("w:p", true) | ("w:br", false) | ("w:cr", false) => text.push('\n'),
+            ("w:tab", false) => text.push('\t'),
+            _ => {}
+        }
+    }
+
+    text
+}
+
+/// Resolves the predefined XML entities and character references.
+fn unescape(value: &str) -> String {
+    if !value.contains('&') {
+        return value.to_string();
+    }
+
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let resolved = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse::<u32>()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+        match resolved {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+
+    result
+}
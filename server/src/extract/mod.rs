@@ -0,0 +1,77 @@
+//! Plain text from uploaded documents. It is read once, when the file is uploaded, and
+//! placed in the conversation so every model can answer questions about the file, not
+//! only those that read PDFs or Word files themselves.
+
+mod docx;
+
+use anyhow::Result;
+
+/// Most characters kept from one file; the rest of a very large document is dropped
+/// at upload rather than stored.
+const MAX_EXTRACTED_CHARS: usize = 1_000_000;
+
+const DOCX_TYPE: &str = "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+
+/// Types stored as text, besides `text/*`: Markdown, CSV and source files arrive as
+/// `text/*`, structured data as one of these.
+const TEXT_TYPES: &[&str] = &[
+    "application/json",
+    "application/xml",
+    "application/x-yaml",
+    "application/toml",
+];
+
+/// The text of a file of type `mime_type`, or `None` for files without text, such as
+/// images. Fails when a document cannot be parsed.
+pub fn extract_text(mime_type: &str, bytes: &[u8]) -> Result<Option<String>> {
+    let text = if mime_type == "application/pdf" {
+        pdf_extract::extract_text_from_mem(bytes)?
+    } else if mime_type == DOCX_TYPE {
+        docx::extract_text(bytes)?
+    } else if mime_type.starts_with("text/") || TEXT_TYPES.contains(&mime_type) {
+        let text = String::from_utf8_lossy(bytes);
+        text.strip_prefix('\u{feff}').unwrap_or(&text).to_string()
+    } else {
+        return Ok(None);
+    };
+
+    // Postgres text cannot hold NUL characters
+    let text = text.replace('\0', "");
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(match text.char_indices().nth(MAX_EXTRACTED_CHARS) {
+        Some((end, _)) => text[..end].to_string(),
+        None => text.to_string(),
+    }))
+}
+
+/// How a file's text appears in the conversation: wrapped in `<attachment>` tags
+/// naming the file, and cut to `max_chars` with a note saying so.
+pub fn context_block(
+    file_name: &str,
+    mime_type: &str,
+    text: Option<&str>,
+    max_chars: usize,
+) -> String {
+    let name = file_name.replace('"', "'");
+    let body = match text {
+        Some(text) => match text.char_indices().nth(max_chars) {
+            Some((end, _)) => format!(
+                "{}\n[Truncated: showing the first {} of {} characters]",
+                &text[..end],
+                max_chars,
+                text.chars().count()
+            ),
+            None => text.to_string(),
+        },
+        None => "[No text could be extracted from this file]".to_string(),
+    };
+
+    format!(
+        "<attachment name=\"{}\" type=\"{}\">\n{}\n</attachment>",
+        name, mime_type, body
+    )
+}
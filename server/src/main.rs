@@ -1,6 +1,7 @@
 use anyhow::Result;
 use axum::{
     Router,
+    extract::DefaultBodyLimit,
     http::HeaderValue,
     routing::{delete, get, post, put},
 };
@@ -23,7 +24,9 @@ mod api;
 mod db;
 mod docs;
 mod env;
+mod extract;
 pub mod middleware;
+mod storage;
 
 /// ONLY use concrete types in app state because of the heap allocation requirements of trait objects.
 /// NEVER derive Debug or Display for AppState.
@@ -35,6 +38,9 @@ pub struct AppState {
     pub user_api_key_repository: Arc<db::repositories::UserApiKeyRepository>,
     pub chat_repository: Arc<db::repositories::ChatRepository>,
     pub user_feature_repository: Arc<db::repositories::UserFeatureRepository>,
+    pub attachment_repository: Arc<db::repositories::AttachmentRepository>,
+    pub tool_registry: Arc<ai::tools::ToolRegistry>,
+    pub storage: Arc<storage::Storage>,
 }
 
 #[tokio::main]
@@ -79,6 +85,36 @@ async fn run() -> Result<()> {
     let chat_repository = Arc::new(db::repositories::ChatRepository::new(pool.clone()));
     let user_feature_repository =
         Arc::new(db::repositories::UserFeatureRepository::new(pool.clone()));
+    let attachment_repository = Arc::new(db::repositories::AttachmentRepository::new(pool.clone()));
+
+    let catalog_path = std::path::PathBuf::from(env::get_model_catalog_path());
+    if catalog_path.exists() {
+        let count =
+            ai::catalog::seed_from_file(ai_model_repository.as_ref(), &catalog_path).await?;
+        tracing::info!(
+            "Seeded {} new model(s) from {}",
+            count,
+            catalog_path.display()
+        );
+    } else {
+        tracing::info!("No model catalog at {}", catalog_path.display());
+    }
+
+    if has_cli_flag("--sync-models") {
+        tracing::info!("Syncing model catalog");
+        let reports = ai::catalog::sync_configured(ai_model_repository.as_ref()).await?;
+        tracing::info!("Model catalog synced for {} provider(s)", reports.len());
+        return Ok(());
+    }
+
+    if env::is_model_sync_on_startup() {
+        let ai_model_repository = ai_model_repository.clone();
+        tokio::spawn(async move {
+            if let Err(e) = ai::catalog::sync_configured(ai_model_repository.as_ref()).await {
+                tracing::warn!("Failed to sync model catalog: {}", e);
+            }
+        });
+    }
 
     let state = AppState {
         db: pool,
@@ -87,6 +123,9 @@ async fn run() -> Result<()> {
         user_api_key_repository,
         chat_repository,
         user_feature_repository,
+        attachment_repository,
+        tool_registry: Arc::new(ai::tools::ToolRegistry::builtin()),
+        storage: Arc::new(storage::Storage::from_env()?),
     };
     tracing::info!("Database configured successfully.");
 
@@ -163,6 +202,10 @@ fn parse_cli_args() -> Option<u16> {
     None
 }
 
+fn has_cli_flag(flag: &str) -> bool {
+    std::env::args().any(|arg| arg == flag)
+}
+
 // Setup
 fn setup_tracing(name: &str) -> Result<()> {
     // Create a directory for logs if it doesn't exist
@@ -262,6 +305,32 @@ fn setup_router(state: AppState) -> Result<Router> {
         .route("/my", get(api::v1::models::list_my_models))
         .route("/{id}/enable", post(api::v1::models::enable_model))
         .route("/{id}/disable", post(api::v1::models::disable_model))
+        .route(
+            "/{id}/preferences",
+            put(api::v1::models::update_model_preferences),
+        )
+        .route("/{id}/default", post(api::v1::models::set_default_model))
+        .route("/default", delete(api::v1::models::clear_default_model))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::auth::auth_middleware,
+        ));
+
+    let admin_models_routes = Router::new()
+        .route("/", post(api::v1::admin::models::create_model))
+        .route("/sync", post(api::v1::admin::models::sync_models))
+        .route(
+            "/{id}",
+            put(api::v1::admin::models::update_model).delete(api::v1::admin::models::delete_model),
+        )
+        .route(
+            "/{id}/activate",
+            post(api::v1::admin::models::activate_model),
+        )
+        .route(
+            "/{id}/deactivate",
+            post(api::v1::admin::models::deactivate_model),
+        )
         .route_layer(axum::middleware::from_fn_with_state(
             state.clone(),
             middleware::auth::auth_middleware,
@@ -295,8 +364,50 @@ fn setup_router(state: AppState) -> Result<Router> {
         ));
 
     let chat_routes = Router::new()
-        .route("/", post(api::v1::chat::chat))
-        .route("/stream", post(api::v1::chat::stream_chat))
+        .route(
+            "/",
+            post(api::v1::chat::chat)
+                .layer(DefaultBodyLimit::max(api::v1::chat::MAX_REQUEST_BYTES)),
+        )
+        .route(
+            "/stream",
+            post(api::v1::chat::stream_chat)
+                .layer(DefaultBodyLimit::max(api::v1::chat::MAX_REQUEST_BYTES)),
+        )
+        .route(
+            "/estimate",
+            post(api::v1::chat::estimate_chat)
+                .layer(DefaultBodyLimit::max(api::v1::chat::MAX_REQUEST_BYTES)),
+        )
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::auth::auth_middleware,
+        ));
+
+    let images_routes = Router::new()
+        .route("/generations", post(api::v1::images::generate_images))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::auth::auth_middleware,
+        ));
+
+    let attachments_routes = Router::new()
+        .route(
+            "/",
+            post(api::v1::attachments::upload_attachment).layer(DefaultBodyLimit::max(
+                // Room for the multipart framing around the file itself
+                env::get_max_attachment_bytes() + 64 * 1024,
+            )),
+        )
+        .route(
+            "/{id}",
+            get(api::v1::attachments::get_attachment)
+                .delete(api::v1::attachments::delete_attachment),
+        )
+        .route(
+            "/{id}/content",
+            get(api::v1::attachments::download_attachment),
+        )
         .route_layer(axum::middleware::from_fn_with_state(
             state.clone(),
             middleware::auth::auth_middleware,
@@ -308,6 +419,10 @@ fn setup_router(state: AppState) -> Result<Router> {
             get(api::v1::user_api_keys::list_keys).post(api::v1::user_api_keys::create_key),
         )
         .route("/{id}", delete(api::v1::user_api_keys::delete_key))
+        .route(
+            "/{id}/models/sync",
+            post(api::v1::user_api_keys::sync_key_models),
+        )
         .route_layer(axum::middleware::from_fn_with_state(
             state.clone(),
             middleware::auth::auth_middleware,
@@ -334,8 +449,11 @@ fn setup_router(state: AppState) -> Result<Router> {
     let api_router = Router::new()
         .route("/health", get(api::v1::health::health_check))
         .nest("/api/v1/models", models_routes)
+        .nest("/api/v1/admin/models", admin_models_routes)
         .nest("/api/v1/chats", chats_routes)
         .nest("/api/v1/chat", chat_routes)
+        .nest("/api/v1/attachments", attachments_routes)
+        .nest("/api/v1/images", images_routes)
         .nest("/api/v1/user-api-keys", user_api_keys_routes)
         .nest("/api/v1/features", features_routes)
         .nest("/api/v1", user_routes);
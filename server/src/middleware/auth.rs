@@ -58,6 +58,28 @@ where
     }
 }
 
+/// An authenticated user listed in `ADMIN_USER_IDS`. Other users get 403.
+#[derive(Clone)]
+pub struct AdminUser(pub UserModel);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AdminUser
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let AuthenticatedUser(user) = AuthenticatedUser::from_request_parts(parts, state).await?;
+
+        if !crate::env::is_admin_user(&user.id) {
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        Ok(AdminUser(user))
+    }
+}
+
 pub async fn auth_middleware(
     State(state): State<AppState>,
     mut request: Request<Body>,
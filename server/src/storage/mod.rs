@@ -0,0 +1,63 @@
+//! Where uploaded files live. Rows in `attachments` only hold a storage key; the bytes
+//! go through a [`StorageBackend`], picked at startup from `STORAGE_BACKEND`.
+
+mod local;
+mod s3;
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+
+use crate::env;
+
+pub use local::LocalStorage;
+pub use s3::S3Storage;
+
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// Stores `bytes` under `key`, replacing anything already there.
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<()>;
+    async fn get(&self, key: &str) -> Result<Vec<u8>>;
+    /// Removes `key`; deleting a key that does not exist is not an error.
+    async fn delete(&self, key: &str) -> Result<()>;
+}
+
+/// The configured backend. An enum rather than a trait object so it can sit in the app
+/// state as a concrete type.
+pub enum Storage {
+    Local(LocalStorage),
+    S3(S3Storage),
+}
+
+impl Storage {
+    pub fn from_env() -> Result<Self> {
+        match env::get_storage_backend().as_str() {
+            "local" => Ok(Self::Local(
+                LocalStorage::new(env::get_storage_local_path()),
+            )),
+            "s3" => Ok(Self::S3(S3Storage::from_env()?)),
+            other => bail!("Unknown STORAGE_BACKEND `{}`; expected local or s3", other),
+        }
+    }
+
+    fn backend(&self) -> &dyn StorageBackend {
+        match self {
+            Self::Local(storage) => storage,
+            Self::S3(storage) => storage,
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for Storage {
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<()> {
+        self.backend().put(key, bytes, content_type).await
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        self.backend().get(key).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.backend().delete(key).await
+    }
+}
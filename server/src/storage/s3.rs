@@ -0,0 +1,62 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use s3::{Bucket, Region, creds::Credentials};
+
+use super::StorageBackend;
+use crate::env;
+
+/// Keeps files in an S3-compatible bucket. Setting `S3_ENDPOINT` points it at anything
+/// that speaks the S3 API, such as a local MinIO, using path-style addressing.
+pub struct S3Storage {
+    bucket: Box<Bucket>,
+}
+
+impl S3Storage {
+    pub fn from_env() -> Result<Self> {
+        let name = env::get_s3_bucket().context("S3_BUCKET is not set")?;
+        let region_name = env::get_s3_region();
+        let region = match env::get_s3_endpoint() {
+            Some(endpoint) => Region::Custom {
+                region: region_name,
+                endpoint,
+            },
+            None => region_name.parse()?,
+        };
+        // Without explicit keys the usual AWS environment and profile lookup applies
+        let credentials = Credentials::new(
+            env::get_s3_access_key_id().as_deref(),
+            env::get_s3_secret_access_key().as_deref(),
+            None,
+            None,
+            None,
+        )?;
+
+        let mut bucket = Bucket::new(&name, region.clone(), credentials)?;
+        if matches!(region, Region::Custom { .. }) {
+            bucket = bucket.with_path_style();
+        }
+
+        Ok(Self { bucket })
+    }
+}
+
+#[async_trait]
+impl StorageBackend for S3Storage {
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<()> {
+        self.bucket
+            .put_object_with_content_type(key, &bytes, content_type)
+            .await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let response = self.bucket.get_object(key).await?;
+        Ok(response.bytes().to_vec())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        // S3 answers a delete of a missing key with success as well
+        self.bucket.delete_object(key).await?;
+        Ok(())
+    }
+}
@@ -1,13 +1,20 @@
 import type { AIModel } from '@/types/model';
 
+const perMillion = (cost: number) => (Number(cost) * 1_000_000).toFixed(2);
+
 export function ModelInfo({ model }: { model: AIModel }) {
   return (
     <div className="text-sm text-muted-foreground">
       {model.description && <p>{model.description}</p>}
       <div className="mt-2 space-y-1">
         <div>Context Window: {model.context_window.toLocaleString()} tokens</div>
-        {model.cost_per_token && (
-          <div>Cost: ${model.cost_per_token} per token</div>
+        {model.input_cost_per_token != null && model.output_cost_per_token != null ? (
+          <div>
+            Cost: ${perMillion(model.input_cost_per_token)} input / $
+            {perMillion(model.output_cost_per_token)} output per 1M tokens
+          </div>
+        ) : (
+          model.cost_per_token && <div>Cost: ${model.cost_per_token} per token</div>
         )}
         <div className="flex gap-2 mt-2">
           {model.supports_streaming && (
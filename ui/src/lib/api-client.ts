@@ -225,17 +225,22 @@ export class ApiClient {
         if (!response.ok) {
             const contentType = response.headers.get("content-type") || "";
             let message = response.statusText || `HTTP ${response.status}`;
+            let code: string | undefined;
             if (contentType.includes("application/json")) {
                 try {
                     const data = await response.json();
                     if (typeof data === "object" && data && ("message" in data || "error" in data)) {
                         message = String((data as any).message ?? (data as any).error);
                     }
+                    if (typeof data?.code === "string") {
+                        code = data.code;
+                    }
                 } catch {}
             }
             const apiError = new Error(message) as ApiClientError;
             apiError.name = "ApiClientError";
             apiError.status = response.status;
+            apiError.code = code;
             throw apiError;
         }
 
@@ -86,25 +86,43 @@ export class T3ChatClient extends ApiClient {
 
         const reader = response.body.getReader();
         const decoder = new TextDecoder();
+        let buffer = "";
+        let event = "message";
+        let dataLines: string[] = [];
 
         try {
             while (true) {
                 const { done, value } = await reader.read();
                 if (done) break;
 
-                const chunk = decoder.decode(value, { stream: true });
-                const lines = chunk.split("\n").filter((line) => line.trim());
-
-                for (const line of lines) {
-                    if (line.startsWith("data: ")) {
-                        const data = line.slice(6);
-                        if (data === "[DONE]") return;
-                        try {
-                            const parsed = JSON.parse(data);
-                            yield parsed.content || "";
-                        } catch {
-                            // Skip invalid JSON
+                buffer += decoder.decode(value, { stream: true });
+                const lines = buffer.split("\n");
+                // Keep the trailing partial line for the next read
+                buffer = lines.pop() ?? "";
+
+                for (const rawLine of lines) {
+                    const line = rawLine.endsWith("\r") ? rawLine.slice(0, -1) : rawLine;
+
+                    if (line === "") {
+                        // Blank line dispatches the event collected so far
+                        if (dataLines.length > 0) {
+                            const payload = JSON.parse(dataLines.join("\n"));
+                            if (event === "delta") {
+                                yield payload.content || "";
+                            } else if (event === "error") {
+                                const streamError = new Error(payload.message || "Streaming failed") as ApiClientError;
+                                streamError.code = payload.code;
+                                throw streamError;
+                            } else if (event === "done") {
+                                return;
+                            }
                         }
+                        event = "message";
+                        dataLines = [];
+                    } else if (line.startsWith("event:")) {
+                        event = line.slice(6).trim();
+                    } else if (line.startsWith("data:")) {
+                        dataLines.push(line.slice(5).trimStart());
                     }
                 }
             }
@@ -1,38 +1,107 @@
-import type { AiProvider } from "./chat";
+import type { AiProvider, Attachment, ToolCall, ToolChoice, ToolDefinition } from "./chat";
 
 export interface CreateChatRequest {
-    model_provider: AiProvider;
-    model_id: string;
+    /** Omit together with model_id to use the user's default model */
+    model_provider?: AiProvider;
+    model_id?: string;
     title?: string;
+    fallback_models?: string[];
 }
 
 export interface CreateMessageRequest {
     content: string;
-    role?: "user" | "assistant" | "system";
+    role?: "user" | "assistant" | "system" | "tool";
+    /** Required for tool messages */
+    tool_call_id?: string;
 }
 
 export interface ChatRequest {
     chat_id: string;
+    /** May be empty to continue after posting tool results */
     message: string;
+    /** Images as base64 `data:` URLs (PNG, JPEG, GIF or WebP, up to 20 MB each) */
+    images?: string[];
+    /** Ids of files uploaded to /api/v1/attachments */
+    attachment_ids?: string[];
     model_provider: AiProvider;
     model_id: string;
     temperature?: number;
     max_tokens?: number;
     stream?: boolean;
+    tools?: ToolDefinition[];
+    tool_choice?: ToolChoice;
+    /** Let the server run its built-in tools */
+    server_tools?: boolean;
 }
 
 export interface ChatResponse {
     content: string;
+    tool_calls: ToolCall[];
+    provider: AiProvider;
     model: string;
     tokens_used?: number;
+    usage?: TokenUsage | null;
+    /** What the answer cost at the model's catalog prices, as a decimal string */
+    cost?: string | null;
     finish_reason?: string;
 }
 
+export interface TokenUsage {
+    /** Includes cached_tokens */
+    prompt_tokens: number;
+    /** Includes reasoning_tokens */
+    completion_tokens: number;
+    cached_tokens?: number | null;
+    reasoning_tokens?: number | null;
+}
+
+export interface ChatEstimateResponse {
+    /** Tokens that would be sent, after older messages are left out */
+    prompt_tokens: number;
+    /** Tokens of the whole chat plus the new message */
+    history_tokens: number;
+    /** Context window minus max_tokens; null when unknown */
+    budget: number | null;
+    context_window: number | null;
+    max_tokens: number | null;
+    messages: number;
+    dropped_messages: number;
+    /** False when the request would be rejected as too long */
+    fits: boolean;
+    /** False when counted with an approximation of the provider's tokenizer */
+    exact: boolean;
+}
+
+export interface ImageGenerationRequest {
+    chat_id: string;
+    prompt: string;
+    model_provider: "openai" | "google";
+    /** Defaults to gpt-image-1 or imagen-3.0-generate-002 */
+    model_id?: string;
+    /** `WIDTHxHEIGHT`, e.g. 1024x1024 */
+    size?: string;
+    /** 1 to 4 */
+    n?: number;
+}
+
+export interface ImageGenerationResponse {
+    user_message_id: string;
+    message_id: string;
+    provider: AiProvider;
+    model: string;
+    revised_prompt?: string | null;
+    images: Attachment[];
+}
+
 export interface UserApiKey {
     id: string;
     user_id: string;
     provider: AiProvider;
     is_default: boolean;
+    base_url?: string | null;
+    extra_headers?: Record<string, string> | null;
+    /** Models served through this key */
+    models?: string[] | null;
     created_at: string;
     updated_at: string;
 }
@@ -41,4 +110,8 @@ export interface CreateUserApiKeyRequest {
     provider: AiProvider;
     api_key: string;
     is_default?: boolean;
+    /** Must be a public host unless the server allows it */
+    base_url?: string;
+    extra_headers?: Record<string, string>;
+    models?: string[];
 }
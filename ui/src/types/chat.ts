@@ -4,6 +4,10 @@ export interface Chat {
   title: string;
   model_provider: AiProvider;
   model_id: string;
+  fallback_models?: string[] | null;
+  /** Sent to the model in place of the messages up to summarized_through */
+  summary?: string | null;
+  summarized_through?: number | null;
   created_at: string;
   updated_at: string;
   deleted_at?: string;
@@ -12,19 +16,75 @@ export interface Chat {
 export interface Message {
   id: string;
   chat_id: string;
-  role: 'user' | 'assistant' | 'system';
+  role: 'user' | 'assistant' | 'system' | 'tool';
   content: string;
   metadata?: Record<string, unknown>;
   parent_message_id?: string;
   sequence_number: number;
   created_at: string;
   tokens_used?: number;
+  prompt_tokens?: number | null;
+  completion_tokens?: number | null;
+  /** Part of prompt_tokens, read from the provider's prompt cache */
+  cached_tokens?: number | null;
+  /** Part of completion_tokens, spent reasoning */
+  reasoning_tokens?: number | null;
+  /** What the answer cost at the model's catalog prices, as a decimal string */
+  cost?: string | null;
   model_used?: string;
+  tool_calls?: ToolCall[] | null;
+  tool_call_id?: string | null;
+  /** The full content; `content` is its text */
+  content_parts?: ContentPart[];
+  /** Always sent to the model, however long the chat grows */
+  is_pinned?: boolean;
+}
+
+export type ContentPart =
+  | { type: 'text'; text: string }
+  | { type: 'image'; url: string; mime_type?: string; attachment_id?: string }
+  | { type: 'file'; name: string; mime_type?: string; url?: string; attachment_id?: string }
+  | ({ type: 'tool_call' } & ToolCall)
+  | { type: 'tool_result'; tool_call_id: string; content: string; is_error: boolean }
+  | { type: 'reasoning'; text: string };
+
+export interface ToolCall {
+  id: string;
+  name: string;
+  arguments: unknown;
+}
+
+export interface ToolDefinition {
+  name: string;
+  description?: string;
+  /** JSON Schema of the arguments */
+  parameters?: Record<string, unknown>;
+}
+
+export type ToolChoice =
+  | { type: 'auto' }
+  | { type: 'none' }
+  | { type: 'required' }
+  | { type: 'tool'; name: string };
+
+export interface Attachment {
+  id: string;
+  chat_id?: string | null;
+  /** Set once the attachment was sent with a message */
+  message_id?: string | null;
+  file_name: string;
+  mime_type: string;
+  size_bytes: number;
+  /** Whether text was extracted for models that cannot read the file */
+  has_text: boolean;
+  /** Authorized download endpoint; send the bearer token */
+  url: string;
+  created_at: string;
 }
 
 export interface ChatWithMessages extends Chat {
   messages: Message[];
 }
 
-export type AiProvider = 'openai' | 'anthropic' | 'google' | 'deepseek' | 'ollama';
+export type AiProvider = 'openai' | 'anthropic' | 'google' | 'deepseek' | 'ollama' | 'openai_compatible';
 
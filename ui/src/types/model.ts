@@ -11,8 +11,18 @@ export interface AIModel {
   supports_images: boolean;
   supports_functions: boolean;
   cost_per_token?: number;
+  input_cost_per_token?: number;
+  output_cost_per_token?: number;
+  cached_input_cost_per_token?: number;
+  default_parameters?: Record<string, unknown>;
   is_active: boolean;
   created_at: string;
   updated_at: string;
 }
 
+/** A model from /v1/models/my with the user's preferences */
+export interface UserModel extends AIModel {
+  is_favorite: boolean;
+  sort_order?: number | null;
+  is_default: boolean;
+}